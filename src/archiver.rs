@@ -1,12 +1,17 @@
 use crate::processor::{
-    PgDatabaseXidLimits, PgStatActivity, PgStatBgWriterSum, PgStatDatabaseSum, PgStatWalSum,
+    CollectionGap, CollectorStatistics, ConnectionChurn, PgDatabaseXidLimits, PgLockEdge,
+    PgReplicationSlots, PgSettings, PgSettingsChange, PgStatActivity, PgStatBgWriterSum,
+    PgStatCheckpointerSum, PgStatDatabaseSum, PgStatDatabaseSumPerDatabase, PgStatIoSum,
+    PgStatProgress, PgStatReplication, PgStatStatementsQueryid, PgStatUserIndexesSum,
+    PgStatUserTablesSum, PgStatWalReceiver, PgStatWalSum, PgXminHorizon, ProcDiskstatsSum,
+    ProcLoadavg, ProcMeminfo, ProcStatSum, ProcVmstatSum,
 };
-use crate::{DataTransit, ARGS, DATA};
+use crate::{current_cluster, DataTransit, ARGS, CLUSTER, CLUSTERS, DATA};
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, DurationRound, Local};
 use log::debug;
-use std::{env::current_dir, fs::write};
+use std::{env::current_dir, fs::write, path::Path};
 use tokio::time::{interval, Duration, MissedTickBehavior};
 
 pub async fn archiver_main() -> Result<()> {
@@ -25,9 +30,11 @@ pub async fn archiver_main() -> Result<()> {
     loop {
         interval.tick().await;
         if Local::now() > high_time {
-            match save_to_disk(high_time, true).await {
-                Ok(_) => {}
-                Err(error) => return Err(error),
+            for cluster in 0..CLUSTERS.len() {
+                match CLUSTER.scope(cluster, save_to_disk(high_time, true)).await {
+                    Ok(_) => {}
+                    Err(error) => return Err(error),
+                }
             }
             high_time += chrono::Duration::minutes(ARGS.archiver_interval)
         };
@@ -50,7 +57,7 @@ pub async fn save_to_disk(high_time: DateTime<Local>, interval_completed: bool)
     );
 
     macro_rules! generate_transition_collections {
-        ($([$category:ident, $struct:ident]),*) => {
+        ($([$category:ident, $struct:ty]),*) => {
             $(
             transition.$category = DATA
                 .$category
@@ -77,12 +84,53 @@ pub async fn save_to_disk(high_time: DateTime<Local>, interval_completed: bool)
         [pg_stat_database_sum, PgStatDatabaseSum],
         [pg_stat_bgwriter_sum, PgStatBgWriterSum],
         [pg_stat_wal_sum, PgStatWalSum],
-        [pg_database_xid_limits, PgDatabaseXidLimits]
+        [pg_database_xid_limits, PgDatabaseXidLimits],
+        [pg_stat_statements, Vec<PgStatStatementsQueryid>],
+        [pg_stat_checkpointer_sum, PgStatCheckpointerSum],
+        [pg_stat_io_sum, Vec<PgStatIoSum>],
+        [pg_lock_edges, Vec<PgLockEdge>],
+        [pg_stat_replication, Vec<PgStatReplication>],
+        [pg_replication_slots, Vec<PgReplicationSlots>],
+        [pg_stat_wal_receiver, PgStatWalReceiver],
+        [pg_stat_progress, Vec<PgStatProgress>],
+        [pg_stat_user_tables_sum, Vec<PgStatUserTablesSum>],
+        [pg_stat_user_indexes_sum, Vec<PgStatUserIndexesSum>],
+        [pg_stat_database_sum_per_database, PgStatDatabaseSumPerDatabase],
+        [proc_stat_sum, ProcStatSum],
+        [proc_loadavg, ProcLoadavg],
+        [proc_meminfo, ProcMeminfo],
+        [proc_vmstat_sum, ProcVmstatSum],
+        [proc_diskstats_sum, Vec<ProcDiskstatsSum>],
+        [collection_gaps, CollectionGap],
+        [collector_statistics, Vec<CollectorStatistics>],
+        [pg_settings_changes, Vec<PgSettingsChange>],
+        [pg_xmin_horizon, PgXminHorizon],
+        [connection_churn, Vec<ConnectionChurn>]
     );
+    // the settings are only added when they change, so an archive without a change gets the
+    // settings that were in effect at its start, to have the settings in every archive.
+    {
+        let pg_settings = DATA.pg_settings.read().await;
+        transition.pg_settings = pg_settings
+            .iter()
+            .filter(|(ts, _)| *ts > low_time && *ts <= high_time)
+            .cloned()
+            .collect::<Vec<(DateTime<Local>, Vec<PgSettings>)>>();
+        if transition.pg_settings.is_empty() {
+            transition.pg_settings = pg_settings
+                .iter()
+                .rev()
+                .find(|(ts, _)| *ts <= low_time)
+                .cloned()
+                .into_iter()
+                .collect();
+        }
+    }
 
     let current_directory = current_dir()?;
     let filename = current_directory.join(format!(
-        "pas_{}-{}-{}T{}-{}.json",
+        "pas_{}_{}-{}-{}T{}-{}.json",
+        CLUSTERS[current_cluster()].name,
         high_time.format("%Y"),
         high_time.format("%m"),
         high_time.format("%d"),
@@ -100,3 +148,14 @@ pub async fn save_to_disk(high_time: DateTime<Local>, interval_completed: bool)
 
     Ok(())
 }
+
+// the cluster name is between "pas_" and the timestamp in the filename. archives that were
+// written before the cluster name was added to the filename get the cluster name "default".
+pub fn cluster_name_from_filename(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .and_then(|r| r.to_str())
+        .and_then(|r| r.strip_prefix("pas_"))
+        .and_then(|r| r.rsplit_once('_'))
+        .map_or("default".to_string(), |(name, _)| name.to_string())
+}
@@ -5,7 +5,9 @@ use once_cell::sync::Lazy;
 //use std::sync::RwLock;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use sqlx::postgres::PgConnectOptions;
+use std::{ops::Deref, str::FromStr};
+use tokio::sync::{watch, RwLock};
 
 pub mod archiver;
 pub mod processor;
@@ -13,11 +15,33 @@ pub mod reader;
 pub mod webserver;
 
 use processor::{
+    CollectionGap,
+    CollectorStatistics,
+    ConnectionChurn,
     PgDatabaseXidLimits,
+    PgLockEdge,
+    PgReplicationSlots,
+    PgSettings,
+    PgSettingsChange,
     PgStatActivity,
     PgStatBgWriterSum,
+    PgStatCheckpointerSum,
     PgStatDatabaseSum,
+    PgStatDatabaseSumPerDatabase,
+    PgStatIoSum,
+    PgStatProgress,
+    PgStatReplication,
+    PgStatStatementsQueryid,
+    PgStatUserIndexesSum,
+    PgStatUserTablesSum,
+    PgStatWalReceiver,
     PgStatWalSum,
+    PgXminHorizon,
+    ProcDiskstatsSum,
+    ProcLoadavg,
+    ProcMeminfo,
+    ProcStatSum,
+    ProcVmstatSum,
     //PgWaitTypeActivity, PgWaitTypeBufferPin, PgWaitTypeClient, PgWaitTypeExtension, PgWaitTypeIO,
     //PgWaitTypeIPC,
     //PgWaitTypeLWLock,
@@ -42,6 +66,18 @@ pub struct Opts {
     /// Interval
     #[arg(short = 'i', long, value_name = "time (s)", default_value = "1")]
     pub interval: u64,
+    /// pg_stat_activity sampling interval
+    #[arg(
+        short = 'a',
+        long,
+        value_name = "time (ms)",
+        default_value = "1000",
+        value_parser = clap::value_parser!(u64).range(10..)
+    )]
+    pub ash_interval: u64,
+    /// pg_settings and pg_database interval
+    #[arg(short = 's', long, value_name = "time (s)", default_value = "60")]
+    pub settings_interval: u64,
     /// History
     #[arg(
         short = 'n',
@@ -88,20 +124,117 @@ pub struct Opts {
         default_value = "1000"
     )]
     pub graph_height: u32,
+    /// Number of relations and indexes kept per interval
+    #[arg(short = 't', long, value_name = "nr relations", default_value = "10")]
+    pub top_relations: usize,
     /// Read history file(s), don't do active fetching
     #[arg(short = 'r', long, value_name = "read archives", num_args(1..))]
     pub read: Option<Vec<String>>,
-    /// Connection specification
+    /// Connection specification, multiple connection strings monitor multiple clusters
     #[arg(
         short = 'c',
         long,
         value_name = "connection string",
-        default_value = "postgres:///"
+        default_value = "postgres:///",
+        num_args(1..)
     )]
-    pub connection_string: String,
+    pub connection_string: Vec<String>,
+    /// Cluster name per connection string, default host-port
+    #[arg(short = 'C', long, value_name = "cluster name", num_args(1..))]
+    pub cluster_name: Option<Vec<String>>,
 }
 
 pub static ARGS: Lazy<Opts> = Lazy::new(Opts::parse);
+pub static CLUSTERS: Lazy<Vec<Cluster>> = Lazy::new(|| ARGS.clusters());
+
+// every cluster has its own statistics. DATA (and DELTATABLE in the processor) refer to the cluster
+// of the current task, which is set with CLUSTER.scope(), outside of a scope it is the first
+// cluster.
+tokio::task_local! {
+    pub static CLUSTER: usize;
+}
+
+pub fn current_cluster() -> usize {
+    CLUSTER.try_with(|cluster| *cluster).unwrap_or(0)
+}
+
+pub struct PerCluster<T>(pub Vec<T>);
+
+impl<T> Deref for PerCluster<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0[current_cluster()]
+    }
+}
+
+// a monitored cluster. the name is shown in the webserver and is part of the archive filenames.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub name: String,
+    pub connection_string: String,
+}
+
+impl Opts {
+    // the clusters are taken from the connection strings, or from the cluster names in the archive
+    // filenames when archives are read.
+    pub fn clusters(&self) -> Vec<Cluster> {
+        let mut clusters: Vec<Cluster> = Vec::new();
+        let names: Vec<(String, String)> = match &self.read {
+            Some(files) => files
+                .iter()
+                .map(|file| (archiver::cluster_name_from_filename(file), String::new()))
+                .collect(),
+            None => self
+                .connection_string
+                .iter()
+                .enumerate()
+                .map(|(index, connection_string)| {
+                    let name = match self.cluster_name.as_ref().and_then(|r| r.get(index)) {
+                        Some(name) => name.clone(),
+                        None => PgConnectOptions::from_str(connection_string)
+                            .map(|r| format!("{}-{}", r.get_host(), r.get_port()))
+                            .unwrap_or_else(|_| format!("cluster{}", index)),
+                    };
+                    (name, connection_string.clone())
+                })
+                .collect(),
+        };
+        for (name, connection_string) in names {
+            // the name is used in filenames
+            let name = name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect::<String>()
+                .trim_matches('-')
+                .to_string();
+            if self.read.is_some() && clusters.iter().any(|r| r.name == name) {
+                continue;
+            }
+            let name = if clusters.iter().any(|r| r.name == name) {
+                format!("{}-{}", name, clusters.len())
+            } else {
+                name
+            };
+            clusters.push(Cluster {
+                name,
+                connection_string,
+            });
+        }
+        clusters
+    }
+    // pg_stat_activity can be sampled more often than the other statistics, the number of samples
+    // it keeps is raised so it covers the same time as history.
+    pub fn ash_history(&self) -> usize {
+        self.history * (self.interval * 1000 / self.ash_interval).max(1) as usize
+    }
+}
 
 #[derive(Debug)]
 pub struct Data {
@@ -110,27 +243,111 @@ pub struct Data {
     pub pg_stat_bgwriter_sum: RwLock<BoundedVecDeque<(DateTime<Local>, PgStatBgWriterSum)>>,
     pub pg_stat_wal_sum: RwLock<BoundedVecDeque<(DateTime<Local>, PgStatWalSum)>>,
     pub pg_database_xid_limits: RwLock<BoundedVecDeque<(DateTime<Local>, PgDatabaseXidLimits)>>,
+    pub pg_stat_statements:
+        RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatStatementsQueryid>)>>,
+    pub pg_stat_checkpointer_sum: RwLock<BoundedVecDeque<(DateTime<Local>, PgStatCheckpointerSum)>>,
+    pub pg_stat_io_sum: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatIoSum>)>>,
+    pub pg_lock_edges: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgLockEdge>)>>,
+    pub pg_stat_replication: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatReplication>)>>,
+    pub pg_replication_slots: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgReplicationSlots>)>>,
+    pub pg_stat_wal_receiver: RwLock<BoundedVecDeque<(DateTime<Local>, PgStatWalReceiver)>>,
+    pub pg_stat_progress: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatProgress>)>>,
+    pub pg_stat_user_tables_sum:
+        RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatUserTablesSum>)>>,
+    pub pg_stat_user_indexes_sum:
+        RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgStatUserIndexesSum>)>>,
+    pub pg_stat_database_sum_per_database:
+        RwLock<BoundedVecDeque<(DateTime<Local>, PgStatDatabaseSumPerDatabase)>>,
+    pub proc_stat_sum: RwLock<BoundedVecDeque<(DateTime<Local>, ProcStatSum)>>,
+    pub proc_loadavg: RwLock<BoundedVecDeque<(DateTime<Local>, ProcLoadavg)>>,
+    pub proc_meminfo: RwLock<BoundedVecDeque<(DateTime<Local>, ProcMeminfo)>>,
+    pub proc_vmstat_sum: RwLock<BoundedVecDeque<(DateTime<Local>, ProcVmstatSum)>>,
+    pub proc_diskstats_sum: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<ProcDiskstatsSum>)>>,
+    pub collection_gaps: RwLock<BoundedVecDeque<(DateTime<Local>, CollectionGap)>>,
+    pub collector_statistics: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<CollectorStatistics>)>>,
+    pub pg_settings: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgSettings>)>>,
+    pub pg_settings_changes: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<PgSettingsChange>)>>,
+    pub pg_xmin_horizon: RwLock<BoundedVecDeque<(DateTime<Local>, PgXminHorizon)>>,
+    pub connection_churn: RwLock<BoundedVecDeque<(DateTime<Local>, Vec<ConnectionChurn>)>>,
+    // the timestamp of the last pg_stat_activity sample, which is sent after the sample is added,
+    // so the live ash chart does not have to poll for new samples.
+    pub pg_stat_activity_added: watch::Sender<DateTime<Local>>,
 }
 
 impl Data {
-    pub fn new(history: usize) -> Data {
+    pub fn new(history: usize, ash_history: usize) -> Data {
         Data {
-            pg_stat_activity: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_activity: RwLock::new(BoundedVecDeque::new(ash_history)),
             pg_stat_database_sum: RwLock::new(BoundedVecDeque::new(history)),
             pg_stat_bgwriter_sum: RwLock::new(BoundedVecDeque::new(history)),
             pg_stat_wal_sum: RwLock::new(BoundedVecDeque::new(history)),
             pg_database_xid_limits: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_statements: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_checkpointer_sum: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_io_sum: RwLock::new(BoundedVecDeque::new(history)),
+            pg_lock_edges: RwLock::new(BoundedVecDeque::new(ash_history)),
+            pg_stat_replication: RwLock::new(BoundedVecDeque::new(history)),
+            pg_replication_slots: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_wal_receiver: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_progress: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_user_tables_sum: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_user_indexes_sum: RwLock::new(BoundedVecDeque::new(history)),
+            pg_stat_database_sum_per_database: RwLock::new(BoundedVecDeque::new(history)),
+            proc_stat_sum: RwLock::new(BoundedVecDeque::new(history)),
+            proc_loadavg: RwLock::new(BoundedVecDeque::new(history)),
+            proc_meminfo: RwLock::new(BoundedVecDeque::new(history)),
+            proc_vmstat_sum: RwLock::new(BoundedVecDeque::new(history)),
+            proc_diskstats_sum: RwLock::new(BoundedVecDeque::new(history)),
+            collection_gaps: RwLock::new(BoundedVecDeque::new(history)),
+            collector_statistics: RwLock::new(BoundedVecDeque::new(history)),
+            pg_settings: RwLock::new(BoundedVecDeque::new(history)),
+            pg_settings_changes: RwLock::new(BoundedVecDeque::new(history)),
+            pg_xmin_horizon: RwLock::new(BoundedVecDeque::new(history)),
+            connection_churn: RwLock::new(BoundedVecDeque::new(ash_history)),
+            pg_stat_activity_added: watch::Sender::new(DateTime::default()),
         }
     }
 }
 
+// serde(default) allows reading archives that were written before a category was added.
 #[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct DataTransit {
     pub pg_stat_activity: Vec<(DateTime<Local>, Vec<PgStatActivity>)>,
     pub pg_stat_database_sum: Vec<(DateTime<Local>, PgStatDatabaseSum)>,
     pub pg_stat_bgwriter_sum: Vec<(DateTime<Local>, PgStatBgWriterSum)>,
     pub pg_stat_wal_sum: Vec<(DateTime<Local>, PgStatWalSum)>,
     pub pg_database_xid_limits: Vec<(DateTime<Local>, PgDatabaseXidLimits)>,
+    pub pg_stat_statements: Vec<(DateTime<Local>, Vec<PgStatStatementsQueryid>)>,
+    pub pg_stat_checkpointer_sum: Vec<(DateTime<Local>, PgStatCheckpointerSum)>,
+    pub pg_stat_io_sum: Vec<(DateTime<Local>, Vec<PgStatIoSum>)>,
+    pub pg_lock_edges: Vec<(DateTime<Local>, Vec<PgLockEdge>)>,
+    pub pg_stat_replication: Vec<(DateTime<Local>, Vec<PgStatReplication>)>,
+    pub pg_replication_slots: Vec<(DateTime<Local>, Vec<PgReplicationSlots>)>,
+    pub pg_stat_wal_receiver: Vec<(DateTime<Local>, PgStatWalReceiver)>,
+    pub pg_stat_progress: Vec<(DateTime<Local>, Vec<PgStatProgress>)>,
+    pub pg_stat_user_tables_sum: Vec<(DateTime<Local>, Vec<PgStatUserTablesSum>)>,
+    pub pg_stat_user_indexes_sum: Vec<(DateTime<Local>, Vec<PgStatUserIndexesSum>)>,
+    pub pg_stat_database_sum_per_database: Vec<(DateTime<Local>, PgStatDatabaseSumPerDatabase)>,
+    pub proc_stat_sum: Vec<(DateTime<Local>, ProcStatSum)>,
+    pub proc_loadavg: Vec<(DateTime<Local>, ProcLoadavg)>,
+    pub proc_meminfo: Vec<(DateTime<Local>, ProcMeminfo)>,
+    pub proc_vmstat_sum: Vec<(DateTime<Local>, ProcVmstatSum)>,
+    pub proc_diskstats_sum: Vec<(DateTime<Local>, Vec<ProcDiskstatsSum>)>,
+    pub collection_gaps: Vec<(DateTime<Local>, CollectionGap)>,
+    pub collector_statistics: Vec<(DateTime<Local>, Vec<CollectorStatistics>)>,
+    pub pg_settings: Vec<(DateTime<Local>, Vec<PgSettings>)>,
+    pub pg_settings_changes: Vec<(DateTime<Local>, Vec<PgSettingsChange>)>,
+    pub pg_xmin_horizon: Vec<(DateTime<Local>, PgXminHorizon)>,
+    pub connection_churn: Vec<(DateTime<Local>, Vec<ConnectionChurn>)>,
 }
 
-pub static DATA: Lazy<Data> = Lazy::new(|| Data::new(Opts::parse().history));
+pub static DATA: Lazy<PerCluster<Data>> = Lazy::new(|| {
+    let opts = Opts::parse();
+    PerCluster(
+        opts.clusters()
+            .iter()
+            .map(|_| Data::new(opts.history, opts.ash_history()))
+            .collect(),
+    )
+});
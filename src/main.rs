@@ -1,7 +1,7 @@
 use anyhow::Result;
 use chrono::Local;
 use futures::executor::block_on;
-use pas::ARGS;
+use pas::{ARGS, CLUSTER, CLUSTERS};
 use std::time::Duration;
 
 /*
@@ -139,11 +139,13 @@ async fn main() -> Result<()> {
         println!("SIGINT received, terminating.");
         let mut return_value = 0;
         if ARGS.archiver {
-            match block_on(save_to_disk(Local::now(), false)) {
-                Ok(_) => {}
-                Err(error) => {
-                    return_value = 1;
-                    eprintln!("{:?}", error);
+            for cluster in 0..CLUSTERS.len() {
+                match block_on(CLUSTER.scope(cluster, save_to_disk(Local::now(), false))) {
+                    Ok(_) => {}
+                    Err(error) => {
+                        return_value = 1;
+                        eprintln!("{:?}", error);
+                    }
                 }
             }
         }
@@ -152,16 +154,19 @@ async fn main() -> Result<()> {
     .unwrap();
 
     if ARGS.read.is_none() {
-        tokio::spawn(async move {
-            match processor_main().await {
-                Ok(_) => {}
-                Err(error) => {
-                    eprintln!("{:?}", error);
-                    process::exit(1);
+        // every cluster gets its own processor
+        for cluster in 0..CLUSTERS.len() {
+            tokio::spawn(CLUSTER.scope(cluster, async move {
+                match processor_main().await {
+                    Ok(_) => {}
+                    Err(error) => {
+                        eprintln!("{:?}", error);
+                        process::exit(1);
+                    }
                 }
-            }
-            println!("processor done.");
-        });
+                println!("processor done.");
+            }));
+        }
     };
 
     if ARGS.webserver || ARGS.read.is_some() {
@@ -0,0 +1,140 @@
+use crate::processor::Collector;
+use crate::DATA;
+
+use chrono::{DateTime, Local};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// the longest time to wait before a failed collection is tried again.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// missed ticks are only recorded as a gap when no collection was done for this long, so a slow
+// pg_stat_activity sample at a short ash interval does not show up as a gap.
+const MIN_MISSED_GAP: Duration = Duration::from_secs(2);
+
+// the schedules the statistics are collected on, see processor_main.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    Activity,
+    #[default]
+    Statistics,
+    Settings,
+    Os,
+}
+
+impl Schedule {
+    // the name of the schedule in the collector statistics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Schedule::Activity => "activity schedule",
+            Schedule::Statistics => "statistics schedule",
+            Schedule::Settings => "settings schedule",
+            Schedule::Os => "os schedule",
+        }
+    }
+}
+
+// a period in which a schedule did not collect statistics, because the collection failed (for
+// example during a restart of the database) or because the ticks were missed. the gap starts at
+// the last collection before it and ends at the first collection after it.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CollectionGap {
+    pub schedule: Schedule,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub failed_ticks: u64,
+}
+
+// the state of a schedule, to record its gaps in DATA.collection_gaps. a gap of failed
+// collections is kept here until the collection resumes, and is then stored once, with its end
+// time as timestamp, so the timestamps stay in order and a gap is saved in a single archive.
+pub struct ScheduleTracker {
+    schedule: Schedule,
+    period: Duration,
+    last_collected: Option<DateTime<Local>>,
+    last_tick: Option<Instant>,
+    failures: u32,
+    ongoing_gap: Option<CollectionGap>,
+}
+
+impl ScheduleTracker {
+    pub fn new(schedule: Schedule, period: Duration) -> ScheduleTracker {
+        ScheduleTracker {
+            schedule,
+            period,
+            last_collected: None,
+            last_tick: None,
+            failures: 0,
+            ongoing_gap: None,
+        }
+    }
+    // count the ticks that were skipped since the previous tick, because the collection took
+    // longer than the period. ticks that are skipped during a backoff are not counted.
+    pub fn ticked(&mut self, tick: Instant) {
+        if let Some(last_tick) = self.last_tick {
+            if self.failures == 0 {
+                let missed_ticks = (tick - last_tick).as_nanos() / self.period.as_nanos().max(1);
+                if missed_ticks > 1 {
+                    Collector::add_missed_ticks(self.schedule.name(), missed_ticks as u64 - 1);
+                }
+            }
+        }
+        self.last_tick = Some(tick);
+    }
+    pub async fn record(&mut self, collected: bool) {
+        let timestamp = Local::now();
+
+        if collected {
+            if let Some(mut gap) = self.ongoing_gap.take() {
+                gap.end_time = timestamp;
+                info!(
+                    "{:?} collection resumed after {} failed ticks",
+                    self.schedule, gap.failed_ticks
+                );
+                DATA.collection_gaps
+                    .write()
+                    .await
+                    .push_back((timestamp, gap));
+            } else if let Some(last_collected) = self.last_collected {
+                // a tick is missed when a collection takes longer than the period, with
+                // MissedTickBehavior::Skip the next tick is at the next multiple of the period.
+                if (timestamp - last_collected).to_std().unwrap_or_default()
+                    > (self.period * 2).max(MIN_MISSED_GAP)
+                {
+                    DATA.collection_gaps.write().await.push_back((
+                        timestamp,
+                        CollectionGap {
+                            schedule: self.schedule,
+                            start_time: last_collected,
+                            end_time: timestamp,
+                            failed_ticks: 0,
+                        },
+                    ));
+                }
+            }
+            self.last_collected = Some(timestamp);
+            self.failures = 0;
+        } else {
+            let gap = self.ongoing_gap.get_or_insert_with(|| CollectionGap {
+                schedule: self.schedule,
+                start_time: self.last_collected.unwrap_or(timestamp),
+                ..Default::default()
+            });
+            gap.end_time = timestamp;
+            gap.failed_ticks += 1;
+            self.failures += 1;
+        }
+    }
+    // the time to wait after a failed collection, doubling with every failure after the first,
+    // so an unavailable database is not tried every (ash) interval.
+    pub fn backoff(&self) -> Duration {
+        match self.failures {
+            0 | 1 => Duration::ZERO,
+            failures => self
+                .period
+                .saturating_mul(2_u32.saturating_pow(failures - 1))
+                .min(MAX_BACKOFF),
+        }
+    }
+}
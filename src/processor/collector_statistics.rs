@@ -0,0 +1,258 @@
+use crate::processor::{
+    pg_stat_bgwriter::PgStatBgWriter, pg_stat_checkpointer::PgStatCheckpointer,
+    pg_stat_wal::PgStatWal,
+};
+use crate::{PerCluster, CLUSTERS, DATA};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::info;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+    time::Instant,
+};
+use tokio::sync::{RwLock, RwLockWriteGuard};
+
+// the collector that the current task runs, see Collector::timed.
+tokio::task_local! {
+    static COLLECTOR: &'static str;
+}
+
+// the overhead of pas itself per collector, accumulated since it was last added to DATA. the
+// accumulation is done in the drop of a lock guard, so it uses a synchronous mutex.
+static COLLECTOR_STATISTICS: Lazy<PerCluster<Mutex<HashMap<&'static str, CollectorStatistics>>>> =
+    Lazy::new(|| {
+        PerCluster(
+            CLUSTERS
+                .iter()
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+        )
+    });
+
+// the overhead of a collector since the previous entry in DATA.collector_statistics. the times
+// are in milliseconds. the query time is measured by pas, so it includes the network roundtrip.
+// the lock times are the times the collector waited for and held write locks on DATA, during
+// which the webserver cannot read that category.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CollectorStatistics {
+    pub collector: String,
+    pub calls: u64,
+    pub failures: u64,
+    pub rows: u64,
+    pub elapsed_time: f64,
+    pub max_elapsed_time: f64,
+    pub query_time: f64,
+    pub lock_wait_time: f64,
+    pub lock_hold_time: f64,
+    pub missed_ticks: u64,
+}
+
+// the number of rows of a query result.
+pub trait QueryRows {
+    fn rows(&self) -> usize;
+}
+
+impl<T> QueryRows for Vec<T> {
+    fn rows(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> QueryRows for Option<T> {
+    fn rows(&self) -> usize {
+        self.is_some().into()
+    }
+}
+
+impl QueryRows for PgStatBgWriter {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
+impl QueryRows for PgStatCheckpointer {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
+impl QueryRows for PgStatWal {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
+// a write lock on a category in DATA that records the time it was held when it is dropped.
+pub struct TimedWriteGuard<'a, T> {
+    guard: RwLockWriteGuard<'a, T>,
+    acquired: Instant,
+}
+
+impl<T> Deref for TimedWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TimedWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for TimedWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        let hold_time = milliseconds(self.acquired);
+        Collector::update(current_collector(), |r| r.lock_hold_time += hold_time);
+    }
+}
+
+pub struct Collector {}
+
+impl Collector {
+    // run a collector, and record its elapsed time and whether it failed. the queries and the
+    // locks on DATA in the collector are recorded for it via the COLLECTOR task local.
+    pub async fn timed<F>(collector: &'static str, future: F) -> bool
+    where
+        F: Future<Output = bool>,
+    {
+        let start = Instant::now();
+        let collected = COLLECTOR.scope(collector, future).await;
+        let elapsed_time = milliseconds(start);
+        Collector::update(collector, |r| {
+            r.calls += 1;
+            r.failures += u64::from(!collected);
+            r.elapsed_time += elapsed_time;
+            r.max_elapsed_time = r.max_elapsed_time.max(elapsed_time);
+        });
+        collected
+    }
+    pub async fn timed_query<T, F>(query: F) -> Result<T>
+    where
+        T: QueryRows,
+        F: Future<Output = Result<T>>,
+    {
+        let start = Instant::now();
+        let result = query.await;
+        let query_time = milliseconds(start);
+        let rows = result.as_ref().map_or(0, |r| r.rows()) as u64;
+        Collector::update(current_collector(), |r| {
+            r.query_time += query_time;
+            r.rows += rows;
+        });
+        result
+    }
+    pub async fn timed_write<T>(lock: &RwLock<T>) -> TimedWriteGuard<'_, T> {
+        let start = Instant::now();
+        let guard = lock.write().await;
+        let lock_wait_time = milliseconds(start);
+        Collector::update(current_collector(), |r| r.lock_wait_time += lock_wait_time);
+        TimedWriteGuard {
+            guard,
+            acquired: Instant::now(),
+        }
+    }
+    pub fn add_missed_ticks(collector: &'static str, missed_ticks: u64) {
+        Collector::update(collector, |r| r.missed_ticks += missed_ticks);
+    }
+    // move the statistics accumulated since the previous call to DATA.
+    pub async fn add_to_data() {
+        let collector_statistics: Vec<CollectorStatistics> = COLLECTOR_STATISTICS
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, r)| r)
+            .collect();
+        DATA.collector_statistics
+            .write()
+            .await
+            .push_back((Local::now(), collector_statistics));
+    }
+    // log the overhead since low_time, with the collectors that took the most time first.
+    pub async fn log_summary(cluster_name: &str, low_time: DateTime<Local>) {
+        let mut totals: HashMap<String, CollectorStatistics> = HashMap::new();
+        let mut high_time = low_time;
+        for (timestamp, collector_statistics) in DATA
+            .collector_statistics
+            .read()
+            .await
+            .iter()
+            .filter(|(timestamp, _)| *timestamp > low_time)
+        {
+            high_time = *timestamp;
+            for row in collector_statistics {
+                let total =
+                    totals
+                        .entry(row.collector.clone())
+                        .or_insert_with(|| CollectorStatistics {
+                            collector: row.collector.clone(),
+                            ..Default::default()
+                        });
+                total.calls += row.calls;
+                total.failures += row.failures;
+                total.rows += row.rows;
+                total.elapsed_time += row.elapsed_time;
+                total.max_elapsed_time = total.max_elapsed_time.max(row.max_elapsed_time);
+                total.query_time += row.query_time;
+                total.lock_wait_time += row.lock_wait_time;
+                total.lock_hold_time += row.lock_hold_time;
+                total.missed_ticks += row.missed_ticks;
+            }
+        }
+        let seconds = (high_time - low_time).num_milliseconds() as f64 / 1000_f64;
+        if seconds <= 0_f64 {
+            return;
+        }
+        let mut totals: Vec<CollectorStatistics> = totals.into_values().collect();
+        totals.sort_by(|a, b| b.elapsed_time.total_cmp(&a.elapsed_time));
+        info!(
+            "Overhead of cluster {} in the last {:.0}s: {:.1} ms/s query time, {:.1} ms/s collector time, {} missed ticks, {} failures.",
+            cluster_name,
+            seconds,
+            totals.iter().map(|r| r.query_time).sum::<f64>() / seconds,
+            totals.iter().map(|r| r.elapsed_time).sum::<f64>() / seconds,
+            totals.iter().map(|r| r.missed_ticks).sum::<u64>(),
+            totals.iter().map(|r| r.failures).sum::<u64>(),
+        );
+        for total in totals.iter().filter(|r| r.calls > 0).take(3) {
+            info!(
+                "  {}: {} calls, avg {:.1} ms, max {:.1} ms, avg {:.0} rows, {:.1} ms lock wait.",
+                total.collector,
+                total.calls,
+                total.elapsed_time / total.calls as f64,
+                total.max_elapsed_time,
+                total.rows as f64 / total.calls as f64,
+                total.lock_wait_time,
+            );
+        }
+    }
+    fn update(collector: &'static str, update: impl FnOnce(&mut CollectorStatistics)) {
+        update(
+            COLLECTOR_STATISTICS
+                .lock()
+                .unwrap()
+                .entry(collector)
+                .or_insert_with(|| CollectorStatistics {
+                    collector: collector.to_string(),
+                    ..Default::default()
+                }),
+        );
+    }
+}
+
+// the collector of the current task, writes outside of a collector are recorded as "other".
+fn current_collector() -> &'static str {
+    COLLECTOR.try_with(|r| *r).unwrap_or("other")
+}
+
+fn milliseconds(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000_f64
+}
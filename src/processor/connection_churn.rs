@@ -0,0 +1,117 @@
+use crate::processor::{Collector, PgStatActivity};
+use crate::DATA;
+
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+// two samples of a pid are the same session when their backend start differs at most this many
+// seconds, backend_time is truncated to whole seconds in each sample.
+static BACKEND_START_TOLERANCE: i64 = 1;
+
+// the client backends that connected and disconnected between two consecutive samples of
+// pg_stat_activity, per database, application, user and client address. a session that connects
+// and disconnects between two samples is not seen, so these are a lower bound of the churn, the
+// sessions counter of pg_stat_database counts all of them.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ConnectionChurn {
+    pub datname: String,
+    pub application_name: String,
+    pub usename: String,
+    pub client_addr: String,
+    pub connects: u64,
+    pub disconnects: u64,
+}
+
+type ChurnKey = (String, String, String, String);
+
+impl ConnectionChurn {
+    // the session is identified by its pid and its backend start, because a pid can be reused by
+    // a new session between two samples.
+    // the connections of pas are not counted: pg_stat_activity is sampled without the connection
+    // that queries it, which can be another connection of the pool at every sample.
+    fn session(pg_stat_activity: &PgStatActivity) -> Option<(i32, DateTime<Local>)> {
+        if pg_stat_activity.backend_type.as_deref() != Some("client backend")
+            || pg_stat_activity.application_name.as_deref() == Some("PAS")
+        {
+            return None;
+        }
+        pg_stat_activity.backend_time.map(|backend_time| {
+            (
+                pg_stat_activity.pid,
+                pg_stat_activity.timestamp - Duration::seconds(backend_time),
+            )
+        })
+    }
+    fn key(pg_stat_activity: &PgStatActivity) -> ChurnKey {
+        (
+            pg_stat_activity.datname.clone().unwrap_or_default(),
+            pg_stat_activity
+                .application_name
+                .clone()
+                .unwrap_or_default(),
+            pg_stat_activity.usename.clone().unwrap_or_default(),
+            pg_stat_activity
+                .client_addr
+                .clone()
+                .unwrap_or_else(|| "local".to_string()),
+        )
+    }
+    // the sessions of one sample that are not in the other sample.
+    fn missing_from<'a>(
+        sample: &'a [PgStatActivity],
+        other: &[PgStatActivity],
+    ) -> impl Iterator<Item = &'a PgStatActivity> {
+        let other: HashSet<(i32, i64)> = other
+            .iter()
+            .filter_map(ConnectionChurn::session)
+            .map(|(pid, backend_start)| (pid, backend_start.timestamp()))
+            .collect();
+        sample.iter().filter(move |r| {
+            ConnectionChurn::session(r).is_some_and(|(pid, backend_start)| {
+                (-BACKEND_START_TOLERANCE..=BACKEND_START_TOLERANCE)
+                    .all(|offset| !other.contains(&(pid, backend_start.timestamp() + offset)))
+            })
+        })
+    }
+    pub fn between(
+        previous: &[PgStatActivity],
+        current: &[PgStatActivity],
+    ) -> Vec<ConnectionChurn> {
+        let mut churn: BTreeMap<ChurnKey, ConnectionChurn> = BTreeMap::new();
+        for r in ConnectionChurn::missing_from(current, previous) {
+            churn.entry(ConnectionChurn::key(r)).or_default().connects += 1;
+        }
+        for r in ConnectionChurn::missing_from(previous, current) {
+            churn
+                .entry(ConnectionChurn::key(r))
+                .or_default()
+                .disconnects += 1;
+        }
+        churn
+            .into_iter()
+            .map(
+                |((datname, application_name, usename, client_addr), r)| ConnectionChurn {
+                    datname,
+                    application_name,
+                    usename,
+                    client_addr,
+                    ..r
+                },
+            )
+            .collect()
+    }
+    // only the samples with connects or disconnects are added.
+    pub async fn add_to_data(
+        previous: &[PgStatActivity],
+        current: &[PgStatActivity],
+        timestamp: DateTime<Local>,
+    ) {
+        let churn = ConnectionChurn::between(previous, current);
+        if !churn.is_empty() {
+            Collector::timed_write(&DATA.connection_churn)
+                .await
+                .push_back((timestamp, churn));
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLockWriteGuard;
 
-use crate::processor::DELTATABLE;
+use crate::processor::{KeyedDeltaHashMap, DELTATABLE, KEYED_DELTATABLE};
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct StatisticsDelta {
     pub last_timestamp: DateTime<Local>,
     pub last_value: f64,
@@ -12,6 +14,51 @@ pub struct StatisticsDelta {
     pub updated_value: bool,
 }
 
+impl StatisticsDelta {
+    fn new(last_timestamp: DateTime<Local>, last_value: f64) -> Self {
+        StatisticsDelta {
+            last_timestamp,
+            last_value,
+            delta_value: 0_f64,
+            per_second_value: 0_f64,
+            updated_value: false,
+        }
+    }
+    fn update(&mut self, last_timestamp: DateTime<Local>, last_value: f64) {
+        // if fetched timestamp doesn't make sense alias the fetch was invalid:
+        if last_timestamp == self.last_timestamp {
+            self.updated_value = false;
+        } else {
+            // if the statistics are reset
+            if self.last_value > last_value {
+                self.last_timestamp = last_timestamp;
+                self.last_value = last_value;
+                self.delta_value = 0_f64;
+                self.per_second_value = 0_f64;
+                self.updated_value = false;
+            } else {
+                // this is the normal situation after the insert, where we can calculate
+                // the delta, as well as the amount per second
+                self.delta_value = last_value - self.last_value;
+                // the per secon value is caluclated by dividing it by the number of
+                // milliseconds (not seconds), and then dividing it by 1000 to make it per
+                // second.
+                self.per_second_value = self.delta_value
+                    / (last_timestamp
+                        .signed_duration_since(self.last_timestamp)
+                        .num_milliseconds() as f64
+                        / 1000_f64);
+                self.last_value = last_value;
+                self.last_timestamp = last_timestamp;
+                self.updated_value = true;
+                if self.per_second_value.is_nan() {
+                    self.per_second_value = 0_f64
+                }
+            }
+        };
+    }
+}
+
 pub struct DeltaTable {}
 
 impl DeltaTable {
@@ -20,45 +67,54 @@ impl DeltaTable {
             .write()
             .await
             .entry(name.to_string())
-            .and_modify(|r| {
-                // if fetched timestamp doesn't make sense alias the fetch was invalid:
-                if last_timestamp == r.last_timestamp {
-                    r.updated_value = false;
-                } else {
-                    // if the statistics are reset
-                    if r.last_value > last_value {
-                        r.last_timestamp = last_timestamp;
-                        r.last_value = last_value;
-                        r.delta_value = 0_f64;
-                        r.per_second_value = 0_f64;
-                        r.updated_value = false;
-                    } else {
-                        // this is the normal situation after the insert, where we can calculate
-                        // the delta, as well as the amount per second
-                        r.delta_value = last_value - r.last_value;
-                        // the per secon value is caluclated by dividing it by the number of
-                        // milliseconds (not seconds), and then dividing it by 1000 to make it per
-                        // second.
-                        r.per_second_value = r.delta_value
-                            / (last_timestamp
-                                .signed_duration_since(r.last_timestamp)
-                                .num_milliseconds() as f64
-                                / 1000_f64);
-                        r.last_value = last_value;
-                        r.last_timestamp = last_timestamp;
-                        r.updated_value = true;
-                        if r.per_second_value.is_nan() {
-                            r.per_second_value = 0_f64
-                        }
-                    }
-                };
-            })
-            .or_insert(StatisticsDelta {
-                last_timestamp,
-                last_value,
-                delta_value: 0_f64,
-                per_second_value: 0_f64,
-                updated_value: false,
-            });
+            .and_modify(|r| r.update(last_timestamp, last_value))
+            .or_insert(StatisticsDelta::new(last_timestamp, last_value));
+    }
+    pub async fn get(name: &str) -> Option<StatisticsDelta> {
+        DELTATABLE.read().await.get(name).cloned()
+    }
+    // the keyed statistics of a fetch with a single timestamp, see KeyedDeltaTable.
+    pub async fn keyed(last_timestamp: DateTime<Local>) -> KeyedDeltaTable {
+        KeyedDeltaTable {
+            table: KEYED_DELTATABLE.write().await,
+            last_timestamp,
+        }
+    }
+}
+
+// statistics that have a row per key, such as a queryid, a relation or a pid, are kept per name
+// and key. a fetch can have thousands of keys, so all keys of a fetch are updated, read and
+// removed under a single write lock, which is held until the KeyedDeltaTable is dropped.
+pub struct KeyedDeltaTable {
+    table: RwLockWriteGuard<'static, KeyedDeltaHashMap>,
+    last_timestamp: DateTime<Local>,
+}
+
+impl KeyedDeltaTable {
+    pub fn add_or_update(&mut self, name: &str, key: &str, last_value: f64) {
+        let last_timestamp = self.last_timestamp;
+        if !self.table.contains_key(name) {
+            self.table.insert(name.to_string(), HashMap::new());
+        }
+        let keys = self.table.get_mut(name).unwrap();
+        match keys.get_mut(key) {
+            Some(r) => r.update(last_timestamp, last_value),
+            None => {
+                keys.insert(
+                    key.to_string(),
+                    StatisticsDelta::new(last_timestamp, last_value),
+                );
+            }
+        }
+    }
+    pub fn get(&self, name: &str, key: &str) -> Option<StatisticsDelta> {
+        self.table.get(name).and_then(|r| r.get(key)).cloned()
+    }
+    // keys can disappear from the source (for example a deallocated pg_stat_statements entry),
+    // remove the keys of a name that were not part of the fetch at last_timestamp.
+    pub fn remove_stale(&mut self, name: &str) {
+        if let Some(keys) = self.table.get_mut(name) {
+            keys.retain(|_, r| r.last_timestamp == self.last_timestamp);
+        }
     }
 }
@@ -1,82 +1,402 @@
 use crate::{
+    current_cluster,
     processor::{
-        pg_database::PgDatabase, pg_settings::PgSettings, pg_stat_bgwriter::PgStatBgWriter,
-        pg_stat_database::PgStatDatabase, pg_stat_wal::PgStatWal,
+        pg_database::PgDatabase, pg_stat_bgwriter::PgStatBgWriter,
+        pg_stat_checkpointer::PgStatCheckpointer, pg_stat_database::PgStatDatabase,
+        pg_stat_io::PgStatIo, pg_stat_statements::PgStatStatements,
+        pg_stat_user_indexes::PgStatUserIndexes, pg_stat_user_tables::PgStatUserTables,
+        pg_stat_wal::PgStatWal, proc_diskstats::ProcDiskstats, proc_stat::ProcStat,
+        proc_vmstat::ProcVmstat,
     },
-    ARGS,
+    PerCluster, ARGS, CLUSTER, CLUSTERS,
 };
 
 use anyhow::Result;
+use chrono::{Local, TimeDelta};
 use log::{debug, info, warn};
 use once_cell::sync::Lazy;
-use sqlx::{postgres::PgPoolOptions, Executor};
-use std::{collections::HashMap, time::Duration};
+use sqlx::{postgres::PgPoolOptions, query_as, Executor, Pool, Postgres};
+use std::{collections::HashMap, path::Path, time::Duration};
 use tokio::{
-    sync::RwLock,
+    sync::{watch, RwLock},
     time::{self, MissedTickBehavior},
 };
 
+pub mod collection_gaps;
+pub mod collector_statistics;
+pub mod connection_churn;
 pub mod deltatable;
 pub mod pg_database;
+pub mod pg_locks;
+pub mod pg_replication_slots;
 pub mod pg_settings;
 pub mod pg_stat_activity;
 pub mod pg_stat_bgwriter;
+pub mod pg_stat_checkpointer;
 pub mod pg_stat_database;
+pub mod pg_stat_io;
+pub mod pg_stat_progress;
+pub mod pg_stat_replication;
+pub mod pg_stat_statements;
+pub mod pg_stat_user_indexes;
+pub mod pg_stat_user_tables;
 pub mod pg_stat_wal;
+pub mod pg_stat_wal_receiver;
+pub mod pg_xmin_horizon;
+pub mod proc_diskstats;
+pub mod proc_loadavg;
+pub mod proc_meminfo;
+pub mod proc_pid;
+pub mod proc_stat;
+pub mod proc_vmstat;
 
+pub use collection_gaps::{CollectionGap, Schedule, ScheduleTracker};
+pub use collector_statistics::{Collector, CollectorStatistics};
+pub use connection_churn::ConnectionChurn;
 pub use deltatable::{DeltaTable, StatisticsDelta};
 pub use pg_database::PgDatabaseXidLimits;
+pub use pg_locks::PgLockEdge;
+pub use pg_replication_slots::PgReplicationSlots;
+pub use pg_settings::{PgSettings, PgSettingsChange};
 pub use pg_stat_activity::PgStatActivity;
 pub use pg_stat_bgwriter::PgStatBgWriterSum;
-pub use pg_stat_database::PgStatDatabaseSum;
+pub use pg_stat_checkpointer::PgStatCheckpointerSum;
+pub use pg_stat_database::{PgStatDatabaseSum, PgStatDatabaseSumPerDatabase};
+pub use pg_stat_io::PgStatIoSum;
+pub use pg_stat_progress::PgStatProgress;
+pub use pg_stat_replication::PgStatReplication;
+pub use pg_stat_statements::PgStatStatementsQueryid;
+pub use pg_stat_user_indexes::PgStatUserIndexesSum;
+pub use pg_stat_user_tables::PgStatUserTablesSum;
 pub use pg_stat_wal::PgStatWalSum;
+pub use pg_stat_wal_receiver::PgStatWalReceiver;
+pub use pg_xmin_horizon::{PgXminHorizon, XminHolder};
+pub use proc_diskstats::ProcDiskstatsSum;
+pub use proc_loadavg::ProcLoadavg;
+pub use proc_meminfo::ProcMeminfo;
+pub use proc_pid::ProcPid;
+pub use proc_stat::ProcStatSum;
+pub use proc_vmstat::ProcVmstatSum;
+
+// the overhead of pas is logged every SUMMARY_INTERVAL, see Collector::log_summary.
+const SUMMARY_INTERVAL: TimeDelta = TimeDelta::minutes(5);
 
 type DeltaHashTable = RwLock<HashMap<String, StatisticsDelta>>;
-static DELTATABLE: Lazy<DeltaHashTable> = Lazy::new(|| RwLock::new(HashMap::new()));
+static DELTATABLE: Lazy<PerCluster<DeltaHashTable>> = Lazy::new(|| {
+    PerCluster(
+        CLUSTERS
+            .iter()
+            .map(|_| RwLock::new(HashMap::new()))
+            .collect(),
+    )
+});
+type KeyedDeltaHashMap = HashMap<String, HashMap<String, StatisticsDelta>>;
+static KEYED_DELTATABLE: Lazy<PerCluster<RwLock<KeyedDeltaHashMap>>> = Lazy::new(|| {
+    PerCluster(
+        CLUSTERS
+            .iter()
+            .map(|_| RwLock::new(HashMap::new()))
+            .collect(),
+    )
+});
 
+// the processor collects the statistics of the cluster of the current task, see CLUSTER.
+// every schedule records the ticks in which it did not collect as a gap, see ScheduleTracker.
 pub async fn processor_main() -> Result<()> {
-    let mut interval = time::interval(Duration::from_secs(ARGS.interval));
-    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let cluster = current_cluster();
+    let cluster_name = &CLUSTERS[cluster].name;
 
-    info!("Setup database connectionpool.");
-    // loop until connection pool becomes available
-    let pool = loop {
-        match PgPoolOptions::new()
-            .min_connections(1)
-            .max_connections(1)
-            .acquire_timeout(Duration::from_secs(10))
-            .after_connect(|connection, _| {
-                Box::pin(async move {
-                    connection.execute("set application_name = 'PAS';").await?;
-                    Ok(())
-                })
-            })
-            .connect(&ARGS.connection_string)
-            .await
-        {
-            Ok(pool) => {
-                info!("Database connectionpool created.");
-                break pool;
+    // the os statistics are only available on linux, pas is expected to run on the database
+    // server for these to be meaningful.
+    let os_statistics = Path::new("/proc/stat").exists();
+    if !os_statistics {
+        info!("/proc/stat not found, os statistics are not collected.");
+    }
+
+    // the connection pool and the server version are handed to the tasks of the schedules via a
+    // watch channel, so these keep running when the pool is re-established. None means the
+    // cluster cannot be reached.
+    let (connection_sender, connection) = watch::channel(None::<(Pool<Postgres>, i32)>);
+
+    // pg_stat_activity is sampled on its own schedule in milliseconds, so short lwlock and io
+    // spikes are not missed. the lock edges belong to a pg_stat_activity sample, so these are
+    // fetched with it.
+    let mut ash_connection = connection.clone();
+    tokio::spawn(CLUSTER.scope(cluster, async move {
+        let _ = ash_connection.wait_for(|r| r.is_some()).await;
+        let period = Duration::from_millis(ARGS.ash_interval);
+        let mut ash_interval = time::interval(period);
+        ash_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut schedule = ScheduleTracker::new(Schedule::Activity, period);
+        loop {
+            let tick = ash_interval.tick().await;
+            schedule.ticked(tick);
+            let current_connection = ash_connection.borrow_and_update().clone();
+            let collected = match current_connection {
+                Some((pool, server_version_num)) => {
+                    let collected = Collector::timed(
+                        "pg_stat_activity",
+                        PgStatActivity::fetch_and_add_to_data(
+                            &pool,
+                            server_version_num,
+                            os_statistics,
+                        ),
+                    )
+                    .await;
+                    Collector::timed("pg_locks", PgLockEdge::fetch_and_add_to_data(&pool)).await;
+                    collected
+                }
+                None => false,
+            };
+            schedule.record(collected).await;
+            tokio::select! {
+                _ = time::sleep(schedule.backoff()) => {}
+                _ = ash_connection.changed() => {}
             }
-            Err(error) => {
-                warn!(
-                    "Database connectionpool creation failed, error: {:?}, retrying",
-                    error
-                );
-                interval.tick().await;
+        }
+    }));
+
+    // pg_settings and pg_database change slowly. pg_database uses the settings that are read just
+    // before it.
+    let mut settings_connection = connection.clone();
+    tokio::spawn(CLUSTER.scope(cluster, async move {
+        let _ = settings_connection.wait_for(|r| r.is_some()).await;
+        let period = Duration::from_secs(ARGS.settings_interval);
+        let mut settings_interval = time::interval(period);
+        settings_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut schedule = ScheduleTracker::new(Schedule::Settings, period);
+        loop {
+            let tick = settings_interval.tick().await;
+            schedule.ticked(tick);
+            let current_connection = settings_connection.borrow_and_update().clone();
+            let collected = match current_connection {
+                Some((pool, server_version_num)) => {
+                    let collected =
+                        Collector::timed("pg_settings", PgSettings::fetch_and_add_to_data(&pool))
+                            .await;
+                    Collector::timed(
+                        "pg_database",
+                        PgDatabase::fetch_and_add_to_data(&pool, server_version_num),
+                    )
+                    .await;
+                    collected
+                }
+                None => false,
+            };
+            schedule.record(collected).await;
+            tokio::select! {
+                _ = time::sleep(schedule.backoff()) => {}
+                _ = settings_connection.changed() => {}
             }
-        };
-    };
+        }
+    }));
+
+    // the os statistics do not depend on the database, so these are collected while the cluster
+    // cannot be reached too.
+    if os_statistics {
+        tokio::spawn(CLUSTER.scope(cluster, async move {
+            let period = Duration::from_secs(ARGS.interval);
+            let mut os_interval = time::interval(period);
+            os_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let mut schedule = ScheduleTracker::new(Schedule::Os, period);
+            loop {
+                let tick = os_interval.tick().await;
+                schedule.ticked(tick);
+                let collected = [
+                    Collector::timed("proc_stat", ProcStat::read_and_add_to_data()).await,
+                    Collector::timed("proc_loadavg", ProcLoadavg::read_and_add_to_data()).await,
+                    Collector::timed("proc_meminfo", ProcMeminfo::read_and_add_to_data()).await,
+                    Collector::timed("proc_vmstat", ProcVmstat::read_and_add_to_data()).await,
+                    Collector::timed("proc_diskstats", ProcDiskstats::read_and_add_to_data()).await,
+                ];
+                schedule.record(!collected.contains(&false)).await;
+            }
+        }));
+    }
+
+    let period = Duration::from_secs(ARGS.interval);
+    let mut interval = time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut schedule = ScheduleTracker::new(Schedule::Statistics, period);
+    let mut last_summary = Local::now();
 
     loop {
-        interval.tick().await;
-        debug!("tick!");
-
-        PgStatActivity::fetch_and_add_to_data(&pool).await;
-        PgStatDatabase::fetch_and_add_to_data(&pool).await;
-        PgStatBgWriter::fetch_and_add_to_data(&pool).await;
-        PgStatWal::fetch_and_add_to_data(&pool).await;
-        PgSettings::fetch_and_add_to_data(&pool).await;
-        PgDatabase::fetch_and_add_to_data(&pool).await;
+        info!(
+            "Setup database connectionpool for cluster {}.",
+            cluster_name
+        );
+        // loop until connection pool becomes available
+        let (pool, server_version_num) = loop {
+            match connect(&CLUSTERS[cluster].connection_string).await {
+                Ok((pool, server_version_num)) => {
+                    info!(
+                        "Database connectionpool created for cluster {}, server_version_num: {}",
+                        cluster_name, server_version_num
+                    );
+                    break (pool, server_version_num);
+                }
+                Err(error) => {
+                    warn!(
+                        "Database connectionpool creation for cluster {} failed, error: {:?}, retrying",
+                        cluster_name, error
+                    );
+                    schedule.record(false).await;
+                    time::sleep(schedule.backoff().max(period)).await;
+                }
+            };
+        };
+        connection_sender.send_replace(Some((pool.clone(), server_version_num)));
+        let pg_stat_statements_version = PgStatStatements::extension_version(&pool).await;
+
+        loop {
+            let tick = interval.tick().await;
+            schedule.ticked(tick);
+            debug!("tick!");
+
+            // pg_stat_database is available in every version and for every user, when it cannot
+            // be fetched, the other statistics cannot be fetched either.
+            let collected = Collector::timed(
+                "pg_stat_database",
+                PgStatDatabase::fetch_and_add_to_data(&pool, server_version_num),
+            )
+            .await;
+            if collected {
+                Collector::timed(
+                    "pg_stat_progress",
+                    PgStatProgress::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_bgwriter",
+                    PgStatBgWriter::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_checkpointer",
+                    PgStatCheckpointer::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_io",
+                    PgStatIo::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_wal",
+                    PgStatWal::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_user_tables",
+                    PgStatUserTables::fetch_and_add_to_data(&pool),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_user_indexes",
+                    PgStatUserIndexes::fetch_and_add_to_data(&pool),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_replication",
+                    PgStatReplication::fetch_and_add_to_data(&pool),
+                )
+                .await;
+                Collector::timed(
+                    "pg_replication_slots",
+                    PgReplicationSlots::fetch_and_add_to_data(&pool, server_version_num),
+                )
+                .await;
+                Collector::timed(
+                    "pg_xmin_horizon",
+                    PgXminHorizon::fetch_and_add_to_data(&pool),
+                )
+                .await;
+                Collector::timed(
+                    "pg_stat_wal_receiver",
+                    PgStatWalReceiver::fetch_and_add_to_data(&pool),
+                )
+                .await;
+                if let Some(extversion) = &pg_stat_statements_version {
+                    Collector::timed(
+                        "pg_stat_statements",
+                        PgStatStatements::fetch_and_add_to_data(&pool, extversion),
+                    )
+                    .await;
+                }
+            }
+            schedule.record(collected).await;
+            Collector::add_to_data().await;
+            if Local::now() - last_summary >= SUMMARY_INTERVAL {
+                Collector::log_summary(cluster_name, last_summary).await;
+                last_summary = Local::now();
+            }
+
+            // the pool replaces broken connections by itself, but when the cluster cannot be
+            // reached at all (for example during a restart), the pool is re-established, and the
+            // server version is read again, because it can be changed by the restart.
+            if !collected && self::server_version_num(&pool).await.is_err() {
+                break;
+            }
+            time::sleep(schedule.backoff()).await;
+        }
+        warn!(
+            "Cluster {} cannot be reached, re-establishing the connectionpool.",
+            cluster_name
+        );
+        connection_sender.send_replace(None);
+        pool.close().await;
+    }
+}
+
+// create the connection pool and read the server version.
+async fn connect(connection_string: &str) -> Result<(Pool<Postgres>, i32)> {
+    let pool = PgPoolOptions::new()
+        .min_connections(1)
+        // pg_stat_activity, the statistics and the settings are fetched on their own schedule,
+        // each with its own connection.
+        .max_connections(3)
+        .acquire_timeout(Duration::from_secs(10))
+        .after_connect(|connection, _| {
+            Box::pin(async move {
+                connection.execute("set application_name = 'PAS';").await?;
+                Ok(())
+            })
+        })
+        .connect(connection_string)
+        .await?;
+    // the server version determines the queries and column mappings of the collectors
+    let server_version_num = server_version_num(&pool).await?;
+
+    Ok((pool, server_version_num))
+}
+
+// server_version_num is the version as a number, for example 150004 for version 15.4,
+// which allows the collectors to compare it with the version a column or view appeared.
+pub async fn server_version_num(pool: &Pool<sqlx::Postgres>) -> Result<i32> {
+    let (server_version_num,): (i32,) =
+        query_as("select current_setting('server_version_num')::int")
+            .fetch_one(pool)
+            .await?;
+
+    Ok(server_version_num)
+}
+
+// keep the rows that are in the top n of any of the rankings, so a relation that is for example
+// only seq scanned is kept next to the relations with the most changes.
+pub fn keep_top_n<T>(rows: Vec<T>, n: usize, rankings: &[fn(&T) -> f64]) -> Vec<T> {
+    let mut keep = vec![false; rows.len()];
+    for ranking in rankings {
+        let mut order: Vec<usize> = (0..rows.len())
+            .filter(|index| ranking(&rows[*index]) > 0_f64)
+            .collect();
+        order.sort_by(|a, b| ranking(&rows[*b]).total_cmp(&ranking(&rows[*a])));
+        for index in order.into_iter().take(n) {
+            keep[index] = true;
+        }
     }
+    rows.into_iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(row, _)| row)
+        .collect()
 }
@@ -1,3 +1,4 @@
+use crate::processor::Collector;
 use crate::processor::DeltaTable;
 use crate::processor::DELTATABLE;
 use crate::DATA;
@@ -12,11 +13,12 @@ use sqlx::{query_as, FromRow, Pool};
 pub struct PgDatabaseXidLimits {
     pub age_datfrozenxid: f64,
     pub age_datminmxid: f64,
-    pub vacuum_failsafe_age: f64,
+    // the failsafe ages are None for versions before 14.
+    pub vacuum_failsafe_age: Option<f64>,
     pub autovacuum_freeze_max_age: f64,
     pub vacuum_freeze_table_age: f64,
     pub vacuum_freeze_min_age: f64,
-    pub vacuum_multixact_failsafe_age: f64,
+    pub vacuum_multixact_failsafe_age: Option<f64>,
     pub autovacuum_multixact_freeze_max_age: f64,
     pub vacuum_multixact_freeze_table_age: f64,
     pub vacuum_multixact_freeze_min_age: f64,
@@ -42,83 +44,64 @@ impl PgDatabaseXidLimits {
             pg_database.iter().map(|r| r.age_datminmxid).max().unwrap() as f64,
         )
         .await;
-        if DELTATABLE
-            .read()
-            .await
+        let deltatable = DELTATABLE.read().await;
+        if deltatable
             .get("pg_database.age_datfrozenxid")
             .unwrap()
             .updated_value
         {
-            DATA.pg_database_xid_limits.write().await.push_back((
-                pg_database_timestamp,
-                PgDatabaseXidLimits {
-                    age_datfrozenxid: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_database.age_datfrozenxid")
-                        .unwrap()
-                        .last_value,
-                    age_datminmxid: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_database.age_datminmxid")
-                        .unwrap()
-                        .last_value,
-                    vacuum_failsafe_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_failsafe_age")
-                        .unwrap()
-                        .last_value,
-                    autovacuum_freeze_max_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.autovacuum_freeze_max_age")
-                        .unwrap()
-                        .last_value,
-                    vacuum_freeze_table_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_freeze_table_age")
-                        .unwrap()
-                        .last_value,
-                    vacuum_freeze_min_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_freeze_min_age")
-                        .unwrap()
-                        .last_value,
-                    vacuum_multixact_failsafe_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_multixact_failsafe_age")
-                        .unwrap()
-                        .last_value,
-                    autovacuum_multixact_freeze_max_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.autovacuum_multixact_freeze_max_age")
-                        .unwrap()
-                        .last_value,
-                    vacuum_multixact_freeze_table_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_multixact_freeze_table_age")
-                        .unwrap()
-                        .last_value,
-                    vacuum_multixact_freeze_min_age: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_settings.vacuum_multixact_freeze_min_age")
-                        .unwrap()
-                        .last_value,
-                },
-            ));
+            Collector::timed_write(&DATA.pg_database_xid_limits)
+                .await
+                .push_back((
+                    pg_database_timestamp,
+                    PgDatabaseXidLimits {
+                        age_datfrozenxid: deltatable
+                            .get("pg_database.age_datfrozenxid")
+                            .unwrap()
+                            .last_value,
+                        age_datminmxid: deltatable
+                            .get("pg_database.age_datminmxid")
+                            .unwrap()
+                            .last_value,
+                        vacuum_failsafe_age: deltatable
+                            .get("pg_settings.vacuum_failsafe_age")
+                            .map(|r| r.last_value),
+                        autovacuum_freeze_max_age: deltatable
+                            .get("pg_settings.autovacuum_freeze_max_age")
+                            .unwrap()
+                            .last_value,
+                        vacuum_freeze_table_age: deltatable
+                            .get("pg_settings.vacuum_freeze_table_age")
+                            .unwrap()
+                            .last_value,
+                        vacuum_freeze_min_age: deltatable
+                            .get("pg_settings.vacuum_freeze_min_age")
+                            .unwrap()
+                            .last_value,
+                        vacuum_multixact_failsafe_age: deltatable
+                            .get("pg_settings.vacuum_multixact_failsafe_age")
+                            .map(|r| r.last_value),
+                        autovacuum_multixact_freeze_max_age: deltatable
+                            .get("pg_settings.autovacuum_multixact_freeze_max_age")
+                            .unwrap()
+                            .last_value,
+                        vacuum_multixact_freeze_table_age: deltatable
+                            .get("pg_settings.vacuum_multixact_freeze_table_age")
+                            .unwrap()
+                            .last_value,
+                        vacuum_multixact_freeze_min_age: deltatable
+                            .get("pg_settings.vacuum_multixact_freeze_min_age")
+                            .unwrap()
+                            .last_value,
+                    },
+                ));
         }
     }
 }
 
-// this pg_database is consistent with postgres version 15
+// this pg_database is consistent with postgres version 15.
+// datlocprovider, daticulocale and datcollversion are None for versions before 15, and
+// daticulocale is read from datlocale, which is its name since version 17.
 #[derive(Debug, FromRow, Clone)]
 pub struct PgDatabase {
     pub timestamp: DateTime<Local>,
@@ -126,7 +109,7 @@ pub struct PgDatabase {
     pub datname: String,
     pub datdba: i32,
     pub encoding: i32,
-    pub datlocprovider: String,
+    pub datlocprovider: Option<String>,
     pub datistemplate: bool,
     pub datallowconn: bool,
     pub datconnlimit: i32,
@@ -140,26 +123,47 @@ pub struct PgDatabase {
 }
 
 impl PgDatabase {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
-        match PgDatabase::query(pool).await {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        match Collector::timed_query(PgDatabase::query(pool, server_version_num)).await {
             Ok(pg_database) => {
                 trace!("pg_database: {:#?}", pg_database);
                 PgDatabaseXidLimits::process_pg_database(pg_database).await;
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
-    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgDatabase>> {
-        let pg_database: Vec<PgDatabase> = query_as(
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<Vec<PgDatabase>> {
+        let (datlocprovider, daticulocale, datcollversion) = match server_version_num {
+            170000.. => (
+                "datlocprovider::text",
+                "datlocale as daticulocale",
+                "datcollversion",
+            ),
+            150000.. => ("datlocprovider::text", "daticulocale", "datcollversion"),
+            _ => (
+                "null::text as datlocprovider",
+                "null::text as daticulocale",
+                "null::text as datcollversion",
+            ),
+        };
+        let sql = format!(
             "
             select clock_timestamp() as timestamp,
                    oid::text::int, 
                    datname, 
                    datdba::text::int,
                    encoding,
-                   datlocprovider::text,
+                   {},
                    datistemplate, 
                    datallowconn, 
                    datconnlimit,
@@ -168,13 +172,13 @@ impl PgDatabase {
                    dattablespace::text::int, 
                    datcollate, 
                    datctype, 
-                   daticulocale, 
-                   datcollversion
+                   {}, 
+                   {}
              from  pg_database 
         ",
-        )
-        .fetch_all(pool)
-        .await?;
+            datlocprovider, daticulocale, datcollversion
+        );
+        let pg_database: Vec<PgDatabase> = query_as(&sql).fetch_all(pool).await?;
 
         Ok(pg_database)
     }
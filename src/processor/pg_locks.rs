@@ -0,0 +1,77 @@
+use crate::processor::Collector;
+use crate::DATA;
+use anyhow::Result;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+// a blocker and waiter edge of the blocking tree, the lock details are the lock the waiter waits
+// for. relation is the relation name for the database pas is connected to, and the oid of the
+// relation for other databases: the catalog of the connected database would resolve the oid of
+// another database to an unrelated relation. shared catalogs have database 0 and resolve in every
+// database.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgLockEdge {
+    pub blocker_pid: i32,
+    pub waiter_pid: i32,
+    pub locktype: Option<String>,
+    pub mode: Option<String>,
+    pub relation: Option<String>,
+}
+
+impl PgLockEdge {
+    // pg_locks is only queried when the last pg_stat_activity sample contains backends waiting
+    // for a lock, the edges get the timestamp of that sample, so they can be matched with the
+    // backends.
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        let (timestamp, waiter_pids) = match DATA.pg_stat_activity.read().await.back() {
+            Some((timestamp, pg_stat_activity)) => (
+                *timestamp,
+                pg_stat_activity
+                    .iter()
+                    .filter(|r| r.wait_event_type.as_deref().unwrap_or_default() == "lock")
+                    .map(|r| r.pid)
+                    .collect::<Vec<i32>>(),
+            ),
+            None => return true,
+        };
+        if waiter_pids.is_empty() {
+            return true;
+        }
+        match Collector::timed_query(PgLockEdge::query(pool, &waiter_pids)).await {
+            Ok(pg_lock_edges) => {
+                trace!("pg_lock_edges: {:#?}", pg_lock_edges);
+                if !pg_lock_edges.is_empty() {
+                    Collector::timed_write(&DATA.pg_lock_edges)
+                        .await
+                        .push_back((timestamp, pg_lock_edges));
+                }
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>, waiter_pids: &[i32]) -> Result<Vec<PgLockEdge>> {
+        let sql = "
+            select b.blocker_pid,
+                   l.pid as waiter_pid,
+                   l.locktype,
+                   l.mode,
+                   case when l.database in (0, (select oid from pg_database where datname = current_database()))
+                        then l.relation::regclass::text
+                        else l.relation::text
+                   end as relation
+             from  pg_locks l
+             cross join lateral unnest(pg_blocking_pids(l.pid)) as b(blocker_pid)
+             where not l.granted
+             and   l.pid = any($1)
+        ";
+        let pg_lock_edges: Vec<PgLockEdge> =
+            query_as(sql).bind(waiter_pids).fetch_all(pool).await?;
+
+        Ok(pg_lock_edges)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::processor::Collector;
+use crate::DATA;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+// this pg_replication_slots is consistent with postgres version 15.
+// retained_bytes is the amount of wal between the current wal position and the restart_lsn of
+// the slot, which is the wal that cannot be removed because of the slot.
+// wal_status and safe_wal_size (version 13) are None for older versions.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgReplicationSlots {
+    pub timestamp: DateTime<Local>,
+    pub slot_name: String,
+    pub slot_type: Option<String>,
+    pub database: Option<String>,
+    pub active: Option<bool>,
+    pub wal_status: Option<String>,
+    pub retained_bytes: Option<f64>,
+    pub safe_wal_size: Option<f64>,
+}
+
+impl PgReplicationSlots {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        match Collector::timed_query(PgReplicationSlots::query(pool, server_version_num)).await {
+            Ok(pg_replication_slots) => {
+                trace!("pg_replication_slots: {:#?}", pg_replication_slots);
+                Collector::timed_write(&DATA.pg_replication_slots)
+                    .await
+                    .push_back((Local::now(), pg_replication_slots));
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<Vec<PgReplicationSlots>> {
+        let (wal_status, safe_wal_size) = if server_version_num >= 130000 {
+            (
+                "s.wal_status",
+                "s.safe_wal_size::double precision as safe_wal_size",
+            )
+        } else {
+            (
+                "null::text as wal_status",
+                "null::double precision as safe_wal_size",
+            )
+        };
+        let sql = format!(
+            "
+            with current_wal as (
+              select case when pg_is_in_recovery() then pg_last_wal_receive_lsn()
+                          else pg_current_wal_lsn()
+                     end as lsn
+            )
+            select clock_timestamp() as timestamp,
+                   s.slot_name::text,
+                   s.slot_type,
+                   s.database::text,
+                   s.active,
+                   {},
+                   pg_wal_lsn_diff(c.lsn, s.restart_lsn)::double precision as retained_bytes,
+                   {}
+             from  pg_replication_slots s
+             cross join current_wal c
+        ",
+            wal_status, safe_wal_size
+        );
+        let pg_replication_slots: Vec<PgReplicationSlots> = query_as(&sql).fetch_all(pool).await?;
+
+        Ok(pg_replication_slots)
+    }
+}
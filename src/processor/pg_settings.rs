@@ -1,34 +1,57 @@
+use crate::processor::Collector;
 use crate::processor::DeltaTable;
+use crate::DATA;
 
 use anyhow::Result;
 use chrono::{DateTime, Local};
-use log::{trace, warn};
+use log::{info, trace, warn};
+use serde::{Deserialize, Serialize};
 use sqlx::{query_as, FromRow, Pool};
+use std::collections::BTreeMap;
 
-// this pg_database is consistent with postgres version 15
-#[derive(Debug, FromRow, Clone)]
+// this pg_settings is consistent with postgres version 15
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
 pub struct PgSettings {
     pub timestamp: DateTime<Local>,
     pub name: String,
     pub setting: String,
     pub unit: Option<String>,
     pub category: String,
-    pub boot_val: String,
-    pub reset_val: String,
+    pub source: String,
+    pub boot_val: Option<String>,
+    pub reset_val: Option<String>,
     pub sourcefile: Option<String>,
     pub sourceline: Option<i32>,
     pub pending_restart: bool,
 }
 
+// a setting that changed between two snapshots of pg_settings. a setting that is added or
+// removed, such as the settings of an extension in shared_preload_libraries, has no old or new
+// setting. a setting that is changed in the configuration file, but needs a restart, keeps its
+// setting and only changes pending_restart.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgSettingsChange {
+    pub name: String,
+    pub old_setting: Option<String>,
+    pub new_setting: Option<String>,
+    pub unit: Option<String>,
+    pub pending_restart: bool,
+    pub sourcefile: Option<String>,
+    pub sourceline: Option<i32>,
+}
+
 impl PgSettings {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
-        match PgSettings::query(pool).await {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgSettings::query(pool)).await {
             Ok(pg_settings) => {
                 trace!("pg_settings: {:#?}", pg_settings);
-                PgSettings::add_to_deltatable(pg_settings).await;
+                PgSettings::add_to_deltatable(&pg_settings).await;
+                PgSettings::add_to_data(pg_settings).await;
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
@@ -40,22 +63,14 @@ impl PgSettings {
                    setting, 
                    unit,
                    category,
+                   source,
                    boot_val,
                    reset_val, 
                    sourcefile, 
                    sourceline,
                    pending_restart
             from   pg_settings
-            where  name in (
-                   'autovacuum_freeze_max_age',
-                   'autovacuum_multixact_freeze_max_age',
-                   'vacuum_freeze_min_age',
-                   'vacuum_freeze_table_age',
-                   'vacuum_failsafe_age',
-                   'vacuum_multixact_freeze_min_age',
-                   'vacuum_multixact_freeze_table_age',
-                   'vacuum_multixact_failsafe_age'
-                   )
+            order by name
         ",
         )
         .fetch_all(pool)
@@ -63,104 +78,110 @@ impl PgSettings {
 
         Ok(pg_settings)
     }
-    async fn add_to_deltatable(pg_settings: Vec<PgSettings>) {
+    // the complete settings are only added when they are fetched for the first time, and when
+    // they changed compared to the previous snapshot, together with the changes.
+    async fn add_to_data(pg_settings: Vec<PgSettings>) {
+        let Some(pg_settings_timestamp) = pg_settings.last().map(|r| r.timestamp) else {
+            return;
+        };
+        let mut snapshots = Collector::timed_write(&DATA.pg_settings).await;
+        let changes = match snapshots.back() {
+            Some((_, previous)) => PgSettings::changes(previous, &pg_settings),
+            None => Vec::new(),
+        };
+        if !snapshots.is_empty() && changes.is_empty() {
+            return;
+        }
+        snapshots.push_back((pg_settings_timestamp, pg_settings));
+        drop(snapshots);
+
+        if !changes.is_empty() {
+            for change in changes.iter() {
+                if change.old_setting == change.new_setting {
+                    info!(
+                        "Setting {} changed in {}, pending restart.",
+                        change.name,
+                        change.sourcefile.as_deref().unwrap_or("the configuration"),
+                    );
+                } else {
+                    info!(
+                        "Setting {} changed from {} to {} {}.",
+                        change.name,
+                        change.old_setting.as_deref().unwrap_or("(none)"),
+                        change.new_setting.as_deref().unwrap_or("(none)"),
+                        change.unit.as_deref().unwrap_or_default(),
+                    );
+                }
+            }
+            Collector::timed_write(&DATA.pg_settings_changes)
+                .await
+                .push_back((pg_settings_timestamp, changes));
+        }
+    }
+    // the settings with a different setting or pending_restart, or that exist in only one of the
+    // snapshots. a different sourcefile or sourceline alone is not a change.
+    pub fn changes(previous: &[PgSettings], current: &[PgSettings]) -> Vec<PgSettingsChange> {
+        let previous: BTreeMap<&str, &PgSettings> =
+            previous.iter().map(|r| (r.name.as_str(), r)).collect();
+        let current: BTreeMap<&str, &PgSettings> =
+            current.iter().map(|r| (r.name.as_str(), r)).collect();
+        let mut changes = Vec::new();
+        for (name, current_setting) in current.iter() {
+            match previous.get(name) {
+                Some(previous_setting)
+                    if previous_setting.setting == current_setting.setting
+                        && previous_setting.pending_restart == current_setting.pending_restart => {}
+                previous_setting => changes.push(PgSettingsChange {
+                    name: name.to_string(),
+                    old_setting: previous_setting.map(|r| r.setting.clone()),
+                    new_setting: Some(current_setting.setting.clone()),
+                    unit: current_setting.unit.clone(),
+                    pending_restart: current_setting.pending_restart,
+                    sourcefile: current_setting.sourcefile.clone(),
+                    sourceline: current_setting.sourceline,
+                }),
+            }
+        }
+        for (name, previous_setting) in previous
+            .iter()
+            .filter(|(name, _)| !current.contains_key(*name))
+        {
+            changes.push(PgSettingsChange {
+                name: name.to_string(),
+                old_setting: Some(previous_setting.setting.clone()),
+                unit: previous_setting.unit.clone(),
+                ..Default::default()
+            });
+        }
+        changes
+    }
+    // a setting that does not exist in the server version, such as vacuum_failsafe_age before
+    // version 14, is not added, so its limit is None.
+    async fn add_to_deltatable(pg_settings: &[PgSettings]) {
         let pg_settings_timestamp = pg_settings.last().map(|r| r.timestamp).unwrap();
 
-        DeltaTable::add_or_update(
-            "pg_settings.autovacuum_freeze_max_age",
-            pg_settings_timestamp,
-            *pg_settings
+        for name in [
+            "autovacuum_freeze_max_age",
+            "vacuum_freeze_min_age",
+            "vacuum_freeze_table_age",
+            "vacuum_failsafe_age",
+            "autovacuum_multixact_freeze_max_age",
+            "vacuum_multixact_freeze_min_age",
+            "vacuum_multixact_freeze_table_age",
+            "vacuum_multixact_failsafe_age",
+        ] {
+            if let Some(setting) = pg_settings
                 .iter()
-                .filter(|r| r.name == "autovacuum_freeze_max_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_freeze_min_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_freeze_min_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_freeze_table_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_freeze_table_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_failsafe_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_failsafe_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.autovacuum_multixact_freeze_max_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "autovacuum_multixact_freeze_max_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_multixact_freeze_min_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_multixact_freeze_min_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_multixact_freeze_table_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_multixact_freeze_table_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_settings.vacuum_multixact_failsafe_age",
-            pg_settings_timestamp,
-            *pg_settings
-                .iter()
-                .filter(|r| r.name == "vacuum_multixact_failsafe_age")
-                .filter_map(|r| r.setting.parse::<f64>().ok())
-                .collect::<Vec<f64>>()
-                .first()
-                .unwrap(),
-        )
-        .await;
+                .filter(|r| r.name == name)
+                .find_map(|r| r.setting.parse::<f64>().ok())
+            {
+                DeltaTable::add_or_update(
+                    &format!("pg_settings.{}", name),
+                    pg_settings_timestamp,
+                    setting,
+                )
+                .await;
+            }
+        }
     }
 }
@@ -1,3 +1,6 @@
+use crate::processor::Collector;
+use crate::processor::ConnectionChurn;
+use crate::processor::ProcPid;
 use crate::DATA;
 use anyhow::Result;
 use chrono::{DateTime, Local};
@@ -5,7 +8,8 @@ use log::{trace, warn};
 use serde::{Deserialize, Serialize};
 use sqlx::{query_as, FromRow, Pool};
 
-// this pg_stat_activity is consistent with postgres version 15
+// this pg_stat_activity is consistent with postgres version 15.
+// leader_pid (version 13) and query_id (version 14) are None for older versions.
 #[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
 pub struct PgStatActivity {
     pub timestamp: DateTime<Local>,
@@ -26,37 +30,82 @@ pub struct PgStatActivity {
     pub wait_event_type: Option<String>,
     pub wait_event: Option<String>,
     pub state: Option<String>,
-    pub backend_xid: Option<i32>,
-    pub backend_xmin: Option<i32>,
+    pub backend_xid: Option<i64>,
+    pub backend_xmin: Option<i64>,
     pub query_id: Option<i64>,
     pub query: Option<String>,
     pub backend_type: Option<String>,
+    // the cpu time and run queue time in seconds and the bytes read and written by the backend
+    // since the previous sample, from /proc/<pid>. these are None when the os statistics are not
+    // available, or for the first sample of a backend.
+    #[sqlx(skip)]
+    pub cpu_time: Option<f64>,
+    #[sqlx(skip)]
+    pub runqueue_time: Option<f64>,
+    #[sqlx(skip)]
+    pub read_bytes: Option<f64>,
+    #[sqlx(skip)]
+    pub write_bytes: Option<f64>,
 }
 
 impl PgStatActivity {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
-        match PgStatActivity::query(pool).await {
-            Ok(pg_stat_activity) => {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+        os_statistics: bool,
+    ) -> bool {
+        match Collector::timed_query(PgStatActivity::query(pool, server_version_num)).await {
+            Ok(mut pg_stat_activity) => {
+                if os_statistics {
+                    ProcPid::add_to_pg_stat_activity(&mut pg_stat_activity).await;
+                }
                 trace!("pg_stat_activity: {:#?}", pg_stat_activity);
                 let current_timestamp = Local::now();
-                DATA.pg_stat_activity
-                    .write()
+                // the connection churn is the difference with the previous sample. the previous
+                // sample is cloned, so connection_churn is not locked while pg_stat_activity is.
+                let previous = DATA
+                    .pg_stat_activity
+                    .read()
                     .await
-                    .push_back((current_timestamp, pg_stat_activity.clone()));
+                    .back()
+                    .map(|(_, r)| r.clone());
+                if let Some(previous) = previous {
+                    ConnectionChurn::add_to_data(&previous, &pg_stat_activity, current_timestamp)
+                        .await;
+                }
+                Collector::timed_write(&DATA.pg_stat_activity)
+                    .await
+                    .push_back((current_timestamp, pg_stat_activity));
+                DATA.pg_stat_activity_added.send_replace(current_timestamp);
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
-    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgStatActivity>> {
-        let mut sql_rows: Vec<PgStatActivity> = query_as(
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<Vec<PgStatActivity>> {
+        let leader_pid = if server_version_num >= 130000 {
+            "leader_pid"
+        } else {
+            "null::int as leader_pid"
+        };
+        let query_id = if server_version_num >= 140000 {
+            "query_id"
+        } else {
+            "null::bigint as query_id"
+        };
+        let sql = format!(
             "
             select clock_timestamp() as timestamp,
                    datid::text::int, 
                    datname, 
                    pid,
-                   leader_pid,
+                   {},
                    usesysid::text::int,
                    usename, 
                    application_name, 
@@ -70,17 +119,17 @@ impl PgStatActivity {
                    lower(wait_event_type) as wait_event_type,
                    lower(wait_event) as wait_event,
                    state, 
-                   backend_xid::text::int,
-                   backend_xmin::text::int,
-                   query_id, 
+                   backend_xid::text::bigint,
+                   backend_xmin::text::bigint,
+                   {}, 
                    query, 
                    backend_type 
              from  pg_stat_activity 
              where pid != pg_backend_pid() 
         ",
-        )
-        .fetch_all(pool)
-        .await?;
+            leader_pid, query_id
+        );
+        let mut sql_rows: Vec<PgStatActivity> = query_as(&sql).fetch_all(pool).await?;
         sql_rows.sort_by_key(|a| *a.query_time.as_ref().unwrap_or(&0_i64));
         sql_rows.reverse();
 
@@ -1,3 +1,4 @@
+use crate::processor::Collector;
 use crate::processor::DeltaTable;
 use crate::processor::DELTATABLE;
 use crate::DATA;
@@ -8,6 +9,7 @@ use log::{trace, warn};
 use serde::{Deserialize, Serialize};
 use sqlx::{query_as, FromRow, Pool};
 
+// buffers_backend_ps and buffers_backend_fsync_ps are None since version 17, see PgStatBgWriter.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct PgStatBgWriterSum {
     pub checkpoints_timed: f64,
@@ -16,8 +18,8 @@ pub struct PgStatBgWriterSum {
     pub checkpoint_sync_time_ps: f64,
     pub buffers_checkpoint_ps: f64,
     pub buffers_clean_ps: f64,
-    pub buffers_backend_ps: f64,
-    pub buffers_backend_fsync_ps: f64,
+    pub buffers_backend_ps: Option<f64>,
+    pub buffers_backend_fsync_ps: Option<f64>,
     pub buffers_alloc_ps: f64,
 }
 
@@ -59,95 +61,90 @@ impl PgStatBgWriterSum {
             pg_stat_bgwriter.buffers_clean as f64,
         )
         .await;
-        DeltaTable::add_or_update(
-            "pg_stat_bgwriter.buffers_backend",
-            pg_stat_bgwriter.timestamp,
-            pg_stat_bgwriter.buffers_backend as f64,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_bgwriter.buffers_backend_fsync",
-            pg_stat_bgwriter.timestamp,
-            pg_stat_bgwriter.buffers_backend_fsync as f64,
-        )
-        .await;
+        // the statistics that the server version does not have are not added, so they stay None.
+        if let Some(buffers_backend) = pg_stat_bgwriter.buffers_backend {
+            DeltaTable::add_or_update(
+                "pg_stat_bgwriter.buffers_backend",
+                pg_stat_bgwriter.timestamp,
+                buffers_backend as f64,
+            )
+            .await;
+        }
+        if let Some(buffers_backend_fsync) = pg_stat_bgwriter.buffers_backend_fsync {
+            DeltaTable::add_or_update(
+                "pg_stat_bgwriter.buffers_backend_fsync",
+                pg_stat_bgwriter.timestamp,
+                buffers_backend_fsync as f64,
+            )
+            .await;
+        }
         DeltaTable::add_or_update(
             "pg_stat_bgwriter.buffers_alloc",
             pg_stat_bgwriter.timestamp,
             pg_stat_bgwriter.buffers_alloc as f64,
         )
         .await;
-        if DELTATABLE
-            .read()
-            .await
+        let deltatable = DELTATABLE.read().await;
+        if deltatable
             .get("pg_stat_bgwriter.checkpoints_req")
             .unwrap()
             .updated_value
         {
-            DATA.pg_stat_bgwriter_sum.write().await.push_back((
-                pg_stat_bgwriter.timestamp,
-                PgStatBgWriterSum {
-                    checkpoints_timed: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.checkpoints_timed")
-                        .unwrap()
-                        .delta_value,
-                    checkpoints_req: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.checkpoints_req")
-                        .unwrap()
-                        .delta_value,
-                    checkpoint_write_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.checkpoint_write_time")
-                        .unwrap()
-                        .per_second_value,
-                    checkpoint_sync_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.checkpoint_sync_time")
-                        .unwrap()
-                        .per_second_value,
-                    buffers_checkpoint_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.buffers_checkpoint")
-                        .unwrap()
-                        .per_second_value,
-                    buffers_clean_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.buffers_clean")
-                        .unwrap()
-                        .per_second_value,
-                    buffers_backend_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.buffers_backend")
-                        .unwrap()
-                        .per_second_value,
-                    buffers_backend_fsync_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.buffers_backend_fsync")
-                        .unwrap()
-                        .per_second_value,
-                    buffers_alloc_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_bgwriter.buffers_alloc")
-                        .unwrap()
-                        .per_second_value,
-                },
-            ));
+            Collector::timed_write(&DATA.pg_stat_bgwriter_sum)
+                .await
+                .push_back((
+                    pg_stat_bgwriter.timestamp,
+                    PgStatBgWriterSum {
+                        checkpoints_timed: deltatable
+                            .get("pg_stat_bgwriter.checkpoints_timed")
+                            .unwrap()
+                            .delta_value,
+                        checkpoints_req: deltatable
+                            .get("pg_stat_bgwriter.checkpoints_req")
+                            .unwrap()
+                            .delta_value,
+                        checkpoint_write_time_ps: deltatable
+                            .get("pg_stat_bgwriter.checkpoint_write_time")
+                            .unwrap()
+                            .per_second_value,
+                        checkpoint_sync_time_ps: deltatable
+                            .get("pg_stat_bgwriter.checkpoint_sync_time")
+                            .unwrap()
+                            .per_second_value,
+                        buffers_checkpoint_ps: deltatable
+                            .get("pg_stat_bgwriter.buffers_checkpoint")
+                            .unwrap()
+                            .per_second_value,
+                        buffers_clean_ps: deltatable
+                            .get("pg_stat_bgwriter.buffers_clean")
+                            .unwrap()
+                            .per_second_value,
+                        buffers_backend_ps: deltatable
+                            .get("pg_stat_bgwriter.buffers_backend")
+                            .map(|r| r.per_second_value),
+                        buffers_backend_fsync_ps: deltatable
+                            .get("pg_stat_bgwriter.buffers_backend_fsync")
+                            .map(|r| r.per_second_value),
+                        buffers_alloc_ps: deltatable
+                            .get("pg_stat_bgwriter.buffers_alloc")
+                            .unwrap()
+                            .per_second_value,
+                    },
+                ));
         }
     }
+    // the buffers written per second by the checkpointer, the background writer and, before
+    // version 17, the backends.
+    pub fn buffers_written_ps(&self) -> f64 {
+        self.buffers_checkpoint_ps
+            + self.buffers_clean_ps
+            + self.buffers_backend_ps.unwrap_or_default()
+    }
 }
 
-// this pg_stat_bgwriter is consistent with postgres version 15
+// this pg_stat_bgwriter is consistent with postgres version 15.
+// since version 17 the checkpoint columns are read from pg_stat_checkpointer, and buffers_backend
+// and buffers_backend_fsync are None, because these are only available in pg_stat_io.
 #[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
 pub struct PgStatBgWriter {
     pub timestamp: DateTime<Local>,
@@ -158,27 +155,49 @@ pub struct PgStatBgWriter {
     pub buffers_checkpoint: i64,
     pub buffers_clean: i64,
     pub maxwritten_clean: i64,
-    pub buffers_backend: i64,
-    pub buffers_backend_fsync: i64,
+    pub buffers_backend: Option<i64>,
+    pub buffers_backend_fsync: Option<i64>,
     pub buffers_alloc: i64,
     pub stats_reset: Option<DateTime<Local>>,
 }
 
 impl PgStatBgWriter {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
         //let pg_stat_bgwriter = PgStatBgWriter::query(pool).await;
-        match PgStatBgWriter::query(pool).await {
+        match Collector::timed_query(PgStatBgWriter::query(pool, server_version_num)).await {
             Ok(pg_stat_bgwriter) => {
                 trace!("pg_stat_bgwriter: {:#?}", pg_stat_bgwriter);
                 PgStatBgWriterSum::process_pg_bgwriter(pg_stat_bgwriter).await;
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
-    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<PgStatBgWriter> {
-        let stat_bgwriter: PgStatBgWriter = query_as(
+    async fn query(pool: &Pool<sqlx::Postgres>, server_version_num: i32) -> Result<PgStatBgWriter> {
+        let sql = if server_version_num >= 170000 {
+            "
+            select clock_timestamp() as timestamp,
+                   c.num_timed as checkpoints_timed, 
+                   c.num_requested as checkpoints_req, 
+                   c.write_time as checkpoint_write_time,
+                   c.sync_time as checkpoint_sync_time,
+                   c.buffers_written as buffers_checkpoint,
+                   b.buffers_clean, 
+                   b.maxwritten_clean, 
+                   null::bigint as buffers_backend,
+                   null::bigint as buffers_backend_fsync,
+                   b.buffers_alloc,
+                   b.stats_reset
+             from  pg_stat_bgwriter b
+             cross join pg_stat_checkpointer c
+        "
+        } else {
             "
             select clock_timestamp() as timestamp,
                    checkpoints_timed, 
@@ -193,10 +212,9 @@ impl PgStatBgWriter {
                    buffers_alloc,
                    stats_reset
              from  pg_stat_bgwriter 
-        ",
-        )
-        .fetch_one(pool)
-        .await?;
+        "
+        };
+        let stat_bgwriter: PgStatBgWriter = query_as(sql).fetch_one(pool).await?;
         Ok(stat_bgwriter)
     }
 }
@@ -0,0 +1,191 @@
+use crate::processor::Collector;
+use crate::processor::DeltaTable;
+use crate::processor::DELTATABLE;
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgStatCheckpointerSum {
+    pub num_timed: f64,
+    pub num_requested: f64,
+    pub num_done: f64,
+    pub restartpoints_timed: f64,
+    pub restartpoints_req: f64,
+    pub restartpoints_done: f64,
+    pub write_time_ps: f64,
+    pub sync_time_ps: f64,
+    pub buffers_written_ps: f64,
+    pub slru_written_ps: f64,
+}
+
+impl PgStatCheckpointerSum {
+    pub async fn process_pg_stat_checkpointer(pg_stat_checkpointer: PgStatCheckpointer) {
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.num_timed",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.num_timed as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.num_requested",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.num_requested as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.num_done",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.num_done.unwrap_or_default() as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.restartpoints_timed",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.restartpoints_timed as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.restartpoints_req",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.restartpoints_req as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.restartpoints_done",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.restartpoints_done as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.write_time",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.write_time,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.sync_time",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.sync_time,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.buffers_written",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.buffers_written as f64,
+        )
+        .await;
+        DeltaTable::add_or_update(
+            "pg_stat_checkpointer.slru_written",
+            pg_stat_checkpointer.timestamp,
+            pg_stat_checkpointer.slru_written.unwrap_or_default() as f64,
+        )
+        .await;
+        let deltatable = DELTATABLE.read().await;
+        if deltatable
+            .get("pg_stat_checkpointer.num_requested")
+            .unwrap()
+            .updated_value
+        {
+            let delta_value = |name: &str| deltatable.get(name).unwrap().delta_value;
+            let per_second_value = |name: &str| deltatable.get(name).unwrap().per_second_value;
+            Collector::timed_write(&DATA.pg_stat_checkpointer_sum)
+                .await
+                .push_back((
+                    pg_stat_checkpointer.timestamp,
+                    PgStatCheckpointerSum {
+                        num_timed: delta_value("pg_stat_checkpointer.num_timed"),
+                        num_requested: delta_value("pg_stat_checkpointer.num_requested"),
+                        num_done: delta_value("pg_stat_checkpointer.num_done"),
+                        restartpoints_timed: delta_value(
+                            "pg_stat_checkpointer.restartpoints_timed",
+                        ),
+                        restartpoints_req: delta_value("pg_stat_checkpointer.restartpoints_req"),
+                        restartpoints_done: delta_value("pg_stat_checkpointer.restartpoints_done"),
+                        write_time_ps: per_second_value("pg_stat_checkpointer.write_time"),
+                        sync_time_ps: per_second_value("pg_stat_checkpointer.sync_time"),
+                        buffers_written_ps: per_second_value(
+                            "pg_stat_checkpointer.buffers_written",
+                        ),
+                        slru_written_ps: per_second_value("pg_stat_checkpointer.slru_written"),
+                    },
+                ));
+        }
+    }
+}
+
+// this pg_stat_checkpointer is consistent with postgres version 17.
+// since version 18 num_done and slru_written are added, before that these are None.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatCheckpointer {
+    pub timestamp: DateTime<Local>,
+    pub num_timed: i64,
+    pub num_requested: i64,
+    pub num_done: Option<i64>,
+    pub restartpoints_timed: i64,
+    pub restartpoints_req: i64,
+    pub restartpoints_done: i64,
+    pub write_time: f64,
+    pub sync_time: f64,
+    pub buffers_written: i64,
+    pub slru_written: Option<i64>,
+    pub stats_reset: Option<DateTime<Local>>,
+}
+
+impl PgStatCheckpointer {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        // pg_stat_checkpointer exists since version 17
+        if server_version_num < 170000 {
+            return true;
+        }
+        match Collector::timed_query(PgStatCheckpointer::query(pool, server_version_num)).await {
+            Ok(pg_stat_checkpointer) => {
+                trace!("pg_stat_checkpointer: {:#?}", pg_stat_checkpointer);
+                PgStatCheckpointerSum::process_pg_stat_checkpointer(pg_stat_checkpointer).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<PgStatCheckpointer> {
+        let (num_done, slru_written) = if server_version_num >= 180000 {
+            ("num_done", "slru_written")
+        } else {
+            ("null::bigint as num_done", "null::bigint as slru_written")
+        };
+        let sql = format!(
+            "
+            select clock_timestamp() as timestamp,
+                   num_timed,
+                   num_requested,
+                   {},
+                   restartpoints_timed,
+                   restartpoints_req,
+                   restartpoints_done,
+                   write_time,
+                   sync_time,
+                   buffers_written,
+                   {},
+                   stats_reset
+             from  pg_stat_checkpointer
+        ",
+            num_done, slru_written
+        );
+        let pg_stat_checkpointer: PgStatCheckpointer = query_as(&sql).fetch_one(pool).await?;
+
+        Ok(pg_stat_checkpointer)
+    }
+}
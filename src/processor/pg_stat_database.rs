@@ -1,3 +1,4 @@
+use crate::processor::Collector;
 use crate::processor::DeltaTable;
 use crate::processor::DELTATABLE;
 use crate::DATA;
@@ -7,7 +8,13 @@ use chrono::{DateTime, Local};
 use log::{trace, warn};
 use serde::{Deserialize, Serialize};
 use sqlx::{query_as, FromRow, Pool};
+use std::collections::BTreeMap;
 
+// the statistics per database, with the database name as key.
+pub type PgStatDatabaseSumPerDatabase = BTreeMap<String, PgStatDatabaseSum>;
+
+// checksum_failures_delta is None when data checksums are disabled, and the session statistics
+// are None for versions before 14, see PgStatDatabase.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PgStatDatabaseSum {
     pub xact_commit_ps: f64,
@@ -26,16 +33,21 @@ pub struct PgStatDatabaseSum {
     pub temp_files: f64,
     pub temp_bytes: f64,
     pub deadlocks_delta: f64,
-    pub checksum_failures_delta: f64,
-    pub sessions: f64,
-    pub sessions_abandoned: f64,
-    pub sessions_fatal: f64,
-    pub sessions_killed: f64,
+    pub checksum_failures_delta: Option<f64>,
+    pub sessions: Option<f64>,
+    pub sessions_abandoned: Option<f64>,
+    pub sessions_fatal: Option<f64>,
+    pub sessions_killed: Option<f64>,
 }
 
 impl PgStatDatabaseSum {
     pub async fn process_pg_stat_database(pg_stat_database: Vec<PgStatDatabase>) {
         let pg_stat_database_timestamp = pg_stat_database.last().map(|r| r.timestamp).unwrap();
+        PgStatDatabaseSum::process_pg_stat_database_per_database(
+            &pg_stat_database,
+            pg_stat_database_timestamp,
+        )
+        .await;
         DeltaTable::add_or_update(
             "pg_stat_database.xact_commit",
             pg_stat_database_timestamp,
@@ -180,223 +192,356 @@ impl PgStatDatabaseSum {
                 .fold(0_f64, |sum, b| sum + (b as f64)),
         )
         .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.checksum_failures",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.checksum_failures.unwrap_or_default())
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.session_time",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.session_time)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.active_time",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.active_time)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.idle_in_transaction_time",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.idle_in_transaction_time)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.sessions",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.sessions)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.sessions_abandoned",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.sessions_abandoned)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.sessions_fatal",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.sessions_fatal)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_database.sessions_killed",
-            pg_stat_database_timestamp,
-            pg_stat_database
-                .iter()
-                .map(|r| r.sessions_killed)
-                .fold(0_f64, |sum, b| sum + (b as f64)),
-        )
-        .await;
+        // the statistics that are None for every database are not added, so they stay None.
+        if let Some(checksum_failures) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.checksum_failures)
+            .map(|r| r as f64)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.checksum_failures",
+                pg_stat_database_timestamp,
+                checksum_failures,
+            )
+            .await;
+        }
+        if let Some(session_time) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.session_time)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.session_time",
+                pg_stat_database_timestamp,
+                session_time,
+            )
+            .await;
+        }
+        if let Some(active_time) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.active_time)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.active_time",
+                pg_stat_database_timestamp,
+                active_time,
+            )
+            .await;
+        }
+        if let Some(idle_in_transaction_time) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.idle_in_transaction_time)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.idle_in_transaction_time",
+                pg_stat_database_timestamp,
+                idle_in_transaction_time,
+            )
+            .await;
+        }
+        if let Some(sessions) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.sessions)
+            .map(|r| r as f64)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.sessions",
+                pg_stat_database_timestamp,
+                sessions,
+            )
+            .await;
+        }
+        if let Some(sessions_abandoned) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.sessions_abandoned)
+            .map(|r| r as f64)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.sessions_abandoned",
+                pg_stat_database_timestamp,
+                sessions_abandoned,
+            )
+            .await;
+        }
+        if let Some(sessions_fatal) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.sessions_fatal)
+            .map(|r| r as f64)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.sessions_fatal",
+                pg_stat_database_timestamp,
+                sessions_fatal,
+            )
+            .await;
+        }
+        if let Some(sessions_killed) = pg_stat_database
+            .iter()
+            .filter_map(|r| r.sessions_killed)
+            .map(|r| r as f64)
+            .reduce(|sum, r| sum + r)
+        {
+            DeltaTable::add_or_update(
+                "pg_stat_database.sessions_killed",
+                pg_stat_database_timestamp,
+                sessions_killed,
+            )
+            .await;
+        }
         // only add to DATA if updated_value is true, which means that there have been two
         // additions, and thus a DELTA (difference) is calculated.
-        if DELTATABLE
-            .read()
-            .await
+        let deltatable = DELTATABLE.read().await;
+        if deltatable
             .get("pg_stat_database.xact_commit")
             .unwrap()
             .updated_value
         {
-            DATA.pg_stat_database_sum.write().await.push_back((
-                pg_stat_database_timestamp,
+            Collector::timed_write(&DATA.pg_stat_database_sum)
+                .await
+                .push_back((
+                    pg_stat_database_timestamp,
+                    PgStatDatabaseSum {
+                        xact_commit_ps: deltatable
+                            .get("pg_stat_database.xact_commit")
+                            .unwrap()
+                            .per_second_value,
+                        xact_rollback_ps: deltatable
+                            .get("pg_stat_database.xact_rollback")
+                            .unwrap()
+                            .per_second_value,
+                        blks_read_ps: deltatable
+                            .get("pg_stat_database.blks_read")
+                            .unwrap()
+                            .per_second_value,
+                        blks_hit_ps: deltatable
+                            .get("pg_stat_database.blks_hit")
+                            .unwrap()
+                            .per_second_value,
+                        tup_returned_ps: deltatable
+                            .get("pg_stat_database.tup_returned")
+                            .unwrap()
+                            .per_second_value,
+                        tup_fetched_ps: deltatable
+                            .get("pg_stat_database.tup_fetched")
+                            .unwrap()
+                            .per_second_value,
+                        tup_inserted_ps: deltatable
+                            .get("pg_stat_database.tup_inserted")
+                            .unwrap()
+                            .per_second_value,
+                        tup_updated_ps: deltatable
+                            .get("pg_stat_database.tup_updated")
+                            .unwrap()
+                            .per_second_value,
+                        tup_deleted_ps: deltatable
+                            .get("pg_stat_database.tup_deleted")
+                            .unwrap()
+                            .per_second_value,
+                        blk_read_time_ps: deltatable
+                            .get("pg_stat_database.blk_read_time")
+                            .unwrap()
+                            .per_second_value,
+                        blk_write_time_ps: deltatable
+                            .get("pg_stat_database.blk_write_time")
+                            .unwrap()
+                            .per_second_value,
+                        numbackends: deltatable
+                            .get("pg_stat_database.numbackends")
+                            .unwrap()
+                            .last_value,
+                        conflicts_delta: deltatable
+                            .get("pg_stat_database.conflicts")
+                            .unwrap()
+                            .delta_value,
+                        temp_files: deltatable
+                            .get("pg_stat_database.temp_files")
+                            .unwrap()
+                            .last_value,
+                        temp_bytes: deltatable
+                            .get("pg_stat_database.temp_bytes")
+                            .unwrap()
+                            .last_value,
+                        deadlocks_delta: deltatable
+                            .get("pg_stat_database.deadlocks")
+                            .unwrap()
+                            .delta_value,
+                        checksum_failures_delta: deltatable
+                            .get("pg_stat_database.checksum_failures")
+                            .map(|r| r.delta_value),
+                        sessions: deltatable
+                            .get("pg_stat_database.sessions")
+                            .map(|r| r.last_value),
+                        sessions_abandoned: deltatable
+                            .get("pg_stat_database.sessions_abandoned")
+                            .map(|r| r.last_value),
+                        sessions_fatal: deltatable
+                            .get("pg_stat_database.sessions_fatal")
+                            .map(|r| r.last_value),
+                        sessions_killed: deltatable
+                            .get("pg_stat_database.sessions_killed")
+                            .map(|r| r.last_value),
+                    },
+                ));
+        }
+    }
+    // the same statistics per database, so the load can be attributed to a database. the row
+    // for the shared objects (datname is null) is only part of the cluster wide sum.
+    async fn process_pg_stat_database_per_database(
+        pg_stat_database: &[PgStatDatabase],
+        pg_stat_database_timestamp: DateTime<Local>,
+    ) {
+        let mut deltatable = DeltaTable::keyed(pg_stat_database_timestamp).await;
+        let mut names: Vec<&str> = Vec::new();
+        for row in pg_stat_database.iter() {
+            let datname = match row.datname.as_deref() {
+                Some(datname) => datname,
+                None => continue,
+            };
+            let statistics = [
+                ("pg_stat_database.xact_commit", Some(row.xact_commit as f64)),
+                (
+                    "pg_stat_database.xact_rollback",
+                    Some(row.xact_rollback as f64),
+                ),
+                ("pg_stat_database.blks_read", Some(row.blks_read as f64)),
+                ("pg_stat_database.blks_hit", Some(row.blks_hit as f64)),
+                (
+                    "pg_stat_database.tup_returned",
+                    Some(row.tup_returned as f64),
+                ),
+                ("pg_stat_database.tup_fetched", Some(row.tup_fetched as f64)),
+                (
+                    "pg_stat_database.tup_inserted",
+                    Some(row.tup_inserted as f64),
+                ),
+                ("pg_stat_database.tup_updated", Some(row.tup_updated as f64)),
+                ("pg_stat_database.tup_deleted", Some(row.tup_deleted as f64)),
+                ("pg_stat_database.blk_read_time", Some(row.blk_read_time)),
+                ("pg_stat_database.blk_write_time", Some(row.blk_write_time)),
+                ("pg_stat_database.numbackends", Some(row.numbackends as f64)),
+                ("pg_stat_database.conflicts", Some(row.conflicts as f64)),
+                ("pg_stat_database.temp_files", Some(row.temp_files as f64)),
+                ("pg_stat_database.temp_bytes", Some(row.temp_bytes as f64)),
+                ("pg_stat_database.deadlocks", Some(row.deadlocks as f64)),
+                (
+                    "pg_stat_database.checksum_failures",
+                    row.checksum_failures.map(|r| r as f64),
+                ),
+                ("pg_stat_database.sessions", row.sessions.map(|r| r as f64)),
+                (
+                    "pg_stat_database.sessions_abandoned",
+                    row.sessions_abandoned.map(|r| r as f64),
+                ),
+                (
+                    "pg_stat_database.sessions_fatal",
+                    row.sessions_fatal.map(|r| r as f64),
+                ),
+                (
+                    "pg_stat_database.sessions_killed",
+                    row.sessions_killed.map(|r| r as f64),
+                ),
+            ];
+            for (name, value) in statistics {
+                if let Some(value) = value {
+                    deltatable.add_or_update(name, datname, value);
+                }
+            }
+            names = statistics.iter().map(|(name, _)| *name).collect();
+        }
+        // remove the databases that are dropped.
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+
+        let mut pg_stat_database_sum_per_database = PgStatDatabaseSumPerDatabase::new();
+        for datname in pg_stat_database.iter().filter_map(|r| r.datname.as_deref()) {
+            let delta = |name: &'static str| deltatable.get(name, datname);
+            // only add the database if a delta is calculated, see process_pg_stat_database.
+            if !delta("pg_stat_database.xact_commit").is_some_and(|r| r.updated_value) {
+                continue;
+            }
+            pg_stat_database_sum_per_database.insert(
+                datname.to_string(),
                 PgStatDatabaseSum {
-                    xact_commit_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.xact_commit")
-                        .unwrap()
+                    xact_commit_ps: delta("pg_stat_database.xact_commit")
+                        .unwrap_or_default()
                         .per_second_value,
-                    xact_rollback_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.xact_rollback")
-                        .unwrap()
+                    xact_rollback_ps: delta("pg_stat_database.xact_rollback")
+                        .unwrap_or_default()
                         .per_second_value,
-                    blks_read_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.blks_read")
-                        .unwrap()
+                    blks_read_ps: delta("pg_stat_database.blks_read")
+                        .unwrap_or_default()
                         .per_second_value,
-                    blks_hit_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.blks_hit")
-                        .unwrap()
+                    blks_hit_ps: delta("pg_stat_database.blks_hit")
+                        .unwrap_or_default()
                         .per_second_value,
-                    tup_returned_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.tup_returned")
-                        .unwrap()
+                    tup_returned_ps: delta("pg_stat_database.tup_returned")
+                        .unwrap_or_default()
                         .per_second_value,
-                    tup_fetched_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.tup_fetched")
-                        .unwrap()
+                    tup_fetched_ps: delta("pg_stat_database.tup_fetched")
+                        .unwrap_or_default()
                         .per_second_value,
-                    tup_inserted_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.tup_inserted")
-                        .unwrap()
+                    tup_inserted_ps: delta("pg_stat_database.tup_inserted")
+                        .unwrap_or_default()
                         .per_second_value,
-                    tup_updated_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.tup_updated")
-                        .unwrap()
+                    tup_updated_ps: delta("pg_stat_database.tup_updated")
+                        .unwrap_or_default()
                         .per_second_value,
-                    tup_deleted_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.tup_deleted")
-                        .unwrap()
+                    tup_deleted_ps: delta("pg_stat_database.tup_deleted")
+                        .unwrap_or_default()
                         .per_second_value,
-                    blk_read_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.blk_read_time")
-                        .unwrap()
+                    blk_read_time_ps: delta("pg_stat_database.blk_read_time")
+                        .unwrap_or_default()
                         .per_second_value,
-                    blk_write_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.blk_write_time")
-                        .unwrap()
+                    blk_write_time_ps: delta("pg_stat_database.blk_write_time")
+                        .unwrap_or_default()
                         .per_second_value,
-                    numbackends: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.numbackends")
-                        .unwrap()
+                    numbackends: delta("pg_stat_database.numbackends")
+                        .unwrap_or_default()
                         .last_value,
-                    conflicts_delta: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.conflicts")
-                        .unwrap()
+                    conflicts_delta: delta("pg_stat_database.conflicts")
+                        .unwrap_or_default()
                         .delta_value,
-                    temp_files: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.temp_files")
-                        .unwrap()
+                    temp_files: delta("pg_stat_database.temp_files")
+                        .unwrap_or_default()
                         .last_value,
-                    temp_bytes: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.temp_bytes")
-                        .unwrap()
+                    temp_bytes: delta("pg_stat_database.temp_bytes")
+                        .unwrap_or_default()
                         .last_value,
-                    deadlocks_delta: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.deadlocks")
-                        .unwrap()
-                        .delta_value,
-                    checksum_failures_delta: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.checksum_failures")
-                        .unwrap()
+                    deadlocks_delta: delta("pg_stat_database.deadlocks")
+                        .unwrap_or_default()
                         .delta_value,
-                    sessions: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.sessions")
-                        .unwrap()
-                        .last_value,
-                    sessions_abandoned: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.sessions_abandoned")
-                        .unwrap()
-                        .last_value,
-                    sessions_fatal: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.sessions_fatal")
-                        .unwrap()
-                        .last_value,
-                    sessions_killed: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_database.sessions_killed")
-                        .unwrap()
-                        .last_value,
+                    checksum_failures_delta: delta("pg_stat_database.checksum_failures")
+                        .map(|r| r.delta_value),
+                    sessions: delta("pg_stat_database.sessions").map(|r| r.last_value),
+                    sessions_abandoned: delta("pg_stat_database.sessions_abandoned")
+                        .map(|r| r.last_value),
+                    sessions_fatal: delta("pg_stat_database.sessions_fatal").map(|r| r.last_value),
+                    sessions_killed: delta("pg_stat_database.sessions_killed")
+                        .map(|r| r.last_value),
                 },
-            ));
+            );
+        }
+        drop(deltatable);
+        if !pg_stat_database_sum_per_database.is_empty() {
+            Collector::timed_write(&DATA.pg_stat_database_sum_per_database)
+                .await
+                .push_back((
+                    pg_stat_database_timestamp,
+                    pg_stat_database_sum_per_database,
+                ));
         }
     }
 }
 
-// this pg_stat_database is consistent with postgres version 15
+// this pg_stat_database is consistent with postgres version 15.
+// the session statistics are added in version 14, and are None for older versions.
 #[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
 pub struct PgStatDatabase {
     pub timestamp: DateTime<Local>,
@@ -420,30 +565,55 @@ pub struct PgStatDatabase {
     pub checksum_last_failure: Option<DateTime<Local>>,
     pub blk_read_time: f64,
     pub blk_write_time: f64,
-    pub session_time: f64,
-    pub active_time: f64,
-    pub idle_in_transaction_time: f64,
-    pub sessions: i64,
-    pub sessions_abandoned: i64,
-    pub sessions_fatal: i64,
-    pub sessions_killed: i64,
+    pub session_time: Option<f64>,
+    pub active_time: Option<f64>,
+    pub idle_in_transaction_time: Option<f64>,
+    pub sessions: Option<i64>,
+    pub sessions_abandoned: Option<i64>,
+    pub sessions_fatal: Option<i64>,
+    pub sessions_killed: Option<i64>,
     pub stats_reset: Option<DateTime<Local>>,
 }
 
 impl PgStatDatabase {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
-        match PgStatDatabase::query(pool).await {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        match Collector::timed_query(PgStatDatabase::query(pool, server_version_num)).await {
             Ok(pg_stat_database) => {
                 trace!("pg_stat_database: {:#?}", pg_stat_database);
-                PgStatDatabaseSum::process_pg_stat_database(pg_stat_database).await
+                PgStatDatabaseSum::process_pg_stat_database(pg_stat_database).await;
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
-    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgStatDatabase>> {
-        let stat_database: Vec<PgStatDatabase> = query_as(
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<Vec<PgStatDatabase>> {
+        let session_statistics = if server_version_num >= 140000 {
+            "session_time, 
+                   active_time,
+                   idle_in_transaction_time,
+                   sessions,
+                   sessions_abandoned,
+                   sessions_fatal,
+                   sessions_killed,"
+        } else {
+            "null::double precision as session_time, 
+                   null::double precision as active_time,
+                   null::double precision as idle_in_transaction_time,
+                   null::bigint as sessions,
+                   null::bigint as sessions_abandoned,
+                   null::bigint as sessions_fatal,
+                   null::bigint as sessions_killed,"
+        };
+        let sql = format!(
             "
             select clock_timestamp() as timestamp,
                    datid::text::int, 
@@ -466,19 +636,13 @@ impl PgStatDatabase {
                    checksum_last_failure,
                    blk_read_time,
                    blk_write_time, 
-                   session_time, 
-                   active_time,
-                   idle_in_transaction_time,
-                   sessions,
-                   sessions_abandoned,
-                   sessions_fatal,
-                   sessions_killed,
+                   {}
                    stats_reset
              from  pg_stat_database 
         ",
-        )
-        .fetch_all(pool)
-        .await?;
+            session_statistics
+        );
+        let stat_database: Vec<PgStatDatabase> = query_as(&sql).fetch_all(pool).await?;
 
         Ok(stat_database)
     }
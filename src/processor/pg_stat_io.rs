@@ -0,0 +1,219 @@
+use crate::processor::Collector;
+use crate::processor::{DeltaTable, StatisticsDelta};
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgStatIoSum {
+    pub backend_type: String,
+    pub object: String,
+    pub context: String,
+    pub reads_ps: f64,
+    pub read_bytes_ps: f64,
+    pub read_time_ps: f64,
+    pub writes_ps: f64,
+    pub write_bytes_ps: f64,
+    pub write_time_ps: f64,
+    pub writebacks_ps: f64,
+    pub extends_ps: f64,
+    pub extend_bytes_ps: f64,
+    pub hits_ps: f64,
+    pub evictions_ps: f64,
+    pub reuses_ps: f64,
+    pub fsyncs_ps: f64,
+    pub fsync_time_ps: f64,
+}
+
+impl PgStatIoSum {
+    pub async fn process_pg_stat_io(pg_stat_io: Vec<PgStatIo>) {
+        let pg_stat_io_timestamp = match pg_stat_io.last() {
+            Some(row) => row.timestamp,
+            None => return,
+        };
+        let mut deltatable = DeltaTable::keyed(pg_stat_io_timestamp).await;
+        for row in pg_stat_io.iter() {
+            let key = row.key();
+            for (name, value) in [
+                ("pg_stat_io.reads", row.reads.unwrap_or_default() as f64),
+                ("pg_stat_io.read_bytes", row.read_bytes.unwrap_or_default()),
+                ("pg_stat_io.read_time", row.read_time.unwrap_or_default()),
+                ("pg_stat_io.writes", row.writes.unwrap_or_default() as f64),
+                (
+                    "pg_stat_io.write_bytes",
+                    row.write_bytes.unwrap_or_default(),
+                ),
+                ("pg_stat_io.write_time", row.write_time.unwrap_or_default()),
+                (
+                    "pg_stat_io.writebacks",
+                    row.writebacks.unwrap_or_default() as f64,
+                ),
+                ("pg_stat_io.extends", row.extends.unwrap_or_default() as f64),
+                (
+                    "pg_stat_io.extend_bytes",
+                    row.extend_bytes.unwrap_or_default(),
+                ),
+                ("pg_stat_io.hits", row.hits.unwrap_or_default() as f64),
+                (
+                    "pg_stat_io.evictions",
+                    row.evictions.unwrap_or_default() as f64,
+                ),
+                ("pg_stat_io.reuses", row.reuses.unwrap_or_default() as f64),
+                ("pg_stat_io.fsyncs", row.fsyncs.unwrap_or_default() as f64),
+                ("pg_stat_io.fsync_time", row.fsync_time.unwrap_or_default()),
+            ] {
+                deltatable.add_or_update(name, &key, value);
+            }
+        }
+
+        // only the backend_type, object and context combinations that performed IO in the last
+        // interval are added to DATA.
+        let mut pg_stat_io_sum: Vec<PgStatIoSum> = Vec::new();
+        for row in pg_stat_io.iter() {
+            let key = row.key();
+            match deltatable.get("pg_stat_io.reads", &key) {
+                Some(reads) if reads.updated_value => {}
+                _ => continue,
+            };
+            let per_second_value =
+                |delta: Option<StatisticsDelta>| delta.map_or(0_f64, |r| r.per_second_value);
+            let sum = PgStatIoSum {
+                backend_type: row.backend_type.clone(),
+                object: row.object.clone(),
+                context: row.context.clone(),
+                reads_ps: per_second_value(deltatable.get("pg_stat_io.reads", &key)),
+                read_bytes_ps: per_second_value(deltatable.get("pg_stat_io.read_bytes", &key)),
+                read_time_ps: per_second_value(deltatable.get("pg_stat_io.read_time", &key)),
+                writes_ps: per_second_value(deltatable.get("pg_stat_io.writes", &key)),
+                write_bytes_ps: per_second_value(deltatable.get("pg_stat_io.write_bytes", &key)),
+                write_time_ps: per_second_value(deltatable.get("pg_stat_io.write_time", &key)),
+                writebacks_ps: per_second_value(deltatable.get("pg_stat_io.writebacks", &key)),
+                extends_ps: per_second_value(deltatable.get("pg_stat_io.extends", &key)),
+                extend_bytes_ps: per_second_value(deltatable.get("pg_stat_io.extend_bytes", &key)),
+                hits_ps: per_second_value(deltatable.get("pg_stat_io.hits", &key)),
+                evictions_ps: per_second_value(deltatable.get("pg_stat_io.evictions", &key)),
+                reuses_ps: per_second_value(deltatable.get("pg_stat_io.reuses", &key)),
+                fsyncs_ps: per_second_value(deltatable.get("pg_stat_io.fsyncs", &key)),
+                fsync_time_ps: per_second_value(deltatable.get("pg_stat_io.fsync_time", &key)),
+            };
+            if sum.reads_ps
+                + sum.writes_ps
+                + sum.writebacks_ps
+                + sum.extends_ps
+                + sum.hits_ps
+                + sum.evictions_ps
+                + sum.reuses_ps
+                + sum.fsyncs_ps
+                > 0_f64
+            {
+                pg_stat_io_sum.push(sum);
+            }
+        }
+        drop(deltatable);
+        Collector::timed_write(&DATA.pg_stat_io_sum)
+            .await
+            .push_back((pg_stat_io_timestamp, pg_stat_io_sum));
+    }
+}
+
+// this pg_stat_io is consistent with postgres version 16.
+// since version 18 op_bytes is removed, and the number of bytes is read from the byte columns.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatIo {
+    pub timestamp: DateTime<Local>,
+    pub backend_type: String,
+    pub object: String,
+    pub context: String,
+    pub reads: Option<i64>,
+    pub read_bytes: Option<f64>,
+    pub read_time: Option<f64>,
+    pub writes: Option<i64>,
+    pub write_bytes: Option<f64>,
+    pub write_time: Option<f64>,
+    pub writebacks: Option<i64>,
+    pub writeback_time: Option<f64>,
+    pub extends: Option<i64>,
+    pub extend_bytes: Option<f64>,
+    pub extend_time: Option<f64>,
+    pub hits: Option<i64>,
+    pub evictions: Option<i64>,
+    pub reuses: Option<i64>,
+    pub fsyncs: Option<i64>,
+    pub fsync_time: Option<f64>,
+    pub stats_reset: Option<DateTime<Local>>,
+}
+
+impl PgStatIo {
+    fn key(&self) -> String {
+        format!("{}.{}.{}", self.backend_type, self.object, self.context)
+    }
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        // pg_stat_io exists since version 16
+        if server_version_num < 160000 {
+            return true;
+        }
+        match Collector::timed_query(PgStatIo::query(pool, server_version_num)).await {
+            Ok(pg_stat_io) => {
+                trace!("pg_stat_io: {:#?}", pg_stat_io);
+                PgStatIoSum::process_pg_stat_io(pg_stat_io).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>, server_version_num: i32) -> Result<Vec<PgStatIo>> {
+        let (read_bytes, write_bytes, extend_bytes) = if server_version_num >= 180000 {
+            (
+                "read_bytes::double precision",
+                "write_bytes::double precision",
+                "extend_bytes::double precision",
+            )
+        } else {
+            (
+                "(reads * op_bytes)::double precision as read_bytes",
+                "(writes * op_bytes)::double precision as write_bytes",
+                "(extends * op_bytes)::double precision as extend_bytes",
+            )
+        };
+        let sql = format!(
+            "
+            select clock_timestamp() as timestamp,
+                   backend_type,
+                   object,
+                   context,
+                   reads,
+                   {},
+                   read_time,
+                   writes,
+                   {},
+                   write_time,
+                   writebacks,
+                   writeback_time,
+                   extends,
+                   {},
+                   extend_time,
+                   hits,
+                   evictions,
+                   reuses,
+                   fsyncs,
+                   fsync_time,
+                   stats_reset
+             from  pg_stat_io
+        ",
+            read_bytes, write_bytes, extend_bytes
+        );
+        let pg_stat_io: Vec<PgStatIo> = query_as(&sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_io)
+    }
+}
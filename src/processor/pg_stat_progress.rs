@@ -0,0 +1,118 @@
+use crate::processor::Collector;
+use crate::DATA;
+use anyhow::Result;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+// the progress views of vacuum, analyze, create index and cluster combined into one row type.
+// blocks_total and blocks_done are the columns of the view that best show the progress, for
+// vacuum these are the heap blocks scanned or vacuumed depending on the phase, for create index
+// the tuples are used when blocks are not reported.
+// relation is the relation name for the database pas is connected to, and the oid of the
+// relation for other databases: the catalog of the connected database would resolve the oid of
+// another database to an unrelated relation.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatProgress {
+    pub pid: i32,
+    pub datname: Option<String>,
+    pub relation: Option<String>,
+    pub command: String,
+    pub phase: Option<String>,
+    pub blocks_total: Option<i64>,
+    pub blocks_done: Option<i64>,
+}
+
+impl PgStatProgress {
+    pub fn percent_done(&self) -> Option<f64> {
+        match (self.blocks_total, self.blocks_done) {
+            (Some(total), Some(done)) if total > 0 => Some(done as f64 / total as f64 * 100_f64),
+            _ => None,
+        }
+    }
+    // the progress rows get the timestamp of the last pg_stat_activity sample, so they can be
+    // matched with the backends by pid.
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        let timestamp = match DATA.pg_stat_activity.read().await.back() {
+            Some((timestamp, _)) => *timestamp,
+            None => return true,
+        };
+        match Collector::timed_query(PgStatProgress::query(pool, server_version_num)).await {
+            Ok(pg_stat_progress) => {
+                trace!("pg_stat_progress: {:#?}", pg_stat_progress);
+                if !pg_stat_progress.is_empty() {
+                    Collector::timed_write(&DATA.pg_stat_progress)
+                        .await
+                        .push_back((timestamp, pg_stat_progress));
+                }
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> Result<Vec<PgStatProgress>> {
+        let mut sql = String::from(
+            "
+            select pid,
+                   datname::text,
+                   case when datname = current_database() then relid::regclass::text else relid::text end as relation,
+                   'vacuum' as command,
+                   phase,
+                   heap_blks_total as blocks_total,
+                   case when phase = 'vacuuming heap' then heap_blks_vacuumed
+                        else heap_blks_scanned
+                   end as blocks_done
+             from  pg_stat_progress_vacuum
+        ",
+        );
+        // pg_stat_progress_create_index and pg_stat_progress_cluster exist since version 12
+        if server_version_num >= 120000 {
+            sql += "
+            union all
+            select pid,
+                   datname::text,
+                   case when datname = current_database() then relid::regclass::text else relid::text end as relation,
+                   lower(command) as command,
+                   phase,
+                   case when blocks_total > 0 then blocks_total else tuples_total end as blocks_total,
+                   case when blocks_total > 0 then blocks_done else tuples_done end as blocks_done
+             from  pg_stat_progress_create_index
+            union all
+            select pid,
+                   datname::text,
+                   case when datname = current_database() then relid::regclass::text else relid::text end as relation,
+                   lower(command) as command,
+                   phase,
+                   heap_blks_total as blocks_total,
+                   heap_blks_scanned as blocks_done
+             from  pg_stat_progress_cluster
+        ";
+        }
+        // pg_stat_progress_analyze exists since version 13
+        if server_version_num >= 130000 {
+            sql += "
+            union all
+            select pid,
+                   datname::text,
+                   case when datname = current_database() then relid::regclass::text else relid::text end as relation,
+                   'analyze' as command,
+                   phase,
+                   sample_blks_total as blocks_total,
+                   sample_blks_scanned as blocks_done
+             from  pg_stat_progress_analyze
+        ";
+        }
+        let pg_stat_progress: Vec<PgStatProgress> = query_as(&sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_progress)
+    }
+}
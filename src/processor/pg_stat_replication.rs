@@ -0,0 +1,72 @@
+use crate::processor::Collector;
+use crate::DATA;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+// this pg_stat_replication is consistent with postgres version 15.
+// the lag in bytes is calculated against the current wal position, which is the received wal
+// position if the database is a (cascading) standby.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatReplication {
+    pub timestamp: DateTime<Local>,
+    pub pid: i32,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    pub state: Option<String>,
+    pub sync_state: Option<String>,
+    pub sent_lag_bytes: Option<f64>,
+    pub write_lag_bytes: Option<f64>,
+    pub flush_lag_bytes: Option<f64>,
+    pub replay_lag_bytes: Option<f64>,
+    pub write_lag: Option<f64>,
+    pub flush_lag: Option<f64>,
+    pub replay_lag: Option<f64>,
+}
+
+impl PgStatReplication {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgStatReplication::query(pool)).await {
+            Ok(pg_stat_replication) => {
+                trace!("pg_stat_replication: {:#?}", pg_stat_replication);
+                Collector::timed_write(&DATA.pg_stat_replication)
+                    .await
+                    .push_back((Local::now(), pg_stat_replication));
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgStatReplication>> {
+        let sql = "
+            with current_wal as (
+              select case when pg_is_in_recovery() then pg_last_wal_receive_lsn()
+                          else pg_current_wal_lsn()
+                     end as lsn
+            )
+            select clock_timestamp() as timestamp,
+                   r.pid,
+                   r.application_name,
+                   r.client_addr::text,
+                   r.state,
+                   r.sync_state,
+                   pg_wal_lsn_diff(c.lsn, r.sent_lsn)::double precision as sent_lag_bytes,
+                   pg_wal_lsn_diff(c.lsn, r.write_lsn)::double precision as write_lag_bytes,
+                   pg_wal_lsn_diff(c.lsn, r.flush_lsn)::double precision as flush_lag_bytes,
+                   pg_wal_lsn_diff(c.lsn, r.replay_lsn)::double precision as replay_lag_bytes,
+                   extract(epoch from r.write_lag)::double precision as write_lag,
+                   extract(epoch from r.flush_lag)::double precision as flush_lag,
+                   extract(epoch from r.replay_lag)::double precision as replay_lag
+             from  pg_stat_replication r
+             cross join current_wal c
+        ";
+        let pg_stat_replication: Vec<PgStatReplication> = query_as(sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_replication)
+    }
+}
@@ -0,0 +1,187 @@
+use crate::processor::Collector;
+use crate::processor::DeltaTable;
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{info, trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, query_scalar, Executor, FromRow, Pool};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgStatStatementsQueryid {
+    pub queryid: i64,
+    pub calls_delta: f64,
+    pub total_exec_time_delta: f64,
+    pub rows_delta: f64,
+    pub shared_blks_read_delta: f64,
+    pub shared_blks_hit_delta: f64,
+    pub wal_bytes_delta: f64,
+    pub calls_ps: f64,
+}
+
+impl PgStatStatementsQueryid {
+    pub async fn process_pg_stat_statements(pg_stat_statements: Vec<PgStatStatements>) {
+        let pg_stat_statements_timestamp = match pg_stat_statements.last() {
+            Some(row) => row.timestamp,
+            None => return,
+        };
+        let names = [
+            "pg_stat_statements.calls",
+            "pg_stat_statements.total_exec_time",
+            "pg_stat_statements.rows",
+            "pg_stat_statements.shared_blks_read",
+            "pg_stat_statements.shared_blks_hit",
+            "pg_stat_statements.wal_bytes",
+        ];
+        let mut deltatable = DeltaTable::keyed(pg_stat_statements_timestamp).await;
+        for row in pg_stat_statements.iter() {
+            let queryid = row.queryid.to_string();
+            for (name, value) in names.iter().zip([
+                row.calls as f64,
+                row.total_exec_time,
+                row.rows as f64,
+                row.shared_blks_read as f64,
+                row.shared_blks_hit as f64,
+                row.wal_bytes.unwrap_or_default(),
+            ]) {
+                deltatable.add_or_update(name, &queryid, value);
+            }
+        }
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+
+        // only queryids that have been executed in the last interval are added to DATA, this
+        // keeps the size of a sample limited to the queries that actually were running.
+        let mut pg_stat_statements_queryid: Vec<PgStatStatementsQueryid> = Vec::new();
+        for row in pg_stat_statements.iter() {
+            let queryid = row.queryid.to_string();
+            let calls = match deltatable.get("pg_stat_statements.calls", &queryid) {
+                Some(calls) if calls.updated_value && calls.delta_value > 0_f64 => calls,
+                _ => continue,
+            };
+            let delta_value = |name: &'static str| {
+                deltatable
+                    .get(name, &queryid)
+                    .map_or(0_f64, |r| r.delta_value)
+            };
+            pg_stat_statements_queryid.push(PgStatStatementsQueryid {
+                queryid: row.queryid,
+                calls_delta: calls.delta_value,
+                total_exec_time_delta: delta_value("pg_stat_statements.total_exec_time"),
+                rows_delta: delta_value("pg_stat_statements.rows"),
+                shared_blks_read_delta: delta_value("pg_stat_statements.shared_blks_read"),
+                shared_blks_hit_delta: delta_value("pg_stat_statements.shared_blks_hit"),
+                wal_bytes_delta: delta_value("pg_stat_statements.wal_bytes"),
+                calls_ps: calls.per_second_value,
+            });
+        }
+        drop(deltatable);
+        Collector::timed_write(&DATA.pg_stat_statements)
+            .await
+            .push_back((pg_stat_statements_timestamp, pg_stat_statements_queryid));
+    }
+}
+
+// this pg_stat_statements is consistent with pg_stat_statements version 1.10 (postgres version 15).
+// the rows are summed per queryid, which removes the userid, dbid and toplevel dimensions.
+// before extension version 1.8 total_exec_time is read from total_time, and wal_bytes is None.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatStatements {
+    pub timestamp: DateTime<Local>,
+    pub queryid: i64,
+    pub calls: i64,
+    pub total_exec_time: f64,
+    pub rows: i64,
+    pub shared_blks_read: i64,
+    pub shared_blks_hit: i64,
+    pub wal_bytes: Option<f64>,
+}
+
+impl PgStatStatements {
+    // the version of the pg_stat_statements extension in the database pas connects to, which
+    // determines the columns. this is read once per connection pool: when the extension is not
+    // installed, or cannot be read because it is not in shared_preload_libraries, the statistics
+    // are not collected.
+    pub async fn extension_version(pool: &Pool<sqlx::Postgres>) -> Option<String> {
+        let extversion: Option<String> = match query_scalar(
+            "select extversion from pg_extension where extname = 'pg_stat_statements'",
+        )
+        .fetch_optional(pool)
+        .await
+        {
+            Ok(extversion) => extversion,
+            Err(error) => {
+                warn!("Reading the pg_stat_statements version failed: {:?}", error);
+                return None;
+            }
+        };
+        let Some(extversion) = extversion else {
+            info!("The pg_stat_statements extension is not installed, it is not collected.");
+            return None;
+        };
+        if let Err(error) = pool
+            .execute("select queryid from pg_stat_statements limit 1")
+            .await
+        {
+            info!(
+                "pg_stat_statements cannot be read, it is not collected: {}",
+                error
+            );
+            return None;
+        }
+        info!("pg_stat_statements version {} is collected.", extversion);
+        Some(extversion)
+    }
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>, extversion: &str) -> bool {
+        match Collector::timed_query(PgStatStatements::query(pool, extversion)).await {
+            Ok(pg_stat_statements) => {
+                trace!("pg_stat_statements: {:#?}", pg_stat_statements);
+                PgStatStatementsQueryid::process_pg_stat_statements(pg_stat_statements).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>, extversion: &str) -> Result<Vec<PgStatStatements>> {
+        // the extension version is major.minor, such as 1.10.
+        let version: Vec<u32> = extversion
+            .split('.')
+            .map(|r| r.parse().unwrap_or_default())
+            .collect();
+        let (total_exec_time, wal_bytes) = if version >= vec![1, 8] {
+            (
+                "sum(total_exec_time) as total_exec_time",
+                "sum(wal_bytes)::double precision as wal_bytes",
+            )
+        } else {
+            (
+                "sum(total_time) as total_exec_time",
+                "null::double precision as wal_bytes",
+            )
+        };
+        let sql = format!(
+            "
+            select clock_timestamp() as timestamp,
+                   queryid,
+                   sum(calls)::bigint as calls,
+                   {},
+                   sum(rows)::bigint as rows,
+                   sum(shared_blks_read)::bigint as shared_blks_read,
+                   sum(shared_blks_hit)::bigint as shared_blks_hit,
+                   {}
+             from  pg_stat_statements
+             where queryid is not null
+             group by queryid
+        ",
+            total_exec_time, wal_bytes
+        );
+        let pg_stat_statements: Vec<PgStatStatements> = query_as(&sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_statements)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::processor::Collector;
+use crate::processor::{keep_top_n, DeltaTable, StatisticsDelta};
+use crate::{ARGS, DATA};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgStatUserIndexesSum {
+    pub schemaname: String,
+    pub relname: String,
+    pub indexrelname: String,
+    pub idx_scan_ps: f64,
+    pub idx_tup_read_ps: f64,
+    pub idx_tup_fetch_ps: f64,
+}
+
+impl PgStatUserIndexesSum {
+    pub async fn process_pg_stat_user_indexes(pg_stat_user_indexes: Vec<PgStatUserIndexes>) {
+        let pg_stat_user_indexes_timestamp = match pg_stat_user_indexes.last() {
+            Some(row) => row.timestamp,
+            None => return,
+        };
+        let names = [
+            "pg_stat_user_indexes.idx_scan",
+            "pg_stat_user_indexes.idx_tup_read",
+            "pg_stat_user_indexes.idx_tup_fetch",
+        ];
+        let mut deltatable = DeltaTable::keyed(pg_stat_user_indexes_timestamp).await;
+        for row in pg_stat_user_indexes.iter() {
+            let indexrelid = row.indexrelid.to_string();
+            for (name, value) in
+                names
+                    .iter()
+                    .zip([row.idx_scan, row.idx_tup_read, row.idx_tup_fetch])
+            {
+                deltatable.add_or_update(name, &indexrelid, value.unwrap_or_default() as f64);
+            }
+        }
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+
+        let mut pg_stat_user_indexes_sum: Vec<PgStatUserIndexesSum> = Vec::new();
+        for row in pg_stat_user_indexes.iter() {
+            let indexrelid = row.indexrelid.to_string();
+            match deltatable.get("pg_stat_user_indexes.idx_scan", &indexrelid) {
+                Some(idx_scan) if idx_scan.updated_value => {}
+                _ => continue,
+            };
+            let per_second_value =
+                |delta: Option<StatisticsDelta>| delta.map_or(0_f64, |r| r.per_second_value);
+            pg_stat_user_indexes_sum.push(PgStatUserIndexesSum {
+                schemaname: row.schemaname.clone(),
+                relname: row.relname.clone(),
+                indexrelname: row.indexrelname.clone(),
+                idx_scan_ps: per_second_value(
+                    deltatable.get("pg_stat_user_indexes.idx_scan", &indexrelid),
+                ),
+                idx_tup_read_ps: per_second_value(
+                    deltatable.get("pg_stat_user_indexes.idx_tup_read", &indexrelid),
+                ),
+                idx_tup_fetch_ps: per_second_value(
+                    deltatable.get("pg_stat_user_indexes.idx_tup_fetch", &indexrelid),
+                ),
+            });
+        }
+        // only the top indexes are added to DATA, see pg_stat_user_tables.
+        let pg_stat_user_indexes_sum = keep_top_n(
+            pg_stat_user_indexes_sum,
+            ARGS.top_relations,
+            &[|r| r.idx_scan_ps, |r| r.idx_tup_read_ps],
+        );
+        drop(deltatable);
+        Collector::timed_write(&DATA.pg_stat_user_indexes_sum)
+            .await
+            .push_back((pg_stat_user_indexes_timestamp, pg_stat_user_indexes_sum));
+    }
+}
+
+// this pg_stat_user_indexes is consistent with postgres version 15.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatUserIndexes {
+    pub timestamp: DateTime<Local>,
+    pub indexrelid: i64,
+    pub schemaname: String,
+    pub relname: String,
+    pub indexrelname: String,
+    pub idx_scan: Option<i64>,
+    pub idx_tup_read: Option<i64>,
+    pub idx_tup_fetch: Option<i64>,
+}
+
+impl PgStatUserIndexes {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgStatUserIndexes::query(pool)).await {
+            Ok(pg_stat_user_indexes) => {
+                trace!("pg_stat_user_indexes: {:#?}", pg_stat_user_indexes);
+                PgStatUserIndexesSum::process_pg_stat_user_indexes(pg_stat_user_indexes).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgStatUserIndexes>> {
+        let sql = "
+            select clock_timestamp() as timestamp,
+                   indexrelid::bigint,
+                   schemaname::text,
+                   relname::text,
+                   indexrelname::text,
+                   idx_scan,
+                   idx_tup_read,
+                   idx_tup_fetch
+             from  pg_stat_user_indexes
+        ";
+        let pg_stat_user_indexes: Vec<PgStatUserIndexes> = query_as(sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_user_indexes)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::processor::Collector;
+use crate::processor::{keep_top_n, DeltaTable, StatisticsDelta};
+use crate::{ARGS, DATA};
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgStatUserTablesSum {
+    pub schemaname: String,
+    pub relname: String,
+    pub seq_scan_ps: f64,
+    pub seq_tup_read_ps: f64,
+    pub idx_scan_ps: f64,
+    pub idx_tup_fetch_ps: f64,
+    pub n_tup_ins_ps: f64,
+    pub n_tup_upd_ps: f64,
+    pub n_tup_hot_upd_ps: f64,
+    pub n_tup_del_ps: f64,
+    pub n_live_tup: i64,
+    pub n_dead_tup: i64,
+    pub heap_blks_read_ps: f64,
+    pub heap_blks_hit_ps: f64,
+    pub idx_blks_read_ps: f64,
+    pub idx_blks_hit_ps: f64,
+}
+
+impl PgStatUserTablesSum {
+    pub async fn process_pg_stat_user_tables(pg_stat_user_tables: Vec<PgStatUserTables>) {
+        let pg_stat_user_tables_timestamp = match pg_stat_user_tables.last() {
+            Some(row) => row.timestamp,
+            None => return,
+        };
+        let names = [
+            "pg_stat_user_tables.seq_scan",
+            "pg_stat_user_tables.seq_tup_read",
+            "pg_stat_user_tables.idx_scan",
+            "pg_stat_user_tables.idx_tup_fetch",
+            "pg_stat_user_tables.n_tup_ins",
+            "pg_stat_user_tables.n_tup_upd",
+            "pg_stat_user_tables.n_tup_hot_upd",
+            "pg_stat_user_tables.n_tup_del",
+            "pg_statio_user_tables.heap_blks_read",
+            "pg_statio_user_tables.heap_blks_hit",
+            "pg_statio_user_tables.idx_blks_read",
+            "pg_statio_user_tables.idx_blks_hit",
+        ];
+        let mut deltatable = DeltaTable::keyed(pg_stat_user_tables_timestamp).await;
+        for row in pg_stat_user_tables.iter() {
+            let relid = row.relid.to_string();
+            for (name, value) in names.iter().zip([
+                row.seq_scan,
+                row.seq_tup_read,
+                row.idx_scan,
+                row.idx_tup_fetch,
+                row.n_tup_ins,
+                row.n_tup_upd,
+                row.n_tup_hot_upd,
+                row.n_tup_del,
+                row.heap_blks_read,
+                row.heap_blks_hit,
+                row.idx_blks_read,
+                row.idx_blks_hit,
+            ]) {
+                deltatable.add_or_update(name, &relid, value.unwrap_or_default() as f64);
+            }
+        }
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+
+        let mut pg_stat_user_tables_sum: Vec<PgStatUserTablesSum> = Vec::new();
+        for row in pg_stat_user_tables.iter() {
+            let relid = row.relid.to_string();
+            match deltatable.get("pg_stat_user_tables.seq_scan", &relid) {
+                Some(seq_scan) if seq_scan.updated_value => {}
+                _ => continue,
+            };
+            let per_second_value =
+                |delta: Option<StatisticsDelta>| delta.map_or(0_f64, |r| r.per_second_value);
+            pg_stat_user_tables_sum.push(PgStatUserTablesSum {
+                schemaname: row.schemaname.clone(),
+                relname: row.relname.clone(),
+                seq_scan_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.seq_scan", &relid),
+                ),
+                seq_tup_read_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.seq_tup_read", &relid),
+                ),
+                idx_scan_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.idx_scan", &relid),
+                ),
+                idx_tup_fetch_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.idx_tup_fetch", &relid),
+                ),
+                n_tup_ins_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.n_tup_ins", &relid),
+                ),
+                n_tup_upd_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.n_tup_upd", &relid),
+                ),
+                n_tup_hot_upd_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.n_tup_hot_upd", &relid),
+                ),
+                n_tup_del_ps: per_second_value(
+                    deltatable.get("pg_stat_user_tables.n_tup_del", &relid),
+                ),
+                n_live_tup: row.n_live_tup.unwrap_or_default(),
+                n_dead_tup: row.n_dead_tup.unwrap_or_default(),
+                heap_blks_read_ps: per_second_value(
+                    deltatable.get("pg_statio_user_tables.heap_blks_read", &relid),
+                ),
+                heap_blks_hit_ps: per_second_value(
+                    deltatable.get("pg_statio_user_tables.heap_blks_hit", &relid),
+                ),
+                idx_blks_read_ps: per_second_value(
+                    deltatable.get("pg_statio_user_tables.idx_blks_read", &relid),
+                ),
+                idx_blks_hit_ps: per_second_value(
+                    deltatable.get("pg_statio_user_tables.idx_blks_hit", &relid),
+                ),
+            });
+        }
+        // only the top relations are added to DATA, a database can have a very large number of
+        // tables, of which most are idle in a given interval.
+        let pg_stat_user_tables_sum = keep_top_n(
+            pg_stat_user_tables_sum,
+            ARGS.top_relations,
+            &[
+                |r| r.seq_scan_ps,
+                |r| r.seq_tup_read_ps,
+                |r| r.idx_scan_ps,
+                |r| r.n_tup_ins_ps + r.n_tup_upd_ps + r.n_tup_del_ps,
+                |r| r.heap_blks_read_ps,
+            ],
+        );
+        drop(deltatable);
+        Collector::timed_write(&DATA.pg_stat_user_tables_sum)
+            .await
+            .push_back((pg_stat_user_tables_timestamp, pg_stat_user_tables_sum));
+    }
+}
+
+// this pg_stat_user_tables is consistent with postgres version 15, and is joined with
+// pg_statio_user_tables for the block statistics.
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatUserTables {
+    pub timestamp: DateTime<Local>,
+    pub relid: i64,
+    pub schemaname: String,
+    pub relname: String,
+    pub seq_scan: Option<i64>,
+    pub seq_tup_read: Option<i64>,
+    pub idx_scan: Option<i64>,
+    pub idx_tup_fetch: Option<i64>,
+    pub n_tup_ins: Option<i64>,
+    pub n_tup_upd: Option<i64>,
+    pub n_tup_hot_upd: Option<i64>,
+    pub n_tup_del: Option<i64>,
+    pub n_live_tup: Option<i64>,
+    pub n_dead_tup: Option<i64>,
+    pub heap_blks_read: Option<i64>,
+    pub heap_blks_hit: Option<i64>,
+    pub idx_blks_read: Option<i64>,
+    pub idx_blks_hit: Option<i64>,
+}
+
+impl PgStatUserTables {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgStatUserTables::query(pool)).await {
+            Ok(pg_stat_user_tables) => {
+                trace!("pg_stat_user_tables: {:#?}", pg_stat_user_tables);
+                PgStatUserTablesSum::process_pg_stat_user_tables(pg_stat_user_tables).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Vec<PgStatUserTables>> {
+        let sql = "
+            select clock_timestamp() as timestamp,
+                   t.relid::bigint,
+                   t.schemaname::text,
+                   t.relname::text,
+                   t.seq_scan,
+                   t.seq_tup_read,
+                   t.idx_scan,
+                   t.idx_tup_fetch,
+                   t.n_tup_ins,
+                   t.n_tup_upd,
+                   t.n_tup_hot_upd,
+                   t.n_tup_del,
+                   t.n_live_tup,
+                   t.n_dead_tup,
+                   s.heap_blks_read,
+                   s.heap_blks_hit,
+                   s.idx_blks_read,
+                   s.idx_blks_hit
+             from  pg_stat_user_tables t
+             join  pg_statio_user_tables s on s.relid = t.relid
+        ";
+        let pg_stat_user_tables: Vec<PgStatUserTables> = query_as(sql).fetch_all(pool).await?;
+
+        Ok(pg_stat_user_tables)
+    }
+}
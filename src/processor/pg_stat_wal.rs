@@ -1,169 +1,158 @@
+use crate::processor::Collector;
 use crate::processor::DeltaTable;
 use crate::processor::DELTATABLE;
 use crate::DATA;
 
 use anyhow::Result;
-use bigdecimal::ToPrimitive;
 use chrono::{DateTime, Local};
 use log::{trace, warn};
 use serde::{Deserialize, Serialize};
 use sqlx::{query_as, FromRow, Pool};
 
+// the statistics that the server version does not have are None, see PgStatWal.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct PgStatWalSum {
-    pub wal_records_ps: f64,
-    pub wal_fpi_ps: f64,
-    pub wal_bytes_ps: f64,
-    pub wal_buffers_full_ps: f64,
-    pub wal_write_ps: f64,
-    pub wal_sync_ps: f64,
-    pub wal_write_time_ps: f64,
-    pub wal_sync_time_ps: f64,
+    pub wal_records_ps: Option<f64>,
+    pub wal_fpi_ps: Option<f64>,
+    pub wal_bytes_ps: Option<f64>,
+    pub wal_buffers_full_ps: Option<f64>,
+    pub wal_write_ps: Option<f64>,
+    pub wal_sync_ps: Option<f64>,
+    pub wal_write_time_ps: Option<f64>,
+    pub wal_sync_time_ps: Option<f64>,
 }
 
 impl PgStatWalSum {
     pub async fn process_pg_stat_wal(pg_stat_wal: PgStatWal) {
+        // the time of the fetch is added too, so the sums are added from the second fetch on,
+        // also for a version without pg_stat_wal, which keeps them in step with the other sums.
         DeltaTable::add_or_update(
-            "pg_stat_wal.wal_records",
+            "pg_stat_wal.timestamp",
             pg_stat_wal.timestamp,
-            pg_stat_wal.wal_records as f64,
+            pg_stat_wal.timestamp.timestamp_millis() as f64,
         )
         .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_fpi",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_fpi as f64,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_bytes",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_bytes.to_f64().unwrap(),
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_buffers_full",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_buffers_full as f64,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_write",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_write as f64,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_sync",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_sync as f64,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_write_time",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_write_time,
-        )
-        .await;
-        DeltaTable::add_or_update(
-            "pg_stat_wal.wal_sync_time",
-            pg_stat_wal.timestamp,
-            pg_stat_wal.wal_sync_time,
-        )
-        .await;
-        if DELTATABLE
-            .read()
-            .await
-            .get("pg_stat_wal.wal_records")
+        for (name, value) in [
+            (
+                "pg_stat_wal.wal_records",
+                pg_stat_wal.wal_records.map(|r| r as f64),
+            ),
+            ("pg_stat_wal.wal_fpi", pg_stat_wal.wal_fpi.map(|r| r as f64)),
+            ("pg_stat_wal.wal_bytes", pg_stat_wal.wal_bytes),
+            (
+                "pg_stat_wal.wal_buffers_full",
+                pg_stat_wal.wal_buffers_full.map(|r| r as f64),
+            ),
+            (
+                "pg_stat_wal.wal_write",
+                pg_stat_wal.wal_write.map(|r| r as f64),
+            ),
+            (
+                "pg_stat_wal.wal_sync",
+                pg_stat_wal.wal_sync.map(|r| r as f64),
+            ),
+            ("pg_stat_wal.wal_write_time", pg_stat_wal.wal_write_time),
+            ("pg_stat_wal.wal_sync_time", pg_stat_wal.wal_sync_time),
+        ] {
+            // a statistic that the server version does not have is not added, so it stays None.
+            if let Some(value) = value {
+                DeltaTable::add_or_update(name, pg_stat_wal.timestamp, value).await;
+            }
+        }
+        let deltatable = DELTATABLE.read().await;
+        if deltatable
+            .get("pg_stat_wal.timestamp")
             .unwrap()
             .updated_value
         {
-            DATA.pg_stat_wal_sum.write().await.push_back((
-                pg_stat_wal.timestamp,
-                PgStatWalSum {
-                    wal_records_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_records")
-                        .unwrap()
-                        .per_second_value,
-                    wal_fpi_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_fpi")
-                        .unwrap()
-                        .per_second_value,
-                    wal_bytes_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_bytes")
-                        .unwrap()
-                        .per_second_value,
-                    wal_buffers_full_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_buffers_full")
-                        .unwrap()
-                        .per_second_value,
-                    wal_write_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_write")
-                        .unwrap()
-                        .per_second_value,
-                    wal_sync_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_sync")
-                        .unwrap()
-                        .per_second_value,
-                    wal_write_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_write_time")
-                        .unwrap()
-                        .per_second_value,
-                    wal_sync_time_ps: DELTATABLE
-                        .read()
-                        .await
-                        .get("pg_stat_wal.wal_sync_time")
-                        .unwrap()
-                        .per_second_value,
-                },
-            ));
+            let per_second_value = |name: &str| deltatable.get(name).map(|r| r.per_second_value);
+            Collector::timed_write(&DATA.pg_stat_wal_sum)
+                .await
+                .push_back((
+                    pg_stat_wal.timestamp,
+                    PgStatWalSum {
+                        wal_records_ps: per_second_value("pg_stat_wal.wal_records"),
+                        wal_fpi_ps: per_second_value("pg_stat_wal.wal_fpi"),
+                        wal_bytes_ps: per_second_value("pg_stat_wal.wal_bytes"),
+                        wal_buffers_full_ps: per_second_value("pg_stat_wal.wal_buffers_full"),
+                        wal_write_ps: per_second_value("pg_stat_wal.wal_write"),
+                        wal_sync_ps: per_second_value("pg_stat_wal.wal_sync"),
+                        wal_write_time_ps: per_second_value("pg_stat_wal.wal_write_time"),
+                        wal_sync_time_ps: per_second_value("pg_stat_wal.wal_sync_time"),
+                    },
+                ));
         }
     }
+    // the wal writes per second, the writes because of full wal buffers included.
+    pub fn writes_ps(&self) -> Option<f64> {
+        Some(self.wal_buffers_full_ps? + self.wal_write_ps?)
+    }
+    // the average time of a wal write in milliseconds, None when the version does not have the
+    // write statistics or nothing is written.
+    pub fn write_latency(&self) -> Option<f64> {
+        let writes = self.writes_ps()?;
+        (writes > 0_f64).then(|| self.wal_write_time_ps.unwrap_or_default() / writes)
+    }
+    // the average time of a wal sync in milliseconds, None when the version does not have the
+    // sync statistics or nothing is synced.
+    pub fn sync_latency(&self) -> Option<f64> {
+        let syncs = self.wal_sync_ps?;
+        (syncs > 0_f64).then(|| self.wal_sync_time_ps.unwrap_or_default() / syncs)
+    }
 }
-// this pg_stat_wal is consistent with postgres version 15
+// this pg_stat_wal is consistent with postgres version 15.
+// pg_stat_wal exists since version 14, for older versions all columns are None. since version 18
+// the write and sync columns are moved to pg_stat_io, and are None too.
 #[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
 pub struct PgStatWal {
     pub timestamp: DateTime<Local>,
-    pub wal_records: i64,
-    pub wal_fpi: i64,
-    pub wal_bytes: f64,
-    pub wal_buffers_full: i64,
-    pub wal_write: i64,
-    pub wal_sync: i64,
-    pub wal_write_time: f64,
-    pub wal_sync_time: f64,
+    pub wal_records: Option<i64>,
+    pub wal_fpi: Option<i64>,
+    pub wal_bytes: Option<f64>,
+    pub wal_buffers_full: Option<i64>,
+    pub wal_write: Option<i64>,
+    pub wal_sync: Option<i64>,
+    pub wal_write_time: Option<f64>,
+    pub wal_sync_time: Option<f64>,
     pub stats_reset: Option<DateTime<Local>>,
 }
 
 impl PgStatWal {
-    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) {
-        match PgStatWal::query(pool).await {
+    pub async fn fetch_and_add_to_data(
+        pool: &Pool<sqlx::Postgres>,
+        server_version_num: i32,
+    ) -> bool {
+        match Collector::timed_query(PgStatWal::query(pool, server_version_num)).await {
             Ok(pg_stat_wal) => {
                 trace!("pg_stat_wal: {:#?}", pg_stat_wal);
                 PgStatWalSum::process_pg_stat_wal(pg_stat_wal).await;
+                true
             }
             Err(error) => {
                 warn!("Pool connection failed: {:?}", error);
+                false
             }
         }
     }
-    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<PgStatWal> {
-        let stat_wal: PgStatWal = query_as(
-            "
+    async fn query(pool: &Pool<sqlx::Postgres>, server_version_num: i32) -> Result<PgStatWal> {
+        let sql = match server_version_num {
+            180000.. => {
+                "
+            select clock_timestamp() as timestamp,
+                   wal_records, 
+                   wal_fpi, 
+                   wal_bytes::double precision,
+                   wal_buffers_full,
+                   null::bigint as wal_write,
+                   null::bigint as wal_sync, 
+                   null::double precision as wal_write_time, 
+                   null::double precision as wal_sync_time,
+                   stats_reset
+             from  pg_stat_wal 
+        "
+            }
+            140000.. => {
+                "
             select clock_timestamp() as timestamp,
                    wal_records, 
                    wal_fpi, 
@@ -175,10 +164,24 @@ impl PgStatWal {
                    wal_sync_time,
                    stats_reset
              from  pg_stat_wal 
-        ",
-        )
-        .fetch_one(pool)
-        .await?;
+        "
+            }
+            _ => {
+                "
+            select clock_timestamp() as timestamp,
+                   null::bigint as wal_records, 
+                   null::bigint as wal_fpi, 
+                   null::double precision as wal_bytes,
+                   null::bigint as wal_buffers_full,
+                   null::bigint as wal_write,
+                   null::bigint as wal_sync, 
+                   null::double precision as wal_write_time, 
+                   null::double precision as wal_sync_time,
+                   null::timestamptz as stats_reset
+        "
+            }
+        };
+        let stat_wal: PgStatWal = query_as(sql).fetch_one(pool).await?;
 
         Ok(stat_wal)
     }
@@ -0,0 +1,60 @@
+use crate::processor::Collector;
+use crate::DATA;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, FromRow, Pool};
+
+// this pg_stat_wal_receiver is consistent with postgres version 15.
+// pg_stat_wal_receiver only contains a row on a standby with a running wal receiver.
+// replay_lag_bytes is the wal that is received but not yet replayed, replay_delay is the time
+// since the last replayed transaction was committed on the primary.
+#[derive(Debug, Default, FromRow, Clone, Serialize, Deserialize)]
+pub struct PgStatWalReceiver {
+    pub timestamp: DateTime<Local>,
+    pub pid: i32,
+    pub status: Option<String>,
+    pub sender_host: Option<String>,
+    pub slot_name: Option<String>,
+    pub replay_lag_bytes: Option<f64>,
+    pub replay_delay: Option<f64>,
+    pub last_msg_receipt_age: Option<f64>,
+}
+
+impl PgStatWalReceiver {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgStatWalReceiver::query(pool)).await {
+            Ok(pg_stat_wal_receiver) => {
+                trace!("pg_stat_wal_receiver: {:#?}", pg_stat_wal_receiver);
+                if let Some(pg_stat_wal_receiver) = pg_stat_wal_receiver {
+                    Collector::timed_write(&DATA.pg_stat_wal_receiver)
+                        .await
+                        .push_back((Local::now(), pg_stat_wal_receiver));
+                }
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<Option<PgStatWalReceiver>> {
+        let sql = "
+            select clock_timestamp() as timestamp,
+                   pid,
+                   status,
+                   sender_host,
+                   slot_name,
+                   pg_wal_lsn_diff(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn())::double precision as replay_lag_bytes,
+                   extract(epoch from (clock_timestamp()-pg_last_xact_replay_timestamp()))::double precision as replay_delay,
+                   extract(epoch from (clock_timestamp()-last_msg_receipt_time))::double precision as last_msg_receipt_age
+             from  pg_stat_wal_receiver
+        ";
+        let pg_stat_wal_receiver: Option<PgStatWalReceiver> =
+            query_as(sql).fetch_optional(pool).await?;
+
+        Ok(pg_stat_wal_receiver)
+    }
+}
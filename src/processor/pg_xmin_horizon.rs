@@ -0,0 +1,160 @@
+use crate::processor::collector_statistics::QueryRows;
+use crate::processor::Collector;
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::Local;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::{query_as, query_scalar, FromRow, Pool};
+
+// the number of holders that are kept per sample, the oldest first.
+static TOP_HOLDERS: usize = 10;
+
+// the transaction ids wrap around at 2^32.
+const XID_RANGE: i64 = 1 << 32;
+
+// something that holds back the xmin horizon of the cluster, which is the oldest transaction id
+// that vacuum must consider running, so vacuum cannot remove the dead tuples that are newer. the
+// age is the number of transactions since the xmin or xid of the holder.
+// a holder is a session (from pg_stat_activity), a replication slot (xmin or catalog_xmin, the
+// xmin of a physical slot is the xmin of a standby with hot_standby_feedback), or a prepared
+// transaction, which holds its xid until it is committed or rolled back.
+#[derive(Debug, Default, FromRow, Clone, Serialize, Deserialize)]
+pub struct XminHolder {
+    pub holder_type: String,
+    pub name: String,
+    pub pid: Option<i32>,
+    pub datname: Option<String>,
+    pub usename: Option<String>,
+    pub state: Option<String>,
+    pub xact_time: Option<i64>,
+    pub query: Option<String>,
+    pub xmin_age: i64,
+}
+
+// the xmin horizon at a sample: the age of the oldest holder, and the oldest holders.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PgXminHorizon {
+    pub horizon_age: i64,
+    pub holders: Vec<XminHolder>,
+}
+
+impl QueryRows for (i64, Vec<XminHolder>) {
+    fn rows(&self) -> usize {
+        self.1.len()
+    }
+}
+
+impl PgXminHorizon {
+    pub async fn fetch_and_add_to_data(pool: &Pool<sqlx::Postgres>) -> bool {
+        match Collector::timed_query(PgXminHorizon::query(pool)).await {
+            Ok((next_xid, holders)) => {
+                trace!("next_xid: {}, xmin holders: {:#?}", next_xid, holders);
+                PgXminHorizon::add_to_data(next_xid, holders).await;
+                true
+            }
+            Err(error) => {
+                warn!("Pool connection failed: {:?}", error);
+                false
+            }
+        }
+    }
+    // the next transaction id is read from the snapshot, because txid_current() would assign a
+    // transaction id. the sessions are not queried here, they are taken from pg_stat_activity.
+    async fn query(pool: &Pool<sqlx::Postgres>) -> Result<(i64, Vec<XminHolder>)> {
+        let next_xid: i64 = query_scalar(
+            "select txid_snapshot_xmax(txid_current_snapshot()) % 4294967296 as next_xid",
+        )
+        .fetch_one(pool)
+        .await?;
+        let holders: Vec<XminHolder> = query_as(
+            "
+            select 'replication slot' as holder_type,
+                   slot_name::text as name,
+                   active_pid as pid,
+                   database::text as datname,
+                   null::text as usename,
+                   case when active then 'active' else 'inactive' end as state,
+                   null::bigint as xact_time,
+                   null::text as query,
+                   greatest(age(xmin), age(catalog_xmin))::bigint as xmin_age
+             from  pg_replication_slots
+             where xmin is not null
+                or catalog_xmin is not null
+            union all
+            select 'prepared transaction' as holder_type,
+                   gid as name,
+                   null::int as pid,
+                   database::text as datname,
+                   owner::text as usename,
+                   'prepared' as state,
+                   cast(extract(epoch from (clock_timestamp()-prepared)) as bigint) as xact_time,
+                   null::text as query,
+                   age(transaction)::bigint as xmin_age
+             from  pg_prepared_xacts
+        ",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok((next_xid, holders))
+    }
+    // the sessions are taken from the last sample of pg_stat_activity, a session holds the
+    // horizon with the oldest of its xmin and its own xid.
+    async fn add_to_data(next_xid: i64, mut holders: Vec<XminHolder>) {
+        if let Some((_, pg_stat_activity)) = DATA.pg_stat_activity.read().await.back() {
+            holders.extend(
+                pg_stat_activity
+                    .iter()
+                    .filter_map(|r| {
+                        [r.backend_xmin, r.backend_xid]
+                            .into_iter()
+                            .flatten()
+                            .map(|xid| (next_xid - xid).rem_euclid(XID_RANGE))
+                            .max()
+                            .map(|xmin_age| (r, xmin_age))
+                    })
+                    .map(|(r, xmin_age)| XminHolder {
+                        holder_type: "session".to_string(),
+                        name: format!(
+                            "{} {}",
+                            r.backend_type.as_deref().unwrap_or_default(),
+                            r.application_name.as_deref().unwrap_or_default()
+                        ),
+                        pid: Some(r.pid),
+                        datname: r.datname.clone(),
+                        usename: r.usename.clone(),
+                        state: r.state.clone(),
+                        xact_time: r.xact_time,
+                        query: r.query.clone(),
+                        xmin_age,
+                    }),
+            );
+        }
+        // holders with an equal age usually hold the horizon because of each other, for example
+        // a logical replication slot that is created waits for a prepared transaction and takes
+        // its xmin. the holder that holds its own xid is named first.
+        holders.sort_by_key(|r| {
+            (
+                std::cmp::Reverse(r.xmin_age),
+                match r.holder_type.as_str() {
+                    "prepared transaction" => 0,
+                    "session" => 1,
+                    _ => 2,
+                },
+            )
+        });
+        holders.truncate(TOP_HOLDERS);
+
+        Collector::timed_write(&DATA.pg_xmin_horizon)
+            .await
+            .push_back((
+                Local::now(),
+                PgXminHorizon {
+                    horizon_age: holders.first().map_or(0, |r| r.xmin_age),
+                    holders,
+                },
+            ));
+    }
+}
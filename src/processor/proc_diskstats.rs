@@ -0,0 +1,165 @@
+use crate::processor::Collector;
+use crate::processor::{DeltaTable, StatisticsDelta};
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// the sectors in /proc/diskstats are always 512 bytes, independent of the device sector size.
+static SECTOR_SIZE: f64 = 512_f64;
+
+// the latencies are the average time per request in the interval in milliseconds, utilization is
+// the percentage of the interval the device had requests in flight.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProcDiskstatsSum {
+    pub device: String,
+    pub reads_ps: f64,
+    pub writes_ps: f64,
+    pub read_bytes_ps: f64,
+    pub write_bytes_ps: f64,
+    pub read_latency: f64,
+    pub write_latency: f64,
+    pub queue_size: f64,
+    pub utilization: f64,
+}
+
+impl ProcDiskstatsSum {
+    pub async fn process_proc_diskstats(proc_diskstats: Vec<ProcDiskstats>) {
+        let proc_diskstats_timestamp = match proc_diskstats.last() {
+            Some(row) => row.timestamp,
+            None => return,
+        };
+        let names = [
+            "proc_diskstats.reads",
+            "proc_diskstats.read_sectors",
+            "proc_diskstats.read_time",
+            "proc_diskstats.writes",
+            "proc_diskstats.write_sectors",
+            "proc_diskstats.write_time",
+            "proc_diskstats.io_time",
+            "proc_diskstats.weighted_io_time",
+        ];
+        let mut deltatable = DeltaTable::keyed(proc_diskstats_timestamp).await;
+        for row in proc_diskstats.iter() {
+            for (name, value) in names.iter().zip([
+                row.reads,
+                row.read_sectors,
+                row.read_time,
+                row.writes,
+                row.write_sectors,
+                row.write_time,
+                row.io_time,
+                row.weighted_io_time,
+            ]) {
+                deltatable.add_or_update(name, &row.device, value as f64);
+            }
+        }
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+
+        let mut proc_diskstats_sum: Vec<ProcDiskstatsSum> = Vec::new();
+        for row in proc_diskstats.iter() {
+            let delta = |name: &'static str| deltatable.get(name, &row.device);
+            let reads = match delta("proc_diskstats.reads") {
+                Some(reads) if reads.updated_value => reads,
+                _ => continue,
+            };
+            let writes = delta("proc_diskstats.writes").unwrap_or_default();
+            let read_time = delta("proc_diskstats.read_time").unwrap_or_default();
+            let write_time = delta("proc_diskstats.write_time").unwrap_or_default();
+            let per_second_value =
+                |delta: Option<StatisticsDelta>| delta.map_or(0_f64, |r| r.per_second_value);
+            proc_diskstats_sum.push(ProcDiskstatsSum {
+                device: row.device.clone(),
+                reads_ps: reads.per_second_value,
+                writes_ps: writes.per_second_value,
+                read_bytes_ps: per_second_value(delta("proc_diskstats.read_sectors")) * SECTOR_SIZE,
+                write_bytes_ps: per_second_value(delta("proc_diskstats.write_sectors"))
+                    * SECTOR_SIZE,
+                read_latency: if reads.delta_value > 0_f64 {
+                    read_time.delta_value / reads.delta_value
+                } else {
+                    0_f64
+                },
+                write_latency: if writes.delta_value > 0_f64 {
+                    write_time.delta_value / writes.delta_value
+                } else {
+                    0_f64
+                },
+                // the io times are in milliseconds
+                queue_size: per_second_value(delta("proc_diskstats.weighted_io_time")) / 1000_f64,
+                utilization: per_second_value(delta("proc_diskstats.io_time")) / 10_f64,
+            });
+        }
+        drop(deltatable);
+        Collector::timed_write(&DATA.proc_diskstats_sum)
+            .await
+            .push_back((proc_diskstats_timestamp, proc_diskstats_sum));
+    }
+}
+
+// the fields of /proc/diskstats that are used, the times are in milliseconds.
+#[derive(Debug, Default, Clone)]
+pub struct ProcDiskstats {
+    pub timestamp: DateTime<Local>,
+    pub device: String,
+    pub reads: u64,
+    pub read_sectors: u64,
+    pub read_time: u64,
+    pub writes: u64,
+    pub write_sectors: u64,
+    pub write_time: u64,
+    pub io_time: u64,
+    pub weighted_io_time: u64,
+}
+
+impl ProcDiskstats {
+    pub async fn read_and_add_to_data() -> bool {
+        match ProcDiskstats::read().await {
+            Ok(proc_diskstats) => {
+                trace!("proc_diskstats: {:#?}", proc_diskstats);
+                ProcDiskstatsSum::process_proc_diskstats(proc_diskstats).await;
+                true
+            }
+            Err(error) => {
+                warn!("Reading /proc/diskstats failed: {:?}", error);
+                false
+            }
+        }
+    }
+    // only whole devices (which are listed in /sys/block) that have done IO are used, so the
+    // partitions and unused loop and ram devices are skipped.
+    async fn read() -> Result<Vec<ProcDiskstats>> {
+        let contents = tokio::fs::read_to_string("/proc/diskstats").await?;
+        let timestamp = Local::now();
+        let mut proc_diskstats: Vec<ProcDiskstats> = Vec::new();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 14 || !Path::new("/sys/block").join(fields[2]).exists() {
+                continue;
+            }
+            let value = |index: usize| fields[index].parse::<u64>().unwrap_or_default();
+            if value(3) + value(7) == 0 {
+                continue;
+            }
+            proc_diskstats.push(ProcDiskstats {
+                timestamp,
+                device: fields[2].to_string(),
+                reads: value(3),
+                read_sectors: value(5),
+                read_time: value(6),
+                writes: value(7),
+                write_sectors: value(9),
+                write_time: value(10),
+                io_time: value(12),
+                weighted_io_time: value(13),
+            });
+        }
+
+        Ok(proc_diskstats)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::processor::Collector;
+use crate::DATA;
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+
+// /proc/loadavg: the load averages, and the number of runnable and total scheduling entities.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProcLoadavg {
+    pub load_1: f64,
+    pub load_5: f64,
+    pub load_15: f64,
+    pub runnable: u64,
+    pub total: u64,
+}
+
+impl ProcLoadavg {
+    pub async fn read_and_add_to_data() -> bool {
+        match ProcLoadavg::read().await {
+            Ok(proc_loadavg) => {
+                trace!("proc_loadavg: {:#?}", proc_loadavg);
+                Collector::timed_write(&DATA.proc_loadavg)
+                    .await
+                    .push_back((Local::now(), proc_loadavg));
+                true
+            }
+            Err(error) => {
+                warn!("Reading /proc/loadavg failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn read() -> Result<ProcLoadavg> {
+        let contents = tokio::fs::read_to_string("/proc/loadavg").await?;
+        let fields: Vec<&str> = contents.split_whitespace().collect();
+        let (runnable, total) = fields
+            .get(3)
+            .and_then(|r| r.split_once('/'))
+            .context("no runnable/total field found in /proc/loadavg")?;
+        let load = |index: usize| -> Result<f64> {
+            Ok(fields
+                .get(index)
+                .context("no load average found in /proc/loadavg")?
+                .parse()?)
+        };
+
+        Ok(ProcLoadavg {
+            load_1: load(0)?,
+            load_5: load(1)?,
+            load_15: load(2)?,
+            runnable: runnable.parse()?,
+            total: total.parse()?,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use crate::processor::Collector;
+use crate::DATA;
+
+use anyhow::{bail, Result};
+use chrono::Local;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+
+// /proc/meminfo, the values are converted from kB to bytes.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProcMeminfo {
+    pub memtotal: f64,
+    pub memfree: f64,
+    pub memavailable: f64,
+    pub buffers: f64,
+    pub cached: f64,
+    pub shmem: f64,
+    pub dirty: f64,
+    pub writeback: f64,
+    pub swaptotal: f64,
+    pub swapfree: f64,
+    pub hugepages_total: f64,
+    pub hugepages_free: f64,
+    pub hugepagesize: f64,
+}
+
+impl ProcMeminfo {
+    pub async fn read_and_add_to_data() -> bool {
+        match ProcMeminfo::read().await {
+            Ok(proc_meminfo) => {
+                trace!("proc_meminfo: {:#?}", proc_meminfo);
+                Collector::timed_write(&DATA.proc_meminfo)
+                    .await
+                    .push_back((Local::now(), proc_meminfo));
+                true
+            }
+            Err(error) => {
+                warn!("Reading /proc/meminfo failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn read() -> Result<ProcMeminfo> {
+        let contents = tokio::fs::read_to_string("/proc/meminfo").await?;
+        let mut proc_meminfo = ProcMeminfo::default();
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default().trim_end_matches(':');
+            let value = fields.next().unwrap_or_default().parse::<f64>()?;
+            // the number of hugepages has no unit, the other values are in kB.
+            let bytes = match fields.next() {
+                Some("kB") => value * 1024_f64,
+                _ => value,
+            };
+            match name {
+                "MemTotal" => proc_meminfo.memtotal = bytes,
+                "MemFree" => proc_meminfo.memfree = bytes,
+                "MemAvailable" => proc_meminfo.memavailable = bytes,
+                "Buffers" => proc_meminfo.buffers = bytes,
+                "Cached" => proc_meminfo.cached = bytes,
+                "Shmem" => proc_meminfo.shmem = bytes,
+                "Dirty" => proc_meminfo.dirty = bytes,
+                "Writeback" => proc_meminfo.writeback = bytes,
+                "SwapTotal" => proc_meminfo.swaptotal = bytes,
+                "SwapFree" => proc_meminfo.swapfree = bytes,
+                "HugePages_Total" => proc_meminfo.hugepages_total = bytes,
+                "HugePages_Free" => proc_meminfo.hugepages_free = bytes,
+                "Hugepagesize" => proc_meminfo.hugepagesize = bytes,
+                _ => {}
+            }
+        }
+        if proc_meminfo.memtotal == 0_f64 {
+            bail!("no MemTotal found in /proc/meminfo");
+        }
+
+        Ok(proc_meminfo)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::processor::proc_stat::USER_HZ;
+use crate::processor::{DeltaTable, PgStatActivity};
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use log::{debug, trace};
+
+// the cpu, run queue and io statistics of a single postgres backend, read from /proc/<pid>.
+// /proc/<pid>/io and /proc/<pid>/schedstat are optional: io is only readable for the owner of
+// the process, and schedstat depends on the kernel configuration.
+#[derive(Debug, Default, Clone)]
+pub struct ProcPid {
+    pub pid: i32,
+    pub starttime: u64,
+    pub cpu_time: u64,
+    pub runqueue_time: Option<u64>,
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+}
+
+impl ProcPid {
+    // add the cpu time, run queue time and io since the previous sample to the rows of a
+    // pg_stat_activity sample. the deltas are keyed by pid and process start time, so a reused
+    // pid does not produce a delta with the previous process.
+    // only active backends are read: the usage is only shown per query, and reading /proc for
+    // every idle backend on every ash sample is wasted work. a backend that was not active in
+    // the previous sample has no delta, because its keys are removed as stale.
+    // the cpu time has a resolution of USER_HZ ticks (usually 10ms), which is coarse for a single
+    // sample, but the sum over the samples of a query is accurate.
+    pub async fn add_to_pg_stat_activity(pg_stat_activity: &mut [PgStatActivity]) {
+        let timestamp = Local::now();
+        let names = [
+            "proc_pid.cpu_time",
+            "proc_pid.runqueue_time",
+            "proc_pid.read_bytes",
+            "proc_pid.write_bytes",
+        ];
+        // /proc is read before the deltatable is locked, the lock is shared with the statistics
+        // collectors.
+        let mut proc_pids: Vec<(usize, ProcPid)> = Vec::new();
+        for (index, row) in pg_stat_activity
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.state.as_deref().unwrap_or_default() == "active")
+        {
+            match ProcPid::read(row.pid).await {
+                Ok(proc_pid) => proc_pids.push((index, proc_pid)),
+                Err(error) => debug!("Reading /proc/{} failed: {:?}", row.pid, error),
+            };
+        }
+        let mut deltatable = DeltaTable::keyed(timestamp).await;
+        for (index, proc_pid) in proc_pids {
+            trace!("proc_pid: {:#?}", proc_pid);
+            let key = format!("{}.{}", proc_pid.pid, proc_pid.starttime);
+            for (name, value) in names.iter().zip([
+                Some(proc_pid.cpu_time),
+                proc_pid.runqueue_time,
+                proc_pid.read_bytes,
+                proc_pid.write_bytes,
+            ]) {
+                if let Some(value) = value {
+                    deltatable.add_or_update(name, &key, value as f64);
+                }
+            }
+            let delta_value = |name: &'static str| {
+                deltatable
+                    .get(name, &key)
+                    .filter(|r| r.updated_value && r.last_timestamp == timestamp)
+                    .map(|r| r.delta_value)
+            };
+            let row = &mut pg_stat_activity[index];
+            row.cpu_time = delta_value("proc_pid.cpu_time").map(|r| r / USER_HZ);
+            row.runqueue_time =
+                delta_value("proc_pid.runqueue_time").map(|r| r / 1_000_000_000_f64);
+            row.read_bytes = delta_value("proc_pid.read_bytes");
+            row.write_bytes = delta_value("proc_pid.write_bytes");
+        }
+        for name in names {
+            deltatable.remove_stale(name);
+        }
+    }
+    async fn read(pid: i32) -> Result<ProcPid> {
+        let stat = tokio::fs::read_to_string(format!("/proc/{}/stat", pid)).await?;
+        // the command name is between parentheses and can contain spaces, the fields after it
+        // start with the process state (field 3).
+        let (command, fields) = stat
+            .split_once(" (")
+            .and_then(|(_, r)| r.rsplit_once(") "))
+            .context("no command found in /proc/<pid>/stat")?;
+        // the pid in pg_stat_activity can belong to a different process when pas does not run on
+        // the database server.
+        if command != "postgres" {
+            bail!("process {} is not a postgres process: {}", pid, command);
+        }
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        let field = |number: usize| -> Result<u64> {
+            Ok(fields
+                .get(number - 3)
+                .context("field not found in /proc/<pid>/stat")?
+                .parse()?)
+        };
+        let mut proc_pid = ProcPid {
+            pid,
+            starttime: field(22)?,
+            cpu_time: field(14)? + field(15)?,
+            ..Default::default()
+        };
+
+        if let Ok(schedstat) = tokio::fs::read_to_string(format!("/proc/{}/schedstat", pid)).await {
+            proc_pid.runqueue_time = schedstat
+                .split_whitespace()
+                .nth(1)
+                .and_then(|r| r.parse().ok());
+        }
+        if let Ok(io) = tokio::fs::read_to_string(format!("/proc/{}/io", pid)).await {
+            for line in io.lines() {
+                match line.split_once(": ") {
+                    Some(("read_bytes", value)) => proc_pid.read_bytes = value.parse().ok(),
+                    Some(("write_bytes", value)) => proc_pid.write_bytes = value.parse().ok(),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(proc_pid)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::processor::Collector;
+use crate::processor::DeltaTable;
+use crate::DATA;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+
+// the cpu times in /proc/stat are in USER_HZ, which is 100 on linux.
+pub static USER_HZ: f64 = 100_f64;
+
+// the cpu times are in cpu seconds per second, which is the number of cpus that are busy, so it
+// can be compared with the number of active sessions.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProcStatSum {
+    pub cpus: usize,
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+    pub context_switches_ps: f64,
+    pub processes_ps: f64,
+    pub procs_running: f64,
+    pub procs_blocked: f64,
+}
+
+impl ProcStatSum {
+    pub async fn process_proc_stat(proc_stat: ProcStat) {
+        for (name, value) in [
+            ("proc_stat.user", proc_stat.user),
+            ("proc_stat.nice", proc_stat.nice),
+            ("proc_stat.system", proc_stat.system),
+            ("proc_stat.idle", proc_stat.idle),
+            ("proc_stat.iowait", proc_stat.iowait),
+            ("proc_stat.irq", proc_stat.irq),
+            ("proc_stat.softirq", proc_stat.softirq),
+            ("proc_stat.steal", proc_stat.steal),
+            ("proc_stat.ctxt", proc_stat.ctxt),
+            ("proc_stat.processes", proc_stat.processes),
+        ] {
+            DeltaTable::add_or_update(name, proc_stat.timestamp, value as f64).await;
+        }
+        if DeltaTable::get("proc_stat.user")
+            .await
+            .is_some_and(|r| r.updated_value)
+        {
+            let cpu_seconds = |name: &'static str| async move {
+                DeltaTable::get(name)
+                    .await
+                    .map_or(0_f64, |r| r.per_second_value / USER_HZ)
+            };
+            let proc_stat_sum = ProcStatSum {
+                cpus: proc_stat.cpus,
+                user: cpu_seconds("proc_stat.user").await,
+                nice: cpu_seconds("proc_stat.nice").await,
+                system: cpu_seconds("proc_stat.system").await,
+                idle: cpu_seconds("proc_stat.idle").await,
+                iowait: cpu_seconds("proc_stat.iowait").await,
+                irq: cpu_seconds("proc_stat.irq").await,
+                softirq: cpu_seconds("proc_stat.softirq").await,
+                steal: cpu_seconds("proc_stat.steal").await,
+                context_switches_ps: DeltaTable::get("proc_stat.ctxt")
+                    .await
+                    .map_or(0_f64, |r| r.per_second_value),
+                processes_ps: DeltaTable::get("proc_stat.processes")
+                    .await
+                    .map_or(0_f64, |r| r.per_second_value),
+                procs_running: proc_stat.procs_running as f64,
+                procs_blocked: proc_stat.procs_blocked as f64,
+            };
+            Collector::timed_write(&DATA.proc_stat_sum)
+                .await
+                .push_back((proc_stat.timestamp, proc_stat_sum));
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProcStat {
+    pub timestamp: DateTime<Local>,
+    pub cpus: usize,
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+    pub ctxt: u64,
+    pub processes: u64,
+    pub procs_running: u64,
+    pub procs_blocked: u64,
+}
+
+impl ProcStat {
+    pub async fn read_and_add_to_data() -> bool {
+        match ProcStat::read().await {
+            Ok(proc_stat) => {
+                trace!("proc_stat: {:#?}", proc_stat);
+                ProcStatSum::process_proc_stat(proc_stat).await;
+                true
+            }
+            Err(error) => {
+                warn!("Reading /proc/stat failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn read() -> Result<ProcStat> {
+        let contents = tokio::fs::read_to_string("/proc/stat").await?;
+        let mut proc_stat = ProcStat {
+            timestamp: Local::now(),
+            ..Default::default()
+        };
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let values: Vec<u64> = fields.map(|r| r.parse().unwrap_or_default()).collect();
+            match name {
+                // the cpu line is the sum of all cpus, the cpuN lines are the individual cpus.
+                "cpu" => {
+                    let value = |index: usize| values.get(index).copied().unwrap_or_default();
+                    proc_stat.user = value(0);
+                    proc_stat.nice = value(1);
+                    proc_stat.system = value(2);
+                    proc_stat.idle = value(3);
+                    proc_stat.iowait = value(4);
+                    proc_stat.irq = value(5);
+                    proc_stat.softirq = value(6);
+                    proc_stat.steal = value(7);
+                }
+                name if name.starts_with("cpu") => proc_stat.cpus += 1,
+                "ctxt" => proc_stat.ctxt = values.first().copied().unwrap_or_default(),
+                "processes" => proc_stat.processes = values.first().copied().unwrap_or_default(),
+                "procs_running" => {
+                    proc_stat.procs_running = values.first().copied().unwrap_or_default()
+                }
+                "procs_blocked" => {
+                    proc_stat.procs_blocked = values.first().copied().unwrap_or_default()
+                }
+                _ => {}
+            }
+        }
+        if proc_stat.cpus == 0 {
+            bail!("no cpu lines found in /proc/stat");
+        }
+
+        Ok(proc_stat)
+    }
+}
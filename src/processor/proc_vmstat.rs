@@ -0,0 +1,104 @@
+use crate::processor::Collector;
+use crate::processor::DeltaTable;
+use crate::DATA;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+
+// the paging and swapping statistics per second, pgpgin and pgpgout are in kB.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ProcVmstatSum {
+    pub pgpgin_ps: f64,
+    pub pgpgout_ps: f64,
+    pub pswpin_ps: f64,
+    pub pswpout_ps: f64,
+    pub pgfault_ps: f64,
+    pub pgmajfault_ps: f64,
+}
+
+impl ProcVmstatSum {
+    pub async fn process_proc_vmstat(proc_vmstat: ProcVmstat) {
+        for (name, value) in [
+            ("proc_vmstat.pgpgin", proc_vmstat.pgpgin),
+            ("proc_vmstat.pgpgout", proc_vmstat.pgpgout),
+            ("proc_vmstat.pswpin", proc_vmstat.pswpin),
+            ("proc_vmstat.pswpout", proc_vmstat.pswpout),
+            ("proc_vmstat.pgfault", proc_vmstat.pgfault),
+            ("proc_vmstat.pgmajfault", proc_vmstat.pgmajfault),
+        ] {
+            DeltaTable::add_or_update(name, proc_vmstat.timestamp, value as f64).await;
+        }
+        if DeltaTable::get("proc_vmstat.pgpgin")
+            .await
+            .is_some_and(|r| r.updated_value)
+        {
+            let per_second_value = |name: &'static str| async move {
+                DeltaTable::get(name)
+                    .await
+                    .map_or(0_f64, |r| r.per_second_value)
+            };
+            let proc_vmstat_sum = ProcVmstatSum {
+                pgpgin_ps: per_second_value("proc_vmstat.pgpgin").await,
+                pgpgout_ps: per_second_value("proc_vmstat.pgpgout").await,
+                pswpin_ps: per_second_value("proc_vmstat.pswpin").await,
+                pswpout_ps: per_second_value("proc_vmstat.pswpout").await,
+                pgfault_ps: per_second_value("proc_vmstat.pgfault").await,
+                pgmajfault_ps: per_second_value("proc_vmstat.pgmajfault").await,
+            };
+            Collector::timed_write(&DATA.proc_vmstat_sum)
+                .await
+                .push_back((proc_vmstat.timestamp, proc_vmstat_sum));
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProcVmstat {
+    pub timestamp: DateTime<Local>,
+    pub pgpgin: u64,
+    pub pgpgout: u64,
+    pub pswpin: u64,
+    pub pswpout: u64,
+    pub pgfault: u64,
+    pub pgmajfault: u64,
+}
+
+impl ProcVmstat {
+    pub async fn read_and_add_to_data() -> bool {
+        match ProcVmstat::read().await {
+            Ok(proc_vmstat) => {
+                trace!("proc_vmstat: {:#?}", proc_vmstat);
+                ProcVmstatSum::process_proc_vmstat(proc_vmstat).await;
+                true
+            }
+            Err(error) => {
+                warn!("Reading /proc/vmstat failed: {:?}", error);
+                false
+            }
+        }
+    }
+    async fn read() -> Result<ProcVmstat> {
+        let contents = tokio::fs::read_to_string("/proc/vmstat").await?;
+        let mut proc_vmstat = ProcVmstat {
+            timestamp: Local::now(),
+            ..Default::default()
+        };
+        for line in contents.lines() {
+            let (name, value) = line.split_once(' ').unwrap_or_default();
+            let value = value.parse::<u64>().unwrap_or_default();
+            match name {
+                "pgpgin" => proc_vmstat.pgpgin = value,
+                "pgpgout" => proc_vmstat.pgpgout = value,
+                "pswpin" => proc_vmstat.pswpin = value,
+                "pswpout" => proc_vmstat.pswpout = value,
+                "pgfault" => proc_vmstat.pgfault = value,
+                "pgmajfault" => proc_vmstat.pgmajfault = value,
+                _ => {}
+            }
+        }
+
+        Ok(proc_vmstat)
+    }
+}
@@ -1,5 +1,6 @@
-use crate::ARGS;
+use crate::archiver::cluster_name_from_filename;
 use crate::DATA;
+use crate::{ARGS, CLUSTER, CLUSTERS};
 use anyhow::{Context, Result};
 use std::{fs::read_to_string, path::Path};
 
@@ -14,27 +15,12 @@ pub async fn reader_main() -> Result<()> {
             )
             .with_context(|| format!("Error reading JSON from: {}", file))?;
 
-            macro_rules! transition_loader {
-                ($($category:ident),*) => {
-                    $(
-                    for record in transition.$category {
-                        DATA.$category
-                            .write()
-                            .await
-                            .push_back(record.clone())
-                            .unwrap_or_default();
-                    };
-                    )*
-                };
-            }
-
-            transition_loader!(
-                pg_stat_activity,
-                pg_stat_database_sum,
-                pg_stat_bgwriter_sum,
-                pg_stat_wal_sum,
-                pg_database_xid_limits
-            );
+            // the archive is loaded into the cluster that is in its filename
+            let cluster = CLUSTERS
+                .iter()
+                .position(|r| r.name == cluster_name_from_filename(&file))
+                .unwrap_or_default();
+            CLUSTER.scope(cluster, load_transition(transition)).await;
 
             println!("✔ {}", &file);
         } else {
@@ -45,3 +31,55 @@ pub async fn reader_main() -> Result<()> {
 
     Ok(())
 }
+
+async fn load_transition(transition: DataTransit) {
+    macro_rules! transition_loader {
+        ($($category:ident),*) => {
+            $(
+            for record in transition.$category {
+                DATA.$category
+                    .write()
+                    .await
+                    .push_back(record.clone())
+                    .unwrap_or_default();
+            };
+            )*
+        };
+    }
+
+    transition_loader!(
+        pg_stat_activity,
+        pg_stat_database_sum,
+        pg_stat_bgwriter_sum,
+        pg_stat_wal_sum,
+        pg_database_xid_limits,
+        pg_stat_statements,
+        pg_stat_checkpointer_sum,
+        pg_stat_io_sum,
+        pg_lock_edges,
+        pg_stat_replication,
+        pg_replication_slots,
+        pg_stat_wal_receiver,
+        pg_stat_progress,
+        pg_stat_user_tables_sum,
+        pg_stat_user_indexes_sum,
+        pg_stat_database_sum_per_database,
+        proc_stat_sum,
+        proc_loadavg,
+        proc_meminfo,
+        proc_vmstat_sum,
+        proc_diskstats_sum,
+        collection_gaps,
+        collector_statistics,
+        pg_settings_changes,
+        pg_xmin_horizon,
+        connection_churn
+    );
+    // consecutive archives can contain the same settings snapshot, see save_to_disk.
+    for record in transition.pg_settings {
+        let mut pg_settings = DATA.pg_settings.write().await;
+        if !pg_settings.iter().any(|(ts, _)| *ts == record.0) {
+            pg_settings.push_back(record).unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::webserver::ash_query_from_parameters;
+use crate::DATA;
+
+// the series of DATA that the api returns, by the name of the field.
+macro_rules! api_series {
+    ($($category:ident),*) => {
+        static SERIES: &[&str] = &[$(stringify!($category)),*];
+
+        // the samples of a series in the time range, as [timestamp, value] pairs.
+        async fn series_json(
+            name: &str,
+            start_time: Option<DateTime<Local>>,
+            end_time: Option<DateTime<Local>>,
+        ) -> Option<Value> {
+            let in_range = |timestamp: &DateTime<Local>| {
+                start_time.is_none_or(|start_time| *timestamp >= start_time)
+                    && end_time.is_none_or(|end_time| *timestamp <= end_time)
+            };
+            match name {
+                $(
+                stringify!($category) => Some(json!(DATA
+                    .$category
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|(timestamp, _)| in_range(timestamp))
+                    .collect::<Vec<_>>())),
+                )*
+                _ => None,
+            }
+        }
+    };
+}
+
+api_series!(
+    pg_stat_activity,
+    pg_stat_database_sum,
+    pg_stat_bgwriter_sum,
+    pg_stat_wal_sum,
+    pg_database_xid_limits,
+    pg_stat_statements,
+    pg_stat_checkpointer_sum,
+    pg_stat_io_sum,
+    pg_lock_edges,
+    pg_stat_replication,
+    pg_replication_slots,
+    pg_stat_wal_receiver,
+    pg_stat_progress,
+    pg_stat_user_tables_sum,
+    pg_stat_user_indexes_sum,
+    pg_stat_database_sum_per_database,
+    proc_stat_sum,
+    proc_loadavg,
+    proc_meminfo,
+    proc_vmstat_sum,
+    proc_diskstats_sum,
+    collection_gaps,
+    collector_statistics,
+    pg_settings,
+    pg_settings_changes,
+    pg_xmin_horizon,
+    connection_churn
+);
+
+pub type TimeRange = (Option<DateTime<Local>>, Option<DateTime<Local>>);
+
+// the active sessions of a group of the ash query.
+#[derive(Serialize)]
+struct AshGroup {
+    group: String,
+    samples: usize,
+    percentage: f64,
+    average_active_sessions: f64,
+}
+
+#[derive(Serialize)]
+struct AshSample {
+    timestamp: DateTime<Local>,
+    counts: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct AshResult {
+    group_by: &'static str,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+    sample_count: usize,
+    max_active: usize,
+    groups: Vec<AshGroup>,
+    samples: Vec<AshSample>,
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+// a timestamp is rfc 3339, such as 2024-06-01T12:00:00+02:00, or a local time with second or
+// fractional second precision, such as 2024-06-01T12:00:00 or 2024-06-01 12:00:00. a + in an
+// unencoded url is decoded as a space, so the offset is also accepted with a space.
+pub fn parse_time(text: &str) -> Option<DateTime<Local>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(text)
+        .or_else(|_| DateTime::parse_from_rfc3339(&text.replace(' ', "+")))
+    {
+        return Some(timestamp.with_timezone(&Local));
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .and_then(|timestamp| Local.from_local_datetime(&timestamp).earliest())
+}
+
+// a relative time range is a number of seconds, minutes, hours or days, such as 90s, 15m, 1h or
+// 2d. a number without unit is a number of seconds.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (number, unit) = text.split_at(
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len()),
+    );
+    let number = number.parse::<i64>().ok()?;
+    match unit {
+        "" | "s" => Duration::try_seconds(number),
+        "m" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        _ => None,
+    }
+}
+
+// the start and end parameters, a missing parameter is an open end of the time range. the last
+// parameter is the time range up to now.
+pub fn time_range_from_parameters(
+    parameters: &HashMap<String, String>,
+) -> Result<TimeRange, String> {
+    if let Some(text) = parameters.get("last") {
+        let duration = parse_duration(text)
+            .ok_or_else(|| format!("last is not a valid duration, such as 15m: {}", text))?;
+        return Ok((Some(Local::now() - duration), None));
+    }
+    let time = |name: &str| match parameters.get(name) {
+        Some(text) => parse_time(text)
+            .map(Some)
+            .ok_or_else(|| format!("{} is not a valid timestamp: {}", name, text)),
+        None => Ok(None),
+    };
+    Ok((time("start")?, time("end")?))
+}
+
+pub async fn api_series_list() -> Json<&'static [&'static str]> {
+    Json(SERIES)
+}
+
+pub async fn api_series(
+    Path(name): Path<String>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Response {
+    let (start_time, end_time) = match time_range_from_parameters(&parameters) {
+        Ok(time_range) => time_range,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, message),
+    };
+    match series_json(&name, start_time, end_time).await {
+        Some(series) => Json(series).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, format!("unknown series: {}", name)),
+    }
+}
+
+// the active sessions grouped and filtered like the ash page, without the database of the
+// session.
+pub async fn api_ash(Query(parameters): Query<HashMap<String, String>>) -> Response {
+    let (start_time, end_time) = match time_range_from_parameters(&parameters) {
+        Ok(time_range) => time_range,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, message),
+    };
+    let ash_query = ash_query_from_parameters(&parameters, None);
+    let aggregate = ash_query.aggregate(start_time, end_time);
+    let total_samples = aggregate.totals.values().sum::<usize>();
+    let sample_count = aggregate.sample_count.max(1);
+    Json(AshResult {
+        group_by: ash_query.group_by.name(),
+        start_time: aggregate.start_time,
+        end_time: aggregate.end_time,
+        sample_count: aggregate.sample_count,
+        max_active: aggregate.max_active,
+        groups: aggregate
+            .groups()
+            .into_iter()
+            .map(|(group, total)| AshGroup {
+                group: group.clone(),
+                samples: *total,
+                percentage: *total as f64 / total_samples.max(1) as f64 * 100_f64,
+                average_active_sessions: *total as f64 / sample_count as f64,
+            })
+            .collect(),
+        samples: aggregate
+            .samples
+            .into_iter()
+            .map(|(timestamp, counts)| AshSample { timestamp, counts })
+            .collect(),
+    })
+    .into_response()
+}
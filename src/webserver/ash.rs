@@ -0,0 +1,466 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_400;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::processor::{PgStatActivity, Schedule};
+use crate::webserver::collection_gaps::{collection_gap_starts, draw_collection_gaps, gap_between};
+use crate::webserver::settings::draw_settings_changes;
+use crate::webserver::{escape_html, wait_type_color};
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the number of groups that are plotted, the other groups are added together.
+static TOP_GROUPS: usize = 20;
+
+// the legend is cut off after this number of characters, for long values such as a query.
+static LABEL_LENGTH: usize = 60;
+
+// a field of pg_stat_activity that the active sessions can be grouped by or filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AshDimension {
+    Datname,
+    Usename,
+    ApplicationName,
+    ClientAddr,
+    BackendType,
+    WaitEventType,
+    WaitEvent,
+    QueryId,
+    LeaderPid,
+    Query,
+}
+
+impl AshDimension {
+    pub const ALL: [AshDimension; 10] = [
+        AshDimension::Datname,
+        AshDimension::Usename,
+        AshDimension::ApplicationName,
+        AshDimension::ClientAddr,
+        AshDimension::BackendType,
+        AshDimension::WaitEventType,
+        AshDimension::WaitEvent,
+        AshDimension::QueryId,
+        AshDimension::LeaderPid,
+        AshDimension::Query,
+    ];
+    // the name is the column name in pg_stat_activity.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AshDimension::Datname => "datname",
+            AshDimension::Usename => "usename",
+            AshDimension::ApplicationName => "application_name",
+            AshDimension::ClientAddr => "client_addr",
+            AshDimension::BackendType => "backend_type",
+            AshDimension::WaitEventType => "wait_event_type",
+            AshDimension::WaitEvent => "wait_event",
+            AshDimension::QueryId => "query_id",
+            AshDimension::LeaderPid => "leader_pid",
+            AshDimension::Query => "query",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<AshDimension> {
+        AshDimension::ALL.into_iter().find(|r| r.name() == name)
+    }
+    // the value of the dimension for a session. a session that is not waiting is on cpu, the
+    // wait event type is ~on_cpu so it is stacked on top, the wait event is prefixed with its
+    // type. a session without a client address is local, and a session that is not a parallel
+    // worker has no leader.
+    pub fn value(&self, row: &PgStatActivity) -> String {
+        let text = |value: &Option<String>| value.as_deref().unwrap_or_default().to_string();
+        match self {
+            AshDimension::Datname => text(&row.datname),
+            AshDimension::Usename => text(&row.usename),
+            AshDimension::ApplicationName => text(&row.application_name),
+            AshDimension::ClientAddr => row
+                .client_addr
+                .clone()
+                .unwrap_or_else(|| "local".to_string()),
+            AshDimension::BackendType => text(&row.backend_type),
+            AshDimension::WaitEventType => match row.wait_event_type.as_deref() {
+                None | Some("") => "~on_cpu".to_string(),
+                Some(wait_event_type) => wait_event_type.to_string(),
+            },
+            AshDimension::WaitEvent => match (
+                row.wait_event_type.as_deref().unwrap_or_default(),
+                row.wait_event.as_deref().unwrap_or_default(),
+            ) {
+                ("", "") => "on_cpu".to_string(),
+                (wait_event_type, wait_event) => format!("{}:{}", wait_event_type, wait_event),
+            },
+            AshDimension::QueryId => row.query_id.unwrap_or_default().to_string(),
+            AshDimension::LeaderPid => row.leader_pid.map_or("none".to_string(), |r| r.to_string()),
+            AshDimension::Query => text(&row.query),
+        }
+    }
+}
+
+// a filter on a dimension: the session must have one of the values, or none of the values when
+// the filter excludes. a wait event matches both as type:event and as the event alone, so
+// clientread excludes client:clientread.
+#[derive(Debug, Clone)]
+pub struct AshFilter {
+    pub dimension: AshDimension,
+    pub values: Vec<String>,
+    pub exclude: bool,
+}
+
+impl AshFilter {
+    // the text is a comma separated list of values, with a leading ! to exclude them.
+    pub fn parse(dimension: AshDimension, text: &str) -> AshFilter {
+        let (exclude, values) = match text.strip_prefix('!') {
+            Some(values) => (true, values),
+            None => (false, text),
+        };
+        AshFilter {
+            dimension,
+            values: values.split(',').map(|r| r.trim().to_string()).collect(),
+            exclude,
+        }
+    }
+    pub fn matches(&self, row: &PgStatActivity) -> bool {
+        let value = self.dimension.value(row);
+        let found = self.values.iter().any(|r| {
+            *r == value
+                || (self.dimension == AshDimension::WaitEvent
+                    && value.split_once(':').is_some_and(|(_, event)| event == r))
+        });
+        found != self.exclude
+    }
+}
+
+// the active sessions of pg_stat_activity, grouped by a dimension, for the sessions that pass all
+// filters.
+#[derive(Debug, Clone)]
+pub struct AshQuery {
+    pub group_by: AshDimension,
+    pub filters: Vec<AshFilter>,
+}
+
+// the number of active sessions per group at every sample, with empty samples at both sides of a
+// gap, the total number of active sessions per group, the highest number of active sessions and
+// the number of samples.
+#[derive(Debug, Default)]
+pub struct AshAggregate {
+    pub samples: Vec<(DateTime<Local>, BTreeMap<String, usize>)>,
+    pub totals: BTreeMap<String, usize>,
+    pub max_active: usize,
+    pub sample_count: usize,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+}
+
+impl AshAggregate {
+    // the groups with the most active sessions first.
+    pub fn groups(&self) -> Vec<(&String, &usize)> {
+        let mut groups: Vec<(&String, &usize)> = self.totals.iter().collect();
+        groups.sort_by_key(|(_, total)| std::cmp::Reverse(**total));
+        groups
+    }
+    // the groups that are plotted, and the active sessions per plotted group at every sample.
+    pub fn top_groups(&self) -> AshTopGroups {
+        let mut groups: Vec<String> = self
+            .groups()
+            .into_iter()
+            .take(TOP_GROUPS)
+            .map(|(group, _)| group.clone())
+            .collect();
+        groups.sort();
+        let group_position: HashMap<&str, usize> = groups
+            .iter()
+            .enumerate()
+            .map(|(position, group)| (group.as_str(), position))
+            .collect();
+        let samples = self
+            .samples
+            .iter()
+            .map(|(timestamp, counts)| {
+                let mut per_group = vec![0_usize; groups.len() + 1];
+                for (group, count) in counts.iter() {
+                    per_group[group_position
+                        .get(group.as_str())
+                        .copied()
+                        .unwrap_or(groups.len())] += count;
+                }
+                (*timestamp, per_group)
+            })
+            .collect();
+        AshTopGroups {
+            other_groups: self.totals.len() - groups.len(),
+            groups,
+            samples,
+        }
+    }
+}
+
+// the TOP_GROUPS groups with the most active sessions, sorted by value, the number of other
+// groups, and the active sessions per group at every sample, where the last group is the other
+// groups added together.
+pub struct AshTopGroups {
+    pub groups: Vec<String>,
+    pub other_groups: usize,
+    pub samples: Vec<(DateTime<Local>, Vec<usize>)>,
+}
+
+impl AshQuery {
+    pub fn new(group_by: AshDimension) -> AshQuery {
+        AshQuery {
+            group_by,
+            filters: Vec::new(),
+        }
+    }
+    pub fn filter(mut self, filter: AshFilter) -> AshQuery {
+        self.filters.push(filter);
+        self
+    }
+    // the common filters of the ash plots: clientread is the time a session waits for the
+    // client, which is not time in the database, and the database selected in the session.
+    pub fn exclude_clientread_and_database(
+        self,
+        exclude_clientread: bool,
+        database: Option<&str>,
+    ) -> AshQuery {
+        let mut query = self;
+        if exclude_clientread {
+            query = query.filter(AshFilter::parse(AshDimension::WaitEvent, "!clientread"));
+        }
+        if let Some(database) = database {
+            query = query.filter(AshFilter {
+                dimension: AshDimension::Datname,
+                values: vec![database.to_string()],
+                exclude: false,
+            });
+        }
+        query
+    }
+    // the time range defaults to the first and last sample of pg_stat_activity.
+    pub fn aggregate(
+        &self,
+        start_time: Option<DateTime<Local>>,
+        end_time: Option<DateTime<Local>>,
+    ) -> AshAggregate {
+        let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+        let mut aggregate = AshAggregate {
+            start_time: start_time.unwrap_or_else(|| {
+                pg_stat_activity
+                    .front()
+                    .map(|(timestamp, _)| *timestamp)
+                    .unwrap_or_default()
+            }),
+            end_time: end_time.unwrap_or_else(|| {
+                pg_stat_activity
+                    .back()
+                    .map(|(timestamp, _)| *timestamp)
+                    .unwrap_or_default()
+            }),
+            ..Default::default()
+        };
+        let gap_starts = collection_gap_starts(Schedule::Activity);
+        let mut previous_timestamp: Option<DateTime<Local>> = None;
+        // the samples are in the order of their timestamp, so the first sample in the time range
+        // is searched, and only the samples in the time range are visited. the live ash chart
+        // aggregates only the samples after the last sample that it received.
+        let first =
+            pg_stat_activity.partition_point(|(timestamp, _)| *timestamp < aggregate.start_time);
+        for (timestamp, per_sample_vector) in pg_stat_activity
+            .range(first..)
+            .take_while(|(timestamp, _)| *timestamp <= aggregate.end_time)
+        {
+            // the areas drop to zero at both sides of a gap in the samples
+            if let Some((after_previous, before_current)) = previous_timestamp
+                .and_then(|previous| gap_between(&gap_starts, previous, *timestamp))
+            {
+                aggregate.samples.push((after_previous, BTreeMap::new()));
+                aggregate.samples.push((before_current, BTreeMap::new()));
+            }
+            previous_timestamp = Some(*timestamp);
+            aggregate.sample_count += 1;
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for row in per_sample_vector
+                .iter()
+                .filter(|r| r.state.as_deref().unwrap_or_default() == "active")
+                .filter(|r| self.filters.iter().all(|filter| filter.matches(r)))
+            {
+                *counts.entry(self.group_by.value(row)).or_default() += 1;
+            }
+            aggregate.max_active = aggregate.max_active.max(counts.values().sum());
+            for (group, count) in counts.iter() {
+                *aggregate.totals.entry(group.clone()).or_default() += count;
+            }
+            aggregate.samples.push((*timestamp, counts));
+        }
+        aggregate
+    }
+}
+
+pub fn group_color(group_by: AshDimension, group: &str, group_number: usize) -> RGBColor {
+    if group_by == AshDimension::WaitEventType {
+        wait_type_color(group)
+    } else {
+        let (red, green, blue) = Palette99::pick(group_number).rgb();
+        RGBColor(red, green, blue)
+    }
+}
+
+fn label(group: &str) -> String {
+    match group.char_indices().nth(LABEL_LENGTH) {
+        Some((position, _)) => format!("{}...", &group[..position]),
+        None => group.to_string(),
+    }
+}
+
+// the active sessions of an ash query, stacked per group. the groups are stacked in the order
+// of their value, so the colors of the wait event types are always in the same place, and the
+// groups after the TOP_GROUPS groups with the most active sessions are added together.
+pub fn ash_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    ash_query: &AshQuery,
+    caption: &str,
+    y_description: &str,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let aggregate = ash_query.aggregate(start_time, end_time);
+    let AshTopGroups {
+        groups,
+        other_groups,
+        samples: stacked,
+    } = aggregate.top_groups();
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(caption, (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(
+            aggregate.start_time..aggregate.end_time,
+            0_usize..aggregate.max_active,
+        )
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc(y_description)
+        .y_label_formatter(&|sessions| format!("{:4.0}", sessions))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Activity);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            stacked
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_usize)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:25} {:>10}  {:>5}", "", "tot", "%"));
+
+    let total_samples = aggregate.totals.values().sum::<usize>();
+    let mut series: Vec<(String, usize, RGBColor)> = groups
+        .iter()
+        .enumerate()
+        .map(|(group_number, group)| {
+            (
+                label(group),
+                aggregate.totals[group],
+                group_color(ash_query.group_by, group, group_number),
+            )
+        })
+        .collect();
+    if other_groups > 0 {
+        series.push((
+            format!("other ({} groups)", other_groups),
+            total_samples - series.iter().map(|(_, total, _)| total).sum::<usize>(),
+            GREY_400,
+        ));
+    }
+    for (series_number, (label, total, color)) in series.into_iter().enumerate() {
+        contextarea
+            .draw_series(AreaSeries::new(
+                stacked.iter().map(|(timestamp, per_group)| {
+                    (*timestamp, per_group[series_number..].iter().sum::<usize>())
+                }),
+                0,
+                color,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10}  {:>5.2}",
+                label,
+                total,
+                total as f64 / total_samples as f64 * 100_f64
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the active sessions per group of an ash query, with the share of the active sessions and the
+// average number of active sessions over the samples.
+pub fn ash_html(
+    ash_query: &AshQuery,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let aggregate = ash_query.aggregate(start_time, end_time);
+    if aggregate.totals.is_empty() {
+        return "<p>No active sessions found in the selected time range.</p>".to_string();
+    }
+    let total_samples = aggregate.totals.values().sum::<usize>();
+    let sample_count = aggregate.sample_count.max(1);
+    let mut html_output = format!(
+        r#"<table border=1>
+            <tr>
+                <th>{}</th>
+                <th align=right>Samples</th>
+                <th align=right>%</th>
+                <th align=right>Average active sessions</th>
+            </tr>"#,
+        ash_query.group_by.name()
+    );
+    for (group, total) in aggregate.groups() {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+            </tr>"#,
+            escape_html(group),
+            total,
+            *total as f64 / total_samples as f64 * 100_f64,
+            *total as f64 / sample_count as f64,
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
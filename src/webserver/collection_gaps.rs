@@ -0,0 +1,80 @@
+use crate::processor::Schedule;
+use crate::DATA;
+
+use chrono::{DateTime, Local, TimeDelta};
+use futures::executor;
+use plotters::coord::ranged1d::Ranged;
+use plotters::coord::types::RangedDateTime;
+use plotters::prelude::*;
+use plotters::style::full_palette::GREY_400;
+
+// shade the periods in which the schedule of a plot did not collect statistics, so a gap in the
+// data, such as a restart of the database, is not read as a period with normal activity. the
+// gaps are drawn before the series, so the series are drawn on top of them.
+pub fn draw_collection_gaps<DB, Y>(
+    contextarea: &mut ChartContext<DB, Cartesian2d<RangedDateTime<DateTime<Local>>, Y>>,
+    schedule: Schedule,
+) where
+    DB: DrawingBackend,
+    Y: Ranged,
+    Y::ValueType: Clone + 'static,
+{
+    let x_range = contextarea.x_range();
+    let y_range = contextarea.y_range();
+    let gaps: Vec<(DateTime<Local>, DateTime<Local>)> =
+        executor::block_on(DATA.collection_gaps.read())
+            .iter()
+            .map(|(_, gap)| gap)
+            .filter(|gap| gap.schedule == schedule)
+            .filter(|gap| gap.end_time >= x_range.start && gap.start_time <= x_range.end)
+            .map(|gap| {
+                (
+                    gap.start_time.max(x_range.start),
+                    gap.end_time.min(x_range.end),
+                )
+            })
+            .collect();
+    if gaps.is_empty() {
+        return;
+    }
+    contextarea
+        .draw_series(gaps.iter().map(|(start_time, end_time)| {
+            Rectangle::new(
+                [
+                    (*start_time, y_range.start.clone()),
+                    (*end_time, y_range.end.clone()),
+                ],
+                GREY_400.mix(0.3).filled(),
+            )
+        }))
+        .unwrap();
+}
+
+// the start times of the gaps of a schedule, for the plots that are stacked areas, which would
+// otherwise be drawn as a slope from the last sample before a gap to the first sample after it.
+pub fn collection_gap_starts(schedule: Schedule) -> Vec<DateTime<Local>> {
+    executor::block_on(DATA.collection_gaps.read())
+        .iter()
+        .map(|(_, gap)| gap)
+        .filter(|gap| gap.schedule == schedule)
+        .map(|gap| gap.start_time)
+        .collect()
+}
+
+// when a gap starts between two consecutive samples, return the timestamps just after the
+// previous and just before the current sample, to add zero samples at both sides of the gap.
+pub fn gap_between(
+    gap_starts: &[DateTime<Local>],
+    previous: DateTime<Local>,
+    current: DateTime<Local>,
+) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    gap_starts
+        .iter()
+        .any(|r| *r >= previous && *r < current)
+        .then(|| {
+            (
+                previous + TimeDelta::milliseconds(1),
+                current - TimeDelta::milliseconds(1),
+            )
+        })
+}
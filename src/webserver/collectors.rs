@@ -0,0 +1,436 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound::Included;
+
+use crate::processor::{CollectorStatistics, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::{
+    ARGS, CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, DATA, LABELS_STYLE_FONT,
+    LABELS_STYLE_FONT_SIZE, LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT,
+    MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE,
+};
+
+// the number of collectors that are shown individually, the other collectors are added up.
+static TOP_COLLECTORS: usize = 8;
+
+type CollectorSample = (DateTime<Local>, f64, Vec<CollectorStatistics>);
+
+// the collector statistics in the time range, with the number of seconds each entry covers, which
+// is the time since the previous entry.
+fn collector_samples(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> (DateTime<Local>, DateTime<Local>, Vec<CollectorSample>) {
+    let collector_statistics = executor::block_on(DATA.collector_statistics.read());
+    let final_start_time = start_time.unwrap_or_else(|| {
+        collector_statistics
+            .front()
+            .map_or_else(Local::now, |(timestamp, _)| *timestamp)
+    });
+    let final_end_time = end_time.unwrap_or_else(|| {
+        collector_statistics
+            .back()
+            .map_or_else(Local::now, |(timestamp, _)| *timestamp)
+    });
+    let mut samples: Vec<CollectorSample> = Vec::new();
+    let mut previous_timestamp: Option<DateTime<Local>> = None;
+    for (timestamp, per_sample_vector) in collector_statistics.iter() {
+        let seconds = previous_timestamp.map_or(ARGS.interval as f64, |previous| {
+            (*timestamp - previous).num_milliseconds() as f64 / 1000_f64
+        });
+        previous_timestamp = Some(*timestamp);
+        if *timestamp >= final_start_time && *timestamp <= final_end_time && seconds > 0_f64 {
+            samples.push((*timestamp, seconds, per_sample_vector.clone()));
+        }
+    }
+    (final_start_time, final_end_time, samples)
+}
+
+// the time spent per collector in milliseconds per second, stacked, with the time spent in the
+// queries of all collectors as a line.
+pub fn collector_time<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time, samples) = collector_samples(start_time, end_time);
+
+    let mut collector_totals: HashMap<String, f64> = HashMap::new();
+    for (_, _, per_sample_vector) in samples.iter() {
+        for row in per_sample_vector.iter().filter(|r| r.calls > 0) {
+            *collector_totals.entry(row.collector.clone()).or_default() += row.elapsed_time;
+        }
+    }
+    let mut top_collectors: Vec<(String, f64)> = collector_totals.into_iter().collect();
+    top_collectors.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let has_other = top_collectors.len() > TOP_COLLECTORS;
+    top_collectors.truncate(TOP_COLLECTORS);
+    let rank_of_collector: HashMap<&str, usize> = top_collectors
+        .iter()
+        .enumerate()
+        .map(|(rank, (collector, _))| (collector.as_str(), rank))
+        .collect();
+    let other_rank = top_collectors.len();
+    let last_rank = if has_other {
+        other_rank
+    } else {
+        other_rank.saturating_sub(1)
+    };
+
+    let mut timestamp_and_collectors: Vec<(DateTime<Local>, BTreeMap<usize, f64>)> = Vec::new();
+    let mut timestamp_and_query_time: Vec<(DateTime<Local>, f64)> = Vec::new();
+    let mut high_value = 0_f64;
+    for (timestamp, seconds, per_sample_vector) in samples.iter() {
+        let mut current_collectors: BTreeMap<usize, f64> =
+            (0..=last_rank).map(|r| (r, 0_f64)).collect();
+        for row in per_sample_vector.iter().filter(|r| r.calls > 0) {
+            let rank = rank_of_collector
+                .get(row.collector.as_str())
+                .unwrap_or(&other_rank);
+            *current_collectors.entry(*rank).or_default() += row.elapsed_time / seconds;
+        }
+        high_value = high_value.max(current_collectors.values().sum::<f64>());
+        timestamp_and_collectors.push((*timestamp, current_collectors));
+        timestamp_and_query_time.push((
+            *timestamp,
+            per_sample_vector.iter().map(|r| r.query_time).sum::<f64>() / seconds,
+        ));
+    }
+    let high_value = high_value.max(1_f64) * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            "PAS collector time",
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Milliseconds per second")
+        .y_label_formatter(&|value| format!("{:.1}", value))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            timestamp_and_collectors
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:30} {:>10} {:>10}", "", "max", "last"));
+
+    let mut legend: Vec<(usize, String)> = top_collectors
+        .iter()
+        .enumerate()
+        .map(|(rank, (collector, _))| (rank, collector.clone()))
+        .collect();
+    if has_other {
+        legend.push((other_rank, "other".to_string()));
+    }
+    for (rank, collector) in legend {
+        let max_value = timestamp_and_collectors
+            .iter()
+            .map(|(_, collectors)| *collectors.get(&rank).unwrap_or(&0_f64))
+            .fold(0_f64, f64::max);
+        let last_value = timestamp_and_collectors
+            .last()
+            .map_or(0_f64, |(_, collectors)| {
+                *collectors.get(&rank).unwrap_or(&0_f64)
+            });
+        contextarea
+            .draw_series(AreaSeries::new(
+                timestamp_and_collectors
+                    .iter()
+                    .map(|(timestamp, collectors)| {
+                        (
+                            *timestamp,
+                            collectors
+                                .range((Included(rank), Included(last_rank)))
+                                .map(|(_, v)| *v)
+                                .sum::<f64>(),
+                        )
+                    }),
+                0_f64,
+                Palette99::pick(rank),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:30} {:>10.2} {:>10.2}",
+                collector, max_value, last_value
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(rank).filled(),
+                )
+            });
+    }
+    let max_query_time = timestamp_and_query_time
+        .iter()
+        .map(|(_, query_time)| *query_time)
+        .fold(0_f64, f64::max);
+    let last_query_time = timestamp_and_query_time
+        .last()
+        .map_or(0_f64, |(_, query_time)| *query_time);
+    contextarea
+        .draw_series(LineSeries::new(
+            timestamp_and_query_time.iter().copied(),
+            BLACK.stroke_width(2),
+        ))
+        .unwrap()
+        .label(format!(
+            "{:30} {:>10.2} {:>10.2}",
+            "query time", max_query_time, last_query_time
+        ))
+        .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLACK.filled()));
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the time the collectors waited for and held write locks on DATA, in milliseconds per second.
+// a high wait time means the webserver holds read locks long enough to delay the collection.
+pub fn collector_lock_time<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time, samples) = collector_samples(start_time, end_time);
+    let lock_wait_time: Vec<(DateTime<Local>, f64)> = samples
+        .iter()
+        .map(|(timestamp, seconds, per_sample_vector)| {
+            (
+                *timestamp,
+                per_sample_vector
+                    .iter()
+                    .map(|r| r.lock_wait_time)
+                    .sum::<f64>()
+                    / seconds,
+            )
+        })
+        .collect();
+    let lock_hold_time: Vec<(DateTime<Local>, f64)> = samples
+        .iter()
+        .map(|(timestamp, seconds, per_sample_vector)| {
+            (
+                *timestamp,
+                per_sample_vector
+                    .iter()
+                    .map(|r| r.lock_hold_time)
+                    .sum::<f64>()
+                    / seconds,
+            )
+        })
+        .collect();
+    let high_value = lock_wait_time
+        .iter()
+        .chain(lock_hold_time.iter())
+        .map(|(_, value)| *value)
+        .fold(0_f64, f64::max)
+        .max(0.1_f64)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            "PAS collector lock times",
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Milliseconds per second")
+        .y_label_formatter(&|value| format!("{:.2}", value))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            lock_wait_time.iter().take(1).copied(),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:30} {:>10} {:>10}", "", "max", "last"));
+
+    for (name, series, color) in [
+        ("lock wait time", &lock_wait_time, RED),
+        ("lock hold time", &lock_hold_time, BLUE),
+    ] {
+        let max_value = series.iter().map(|(_, value)| *value).fold(0_f64, f64::max);
+        let last_value = series.last().map_or(0_f64, |(_, value)| *value);
+        contextarea
+            .draw_series(LineSeries::new(series.iter().copied(), color))
+            .unwrap()
+            .label(format!(
+                "{:30} {:>10.3} {:>10.3}",
+                name, max_value, last_value
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the overhead per collector and the missed ticks per schedule in the time range.
+pub fn collectors_html(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let (_, _, samples) = collector_samples(start_time, end_time);
+    let seconds = samples
+        .iter()
+        .map(|(_, seconds, _)| *seconds)
+        .sum::<f64>()
+        .max(1_f64);
+
+    let mut collectors: BTreeMap<String, CollectorStatistics> = BTreeMap::new();
+    for (_, _, per_sample_vector) in samples.iter() {
+        for row in per_sample_vector.iter() {
+            let collector = collectors.entry(row.collector.clone()).or_default();
+            collector.calls += row.calls;
+            collector.failures += row.failures;
+            collector.rows += row.rows;
+            collector.elapsed_time += row.elapsed_time;
+            collector.max_elapsed_time = collector.max_elapsed_time.max(row.max_elapsed_time);
+            collector.query_time += row.query_time;
+            collector.lock_wait_time += row.lock_wait_time;
+            collector.lock_hold_time += row.lock_hold_time;
+            collector.missed_ticks += row.missed_ticks;
+        }
+    }
+    let mut collectors: Vec<(String, CollectorStatistics)> = collectors.into_iter().collect();
+    collectors.sort_by(|(_, a), (_, b)| b.elapsed_time.total_cmp(&a.elapsed_time));
+
+    let mut html_output = format!(
+        r#"<p>In {:.0} seconds, the queries of PAS took {:.2} ms per second, and the collectors {:.2} ms per second.</p>"#,
+        seconds,
+        collectors.iter().map(|(_, r)| r.query_time).sum::<f64>() / seconds,
+        collectors.iter().map(|(_, r)| r.elapsed_time).sum::<f64>() / seconds,
+    );
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Collector</th>
+                <th align=right>Calls</th>
+                <th align=right>Failures</th>
+                <th align=right>Rows/call</th>
+                <th align=right>Avg ms</th>
+                <th align=right>Max ms</th>
+                <th align=right>Query ms/s</th>
+                <th align=right>Total ms/s</th>
+                <th align=right>Lock wait ms</th>
+                <th align=right>Lock hold ms</th>
+            </tr>"#;
+    for (name, collector) in collectors.iter().filter(|(_, r)| r.calls > 0) {
+        let calls = collector.calls as f64;
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{:.0}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.3}</td>
+                <td align=right>{:.3}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+            </tr>"#,
+            name,
+            collector.calls,
+            collector.failures,
+            collector.rows as f64 / calls,
+            collector.elapsed_time / calls,
+            collector.max_elapsed_time,
+            collector.query_time / seconds,
+            collector.elapsed_time / seconds,
+            collector.lock_wait_time,
+            collector.lock_hold_time,
+        )
+        .as_str();
+    }
+    html_output += "</table><br>";
+
+    // the schedules only have missed ticks, a missed tick means a collection took longer than
+    // the interval of its schedule.
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Schedule</th>
+                <th align=right>Missed ticks</th>
+            </tr>"#;
+    for schedule in [
+        Schedule::Activity,
+        Schedule::Statistics,
+        Schedule::Settings,
+        Schedule::Os,
+    ] {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            schedule.name(),
+            collectors
+                .iter()
+                .find(|(name, _)| name == schedule.name())
+                .map_or(0, |(_, r)| r.missed_ticks),
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+
+    html_output
+}
@@ -0,0 +1,474 @@
+use chrono::{DateTime, Duration, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{GREY_400, ORANGE, PURPLE};
+use std::collections::BTreeMap;
+
+use crate::processor::{ConnectionChurn, PgStatDatabaseSum, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::pg_stat_database_sum;
+use crate::webserver::sessions::activity_time_range;
+use crate::webserver::settings::draw_settings_changes;
+use crate::{
+    ARGS, CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, DATA, LABELS_STYLE_FONT,
+    LABELS_STYLE_FONT_SIZE, LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT,
+    MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE,
+};
+
+// the number of client groups that are plotted, the other groups are added together.
+static TOP_GROUPS: usize = 6;
+
+// the maximal number of buckets over the time range, the connects are counted per bucket.
+static BUCKETS: i64 = 100;
+
+// the connects and disconnects of a group of clients in the time range.
+#[derive(Default)]
+struct ClientGroup {
+    datname: String,
+    application_name: String,
+    usename: String,
+    client_addr: String,
+    connects: u64,
+    disconnects: u64,
+}
+
+impl ClientGroup {
+    fn label(&self) -> String {
+        format!(
+            "{} {}@{}",
+            if self.application_name.is_empty() {
+                "(no application)"
+            } else {
+                self.application_name.as_str()
+            },
+            self.usename,
+            self.client_addr
+        )
+    }
+}
+
+// the connection churn per client group in the time range, most connects first. a group is
+// identified by the database, application, user and client address.
+fn client_groups(
+    database: Option<&str>,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+) -> Vec<ClientGroup> {
+    let mut groups: BTreeMap<(String, String, String, String), ClientGroup> = BTreeMap::new();
+    for r in executor::block_on(DATA.connection_churn.read())
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+        .flat_map(|(_, r)| r.iter())
+        .filter(|r| database.is_none_or(|d| r.datname == d))
+    {
+        let group = groups
+            .entry((
+                r.datname.clone(),
+                r.application_name.clone(),
+                r.usename.clone(),
+                r.client_addr.clone(),
+            ))
+            .or_insert_with(|| ClientGroup {
+                datname: r.datname.clone(),
+                application_name: r.application_name.clone(),
+                usename: r.usename.clone(),
+                client_addr: r.client_addr.clone(),
+                ..Default::default()
+            });
+        group.connects += r.connects;
+        group.disconnects += r.disconnects;
+    }
+    let mut groups: Vec<ClientGroup> = groups.into_values().collect();
+    groups.sort_by_key(|r| std::cmp::Reverse(r.connects));
+    groups
+}
+
+// the bucket width in seconds: the statistics interval, or wider for a long time range.
+fn bucket_seconds(start_time: DateTime<Local>, end_time: DateTime<Local>) -> i64 {
+    ((end_time - start_time).num_seconds() / BUCKETS).max(ARGS.interval as i64)
+}
+
+// the connects per second of every bucket, per group. the key of a group is its position in
+// groups, the groups that are not in groups have key groups.len().
+fn connect_rates(
+    groups: &[ClientGroup],
+    database: Option<&str>,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+) -> Vec<(DateTime<Local>, Vec<f64>)> {
+    let bucket_seconds = bucket_seconds(start_time, end_time);
+    let buckets = ((end_time - start_time).num_seconds() / bucket_seconds + 1).max(1) as usize;
+    let mut rates: Vec<(DateTime<Local>, Vec<f64>)> = (0..buckets)
+        .map(|bucket| {
+            (
+                start_time + Duration::seconds(bucket as i64 * bucket_seconds),
+                vec![0_f64; groups.len() + 1],
+            )
+        })
+        .collect();
+    let group_key = |r: &ConnectionChurn| {
+        groups
+            .iter()
+            .position(|g| {
+                g.datname == r.datname
+                    && g.application_name == r.application_name
+                    && g.usename == r.usename
+                    && g.client_addr == r.client_addr
+            })
+            .unwrap_or(groups.len())
+    };
+    for (timestamp, churn) in executor::block_on(DATA.connection_churn.read())
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+    {
+        let bucket = ((*timestamp - start_time).num_seconds() / bucket_seconds) as usize;
+        for r in churn
+            .iter()
+            .filter(|r| database.is_none_or(|d| r.datname == d))
+        {
+            rates[bucket.min(buckets - 1)].1[group_key(r)] +=
+                r.connects as f64 / bucket_seconds as f64;
+        }
+    }
+    rates
+}
+
+// the sessions, and the sessions that ended by a fatal error, were killed or were abandoned, per
+// second, from the cumulative counters of pg_stat_database. these exist from version 14,
+// for older versions there are no rates.
+fn database_session_rates(
+    database: Option<&str>,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+) -> Vec<(DateTime<Local>, [f64; 4])> {
+    let pg_stat_database = pg_stat_database_sum(database);
+    let counters = |r: &PgStatDatabaseSum| {
+        Some([
+            r.sessions?,
+            r.sessions_fatal?,
+            r.sessions_killed?,
+            r.sessions_abandoned?,
+        ])
+    };
+    pg_stat_database
+        .iter()
+        .zip(pg_stat_database.iter().skip(1))
+        .filter(|(_, (timestamp, _))| *timestamp >= start_time && *timestamp <= end_time)
+        .filter_map(|((previous_timestamp, previous), (timestamp, current))| {
+            let seconds = (*timestamp - *previous_timestamp).num_milliseconds() as f64 / 1000_f64;
+            let (previous, current) = (counters(previous)?, counters(current)?);
+            // a counter that went down was reset.
+            (seconds > 0_f64 && current.iter().zip(previous.iter()).all(|(c, p)| c >= p)).then(
+                || {
+                    (
+                        *timestamp,
+                        [0, 1, 2, 3].map(|i| (current[i] - previous[i]) / seconds),
+                    )
+                },
+            )
+        })
+        .collect()
+}
+
+// the connects per second per client group, stacked, so a client that connects for every request
+// stands out.
+pub fn connection_rate_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let mut groups = client_groups(database, final_start_time, final_end_time);
+    groups.retain(|r| r.connects > 0);
+    let other_groups = groups.split_off(TOP_GROUPS.min(groups.len()));
+    let rates = connect_rates(&groups, database, final_start_time, final_end_time);
+    let high_value = rates
+        .iter()
+        .map(|(_, r)| r.iter().sum::<f64>())
+        .fold(0_f64, f64::max)
+        .max(1_f64)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            database.map_or("Connection rate by client".to_string(), |d| {
+                format!("Connection rate by client: {}", d)
+            }),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Connects per second")
+        .y_label_formatter(&|connects| format!("{:6.2}", connects))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Activity);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            rates
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:50} {:>10} {:>10} {:>10}",
+            "", "connects", "max/s", "disconn."
+        ));
+
+    let mut labels: Vec<(String, u64, u64)> = groups
+        .iter()
+        .map(|r| (r.label(), r.connects, r.disconnects))
+        .collect();
+    if !other_groups.is_empty() {
+        labels.push((
+            format!("other ({} clients)", other_groups.len()),
+            other_groups.iter().map(|r| r.connects).sum(),
+            other_groups.iter().map(|r| r.disconnects).sum(),
+        ));
+    }
+    for (group_number, (label, connects, disconnects)) in labels.into_iter().enumerate() {
+        let color = if group_number == groups.len() {
+            GREY_400
+        } else {
+            let (red, green, blue) = Palette99::pick(group_number).rgb();
+            RGBColor(red, green, blue)
+        };
+        let maximum = rates
+            .iter()
+            .map(|(_, r)| r[group_number])
+            .fold(0_f64, f64::max);
+        contextarea
+            .draw_series(AreaSeries::new(
+                rates
+                    .iter()
+                    .map(|(timestamp, r)| (*timestamp, r[group_number..].iter().sum::<f64>())),
+                0_f64,
+                color,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:50} {:>10} {:>10.2} {:>10}",
+                label, connects, maximum, disconnects
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the sessions per second that pg_stat_database counted, with the connects per second that were
+// seen in pg_stat_activity. the difference are sessions that were shorter than the sampling
+// interval of pg_stat_activity.
+pub fn database_sessions_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let session_rates = database_session_rates(database, final_start_time, final_end_time);
+    let seen_rates: Vec<(DateTime<Local>, f64)> =
+        connect_rates(&[], database, final_start_time, final_end_time)
+            .into_iter()
+            .map(|(timestamp, r)| (timestamp, r.iter().sum::<f64>()))
+            .collect();
+    let high_value = session_rates
+        .iter()
+        .flat_map(|(_, r)| r.iter().copied())
+        .chain(seen_rates.iter().map(|(_, r)| *r))
+        .fold(0_f64, f64::max)
+        .max(1_f64)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            database.map_or("Sessions".to_string(), |d| format!("Sessions: {}", d)),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Sessions per second")
+        .y_label_formatter(&|sessions| format!("{:6.2}", sessions))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            session_rates
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:35} {:>10} {:>10}", "", "avg/s", "max/s"));
+
+    // the session statistics of pg_stat_database are not shown for versions before 14.
+    let mut series = Vec::new();
+    if !session_rates.is_empty() {
+        series.push((
+            "sessions (pg_stat_database)",
+            BLUE,
+            session_rates.iter().map(|(t, r)| (*t, r[0])).collect(),
+        ));
+    }
+    series.push(("connects seen in pg_stat_activity", BLACK, seen_rates));
+    if !session_rates.is_empty() {
+        series.push((
+            "sessions_fatal",
+            RED,
+            session_rates.iter().map(|(t, r)| (*t, r[1])).collect(),
+        ));
+        series.push((
+            "sessions_killed",
+            PURPLE,
+            session_rates.iter().map(|(t, r)| (*t, r[2])).collect(),
+        ));
+        series.push((
+            "sessions_abandoned",
+            ORANGE,
+            session_rates.iter().map(|(t, r)| (*t, r[3])).collect(),
+        ));
+    }
+    for (label, color, values) in series {
+        let average = values.iter().map(|(_, r)| *r).sum::<f64>() / values.len().max(1) as f64;
+        let maximum = values.iter().map(|(_, r)| *r).fold(0_f64, f64::max);
+        contextarea
+            .draw_series(LineSeries::new(values, color.stroke_width(2)))
+            .unwrap()
+            .label(format!("{:35} {:>10.2} {:>10.2}", label, average, maximum))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the connects and disconnects per client group, and how many of the sessions that
+// pg_stat_database counted were seen in pg_stat_activity.
+pub fn connections_html(
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let groups = client_groups(database, final_start_time, final_end_time);
+    let seconds = ((final_end_time - final_start_time).num_seconds() as f64).max(1_f64);
+
+    // the sessions counter of the samples in the time range, a counter that went down was reset.
+    let pg_stat_database = pg_stat_database_sum(database);
+    let database_sessions: f64 = pg_stat_database
+        .iter()
+        .zip(pg_stat_database.iter().skip(1))
+        .filter(|(_, (timestamp, _))| {
+            *timestamp >= final_start_time && *timestamp <= final_end_time
+        })
+        .filter_map(|((_, previous), (_, current))| {
+            Some((current.sessions? - previous.sessions?).max(0_f64))
+        })
+        .sum();
+    let seen_connects: u64 = groups.iter().map(|r| r.connects).sum();
+    let mut html_output = if database_sessions >= 1_f64 {
+        format!(
+            "<p>pg_stat_database counted {:.0} new sessions, {} of these ({:.1}%) were seen in pg_stat_activity, which is sampled every {} ms. The other sessions were shorter than the sampling interval.</p>",
+            database_sessions,
+            seen_connects,
+            (seen_connects as f64 / database_sessions * 100_f64).min(100_f64),
+            ARGS.ash_interval,
+        )
+    } else {
+        String::new()
+    };
+    if groups.is_empty() {
+        html_output += "<p>No connects or disconnects found in the selected time range.</p>";
+        return html_output;
+    }
+
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Database</th>
+                <th>Application</th>
+                <th>User</th>
+                <th>Client</th>
+                <th align=right>Connects</th>
+                <th align=right>Disconnects</th>
+                <th align=right>Connects per second</th>
+            </tr>"#;
+    for group in groups.iter() {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{:.3}</td>
+            </tr>"#,
+            group.datname,
+            group.application_name,
+            group.usename,
+            group.client_addr,
+            group.connects,
+            group.disconnects,
+            group.connects as f64 / seconds,
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
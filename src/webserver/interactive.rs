@@ -0,0 +1,449 @@
+use axum::{
+    extract::Query,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, Json,
+    },
+};
+use axum_session::{Session, SessionNullPool};
+use chrono::{Local, TimeDelta, TimeZone};
+use futures::{stream, Stream};
+use plotters::style::full_palette::GREY_400;
+use plotters::style::RGBColor;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::webserver::ash::{group_color, AshTopGroups};
+use crate::webserver::{ash_form, ash_query_from_parameters, parse_duration, session_time_range};
+use crate::{current_cluster, ARGS, CLUSTER, DATA};
+
+// a group of the chart, in the order of the stack: the first group is drawn on top.
+#[derive(Serialize)]
+struct ChartGroup {
+    name: String,
+    color: String,
+    total: usize,
+}
+
+// the times are milliseconds since the epoch, and the counts of a sample are in the order of the
+// groups.
+#[derive(Serialize)]
+pub struct ChartData {
+    caption: String,
+    start_time: i64,
+    end_time: i64,
+    sample_count: usize,
+    other_groups: usize,
+    groups: Vec<ChartGroup>,
+    samples: Vec<(i64, Vec<usize>)>,
+}
+
+// the new samples of the live chart, with the active sessions per group, and the colors of the
+// groups for the groups that are not in the chart yet.
+#[derive(Serialize)]
+struct LiveSamples {
+    samples: Vec<(i64, BTreeMap<String, usize>)>,
+    colors: BTreeMap<String, String>,
+}
+
+fn css_color(RGBColor(red, green, blue): RGBColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+// the active sessions of the ash query of the url parameters in the time range of the session, with
+// the same groups and colors as the ash plot. the window parameter, such as window=15m, is the
+// time range up to now instead, without changing the time range of the session.
+pub async fn interactive_data(
+    session: Session<SessionNullPool>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Json<ChartData> {
+    let (start_time, end_time) = match parameters.get("window").and_then(|r| parse_duration(r)) {
+        Some(window) => (Some(Local::now() - window), None),
+        None => session_time_range(&session),
+    };
+    let database = session.get::<String>("database");
+    let ash_query = ash_query_from_parameters(&parameters, database.as_deref());
+    let aggregate = ash_query.aggregate(start_time, end_time);
+    let AshTopGroups {
+        groups,
+        other_groups,
+        samples,
+    } = aggregate.top_groups();
+
+    let mut chart_groups: Vec<ChartGroup> = groups
+        .iter()
+        .enumerate()
+        .map(|(group_number, group)| ChartGroup {
+            name: group.clone(),
+            color: css_color(group_color(ash_query.group_by, group, group_number)),
+            total: aggregate.totals[group],
+        })
+        .collect();
+    if other_groups > 0 {
+        chart_groups.push(ChartGroup {
+            name: format!("other ({} groups)", other_groups),
+            color: css_color(GREY_400),
+            total: aggregate.totals.values().sum::<usize>()
+                - chart_groups.iter().map(|r| r.total).sum::<usize>(),
+        });
+    }
+    Json(ChartData {
+        caption: format!("Active sessions by {}", ash_query.group_by.name()),
+        start_time: aggregate.start_time.timestamp_millis(),
+        end_time: aggregate.end_time.timestamp_millis(),
+        sample_count: aggregate.sample_count,
+        other_groups,
+        samples: samples
+            .into_iter()
+            .map(|(timestamp, mut per_group)| {
+                per_group.truncate(chart_groups.len());
+                (timestamp.timestamp_millis(), per_group)
+            })
+            .collect(),
+        groups: chart_groups,
+    })
+}
+
+// the samples of the ash query of the url parameters after the after parameter, the time of the
+// last sample of the chart in milliseconds, are sent to the live chart as soon as they are
+// collected, so the chart does not have to load all samples again. a group that was not sent
+// before gets the color after the colors of the groups parameter, the number of groups of the
+// chart, so it does not get the color of a group in the chart.
+pub async fn interactive_live(
+    session: Session<SessionNullPool>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let database = session.get::<String>("database");
+    let ash_query = ash_query_from_parameters(&parameters, database.as_deref());
+    let after = parameters
+        .get("after")
+        .and_then(|r| r.parse::<i64>().ok())
+        .and_then(|r| Local.timestamp_millis_opt(r).single())
+        .unwrap_or_else(Local::now);
+    let chart_groups = parameters
+        .get("groups")
+        .and_then(|r| r.parse::<usize>().ok())
+        .unwrap_or_default();
+    // the stream is polled outside of the cluster scope of the request.
+    let cluster = current_cluster();
+    let added = DATA.pg_stat_activity_added.subscribe();
+    let state = (ash_query, after, HashMap::<String, String>::new(), added);
+    let samples = stream::unfold(
+        state,
+        move |(ash_query, after, mut colors, mut added)| async move {
+            let mut after = after;
+            loop {
+                // the processor sends a notification after every pg_stat_activity sample.
+                added.changed().await.ok()?;
+                let aggregate = CLUSTER.sync_scope(cluster, || {
+                    ash_query.aggregate(Some(after + TimeDelta::milliseconds(1)), None)
+                });
+                if aggregate.sample_count == 0 {
+                    continue;
+                }
+                let mut sample_colors = BTreeMap::new();
+                for group in aggregate.totals.keys() {
+                    let group_number = chart_groups + colors.len();
+                    let color = colors.entry(group.clone()).or_insert_with(|| {
+                        css_color(group_color(ash_query.group_by, group, group_number))
+                    });
+                    sample_colors.insert(group.clone(), color.clone());
+                }
+                let samples: Vec<(i64, BTreeMap<String, usize>)> = aggregate
+                    .samples
+                    .into_iter()
+                    .map(|(timestamp, counts)| (timestamp.timestamp_millis(), counts))
+                    .collect();
+                after = Local
+                    .timestamp_millis_opt(samples.last().map_or(0, |(timestamp, _)| *timestamp))
+                    .single()
+                    .unwrap_or(after);
+                let event = Event::default().json_data(LiveSamples {
+                    samples,
+                    colors: sample_colors,
+                });
+                return Some((event, (ash_query, after, colors, added)));
+            }
+        },
+    );
+    Sse::new(samples).keep_alive(KeepAlive::default())
+}
+
+// the ash chart drawn in the browser: dragging over the chart sets the time range of the session
+// and reloads the chart, hovering shows the active sessions per group of the nearest sample, and
+// clicking a group in the legend hides or shows it.
+pub async fn interactive_html(
+    session: Session<SessionNullPool>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Html<String> {
+    let database = session.get::<String>("database");
+    let ash_query = ash_query_from_parameters(&parameters, database.as_deref());
+    format!(
+        r#"{form}
+    <p>
+      <button id="reset">reset time range</button>
+      <label><input type="checkbox" id="live"> live</label>
+      drag over the chart to zoom in, click a group in the legend to hide or show it, live adds the
+      samples as they are collected.
+    </p>
+    <div style="position: relative;">
+      <canvas id="chart" width="{width}" height="{height}"></canvas>
+      <div id="tooltip" style="position: absolute; display: none; pointer-events: none; background: rgba(255, 255, 255, 0.9); border: 1px solid black; padding: 4px; font: 13px monospace; white-space: nowrap;"></div>
+    </div>
+    <div id="legend" style="font: 13px monospace;"></div>
+    <script>
+    // the time range of the url is set when the page is loaded, and is not set again when the chart
+    // is reloaded after zooming in.
+    const dataParameters = new URLSearchParams(window.location.search);
+    ["start", "end", "last", "live"].forEach((name) => dataParameters.delete(name));
+    const canvas = document.getElementById("chart");
+    const context = canvas.getContext("2d");
+    const tooltip = document.getElementById("tooltip");
+    const legend = document.getElementById("legend");
+    const liveCheckbox = document.getElementById("live");
+    const margin = {{ left: 70, right: 20, top: 40, bottom: 40 }};
+    const plotWidth = canvas.width - margin.left - margin.right;
+    const plotHeight = canvas.height - margin.top - margin.bottom;
+    const hidden = new Set();
+    let chart = null;
+    let dragStart = null;
+    let pointer = null;
+    let live = null;
+
+    function pad(number, length) {{
+      return String(number).padStart(length || 2, "0");
+    }}
+    function formatTime(time, milliseconds) {{
+      const date = new Date(time);
+      const text = date.getFullYear() + "-" + pad(date.getMonth() + 1) + "-" + pad(date.getDate())
+        + "T" + pad(date.getHours()) + ":" + pad(date.getMinutes()) + ":" + pad(date.getSeconds());
+      return milliseconds ? text + "." + pad(date.getMilliseconds(), 3) : text;
+    }}
+    function escapeHtml(text) {{
+      return text.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
+    }}
+    // the active sessions of the visible groups from group onwards, which is the top of the area
+    // of group in the stack.
+    function stackedCount(counts, group) {{
+      let sum = 0;
+      for (let number = group; number < counts.length; number++) {{
+        if (!hidden.has(number)) sum += counts[number];
+      }}
+      return sum;
+    }}
+    function maxActive() {{
+      return Math.max(1, ...chart.samples.map(([, counts]) => stackedCount(counts, 0)));
+    }}
+    function xOf(time) {{
+      return margin.left + (time - chart.start_time) / Math.max(1, chart.end_time - chart.start_time) * plotWidth;
+    }}
+    function timeOf(x) {{
+      const position = Math.min(Math.max(x - margin.left, 0), plotWidth) / plotWidth;
+      return chart.start_time + position * (chart.end_time - chart.start_time);
+    }}
+    function nearestSample(time) {{
+      let low = 0;
+      let high = chart.samples.length - 1;
+      while (low < high) {{
+        const middle = Math.floor((low + high) / 2);
+        if (chart.samples[middle][0] < time) low = middle + 1; else high = middle;
+      }}
+      if (low > 0 && time - chart.samples[low - 1][0] < chart.samples[low][0] - time) low -= 1;
+      return chart.samples[low];
+    }}
+
+    function draw() {{
+      context.clearRect(0, 0, canvas.width, canvas.height);
+      context.fillStyle = "white";
+      context.fillRect(0, 0, canvas.width, canvas.height);
+      context.fillStyle = "black";
+      context.font = "20px monospace";
+      context.textAlign = "center";
+      context.fillText(chart.caption, canvas.width / 2, 25);
+      if (chart.samples.length === 0) return;
+      const maxY = maxActive();
+      const yOf = (count) => margin.top + plotHeight - count / maxY * plotHeight;
+
+      // the groups are drawn from the top of the stack down, every area starts at zero.
+      chart.groups.forEach((group, number) => {{
+        if (hidden.has(number)) return;
+        context.beginPath();
+        context.moveTo(xOf(chart.samples[0][0]), yOf(0));
+        for (const [time, counts] of chart.samples) context.lineTo(xOf(time), yOf(stackedCount(counts, number)));
+        context.lineTo(xOf(chart.samples[chart.samples.length - 1][0]), yOf(0));
+        context.closePath();
+        context.fillStyle = group.color;
+        context.fill();
+      }});
+
+      context.strokeStyle = "black";
+      context.lineWidth = 1;
+      context.strokeRect(margin.left, margin.top, plotWidth, plotHeight);
+      context.font = "13px monospace";
+      context.fillStyle = "black";
+      context.textAlign = "right";
+      const step = Math.max(1, Math.ceil(maxY / 8));
+      for (let count = 0; count <= maxY; count += step) {{
+        context.fillText(count, margin.left - 6, yOf(count) + 4);
+      }}
+      context.textAlign = "center";
+      for (let label = 0; label <= 5; label++) {{
+        const time = chart.start_time + label / 5 * (chart.end_time - chart.start_time);
+        context.fillText(formatTime(time, false), xOf(time), margin.top + plotHeight + 20);
+      }}
+
+      if (dragStart !== null && pointer !== null) {{
+        context.fillStyle = "rgba(0, 0, 255, 0.15)";
+        context.fillRect(Math.min(dragStart, pointer), margin.top, Math.abs(pointer - dragStart), plotHeight);
+      }} else if (pointer !== null) {{
+        const x = xOf(nearestSample(timeOf(pointer))[0]);
+        context.strokeStyle = "black";
+        context.beginPath();
+        context.moveTo(x, margin.top);
+        context.lineTo(x, margin.top + plotHeight);
+        context.stroke();
+      }}
+    }}
+
+    function drawTooltip(event) {{
+      if (dragStart !== null || pointer === null || chart.samples.length === 0) {{
+        tooltip.style.display = "none";
+        return;
+      }}
+      const [time, counts] = nearestSample(timeOf(pointer));
+      let rows = "";
+      chart.groups.forEach((group, number) => {{
+        if (hidden.has(number) || counts[number] === 0) return;
+        rows += `<tr><td><span style="color: ${{group.color}};">&#9632;</span> ${{escapeHtml(group.name)}}</td><td align=right>${{counts[number]}}</td></tr>`;
+      }});
+      tooltip.innerHTML = `<b>${{formatTime(time, true)}}</b><br>active sessions: ${{stackedCount(counts, 0)}}<table>${{rows}}</table>`;
+      tooltip.style.display = "block";
+      const left = event.offsetX + 15;
+      tooltip.style.left = (left + tooltip.offsetWidth > canvas.width ? event.offsetX - tooltip.offsetWidth - 15 : left) + "px";
+      tooltip.style.top = (event.offsetY + 15) + "px";
+    }}
+
+    function drawLegend() {{
+      const total = chart.groups.reduce((sum, group) => sum + group.total, 0);
+      legend.innerHTML = "";
+      chart.groups.forEach((group, number) => {{
+        const item = document.createElement("div");
+        item.style.cursor = "pointer";
+        item.style.opacity = hidden.has(number) ? 0.3 : 1;
+        item.innerHTML = `<span style="color: ${{group.color}};">&#9632;</span> ${{escapeHtml(group.name)}}: ${{group.total}} (${{(group.total / Math.max(1, total) * 100).toFixed(2)}}%, ${{(group.total / Math.max(1, chart.sample_count)).toFixed(2)}} average active sessions)`;
+        item.addEventListener("click", () => {{
+          if (hidden.has(number)) hidden.delete(number); else hidden.add(number);
+          drawLegend();
+          draw();
+        }});
+        legend.appendChild(item);
+      }});
+      if (chart.groups.length === 0) legend.textContent = "No active sessions found in the selected time range.";
+    }}
+
+    function load() {{
+      stopLive();
+      fetch("/interactive_data?" + dataParameters).then((response) => response.json()).then((data) => {{
+        chart = data;
+        hidden.clear();
+        drawLegend();
+        draw();
+        if (liveCheckbox.checked) startLive();
+      }});
+    }}
+
+    // the live chart keeps the width of the time range, and moves along with the new samples. a
+    // new group gets its own area, unless the chart adds the groups after the largest groups
+    // together, then it is one of the other groups.
+    function addSamples(liveSamples) {{
+      const width = chart.end_time - chart.start_time;
+      for (const [time, counts] of liveSamples.samples) {{
+        const sample = new Array(chart.groups.length).fill(0);
+        for (const [name, count] of Object.entries(counts)) {{
+          let number = chart.groups.findIndex((group) => group.name === name);
+          if (number === -1 && chart.other_groups > 0) {{
+            number = chart.groups.length - 1;
+          }} else if (number === -1) {{
+            chart.groups.push({{ name: name, color: liveSamples.colors[name], total: 0 }});
+            chart.samples.forEach(([, previous]) => previous.push(0));
+            sample.push(0);
+            number = chart.groups.length - 1;
+          }}
+          sample[number] += count;
+        }}
+        chart.samples.push([time, sample]);
+        chart.end_time = Math.max(chart.end_time, time);
+      }}
+      chart.start_time = chart.end_time - width;
+      while (chart.samples.length > 0 && chart.samples[0][0] < chart.start_time) chart.samples.shift();
+      // the legend shows the samples in the chart.
+      chart.groups.forEach((group, number) => {{
+        group.total = chart.samples.reduce((total, [, sample]) => total + sample[number], 0);
+      }});
+      chart.sample_count = chart.samples.length;
+      drawLegend();
+      draw();
+    }}
+    function startLive() {{
+      stopLive();
+      const liveParameters = new URLSearchParams(dataParameters);
+      liveParameters.set("groups", chart.groups.length);
+      liveParameters.set("after", chart.samples.length > 0 ? chart.samples[chart.samples.length - 1][0] : chart.end_time);
+      live = new EventSource("/interactive_live?" + liveParameters);
+      live.onmessage = (message) => addSamples(JSON.parse(message.data));
+    }}
+    function stopLive() {{
+      if (live !== null) live.close();
+      live = null;
+    }}
+    // the time range is the time range of the session, so the other pages show the same time range.
+    function setTime(startTime, endTime) {{
+      liveCheckbox.checked = false;
+      dataParameters.delete("window");
+      fetch("/set_time", {{
+        method: "POST",
+        headers: {{ "Content-Type": "application/x-www-form-urlencoded" }},
+        body: new URLSearchParams({{ start_time: startTime, end_time: endTime }}),
+      }}).then(load);
+    }}
+
+    canvas.addEventListener("mousedown", (event) => {{
+      if (chart === null) return;
+      dragStart = Math.min(Math.max(event.offsetX, margin.left), margin.left + plotWidth);
+      pointer = dragStart;
+    }});
+    canvas.addEventListener("mousemove", (event) => {{
+      if (chart === null) return;
+      pointer = Math.min(Math.max(event.offsetX, margin.left), margin.left + plotWidth);
+      draw();
+      drawTooltip(event);
+    }});
+    window.addEventListener("mouseup", () => {{
+      if (dragStart === null) return;
+      const [left, right] = [Math.min(dragStart, pointer), Math.max(dragStart, pointer)];
+      dragStart = null;
+      if (right - left > 5) {{
+        setTime(new Date(timeOf(left)).toISOString(), new Date(timeOf(right)).toISOString());
+      }} else {{
+        draw();
+      }}
+    }});
+    canvas.addEventListener("mouseleave", () => {{
+      if (dragStart !== null) return;
+      pointer = null;
+      tooltip.style.display = "none";
+      if (chart !== null) draw();
+    }});
+    document.getElementById("reset").addEventListener("click", () => setTime("-", "-"));
+    liveCheckbox.addEventListener("change", () => {{
+      if (!liveCheckbox.checked) stopLive(); else if (chart !== null) startLive();
+    }});
+    liveCheckbox.checked = new URLSearchParams(window.location.search).get("live") === "Y";
+    load();
+    </script>"#,
+        form = ash_form("/interactive", &ash_query, &parameters),
+        width = ARGS.graph_width,
+        height = ARGS.graph_height / 2,
+    )
+    .into()
+}
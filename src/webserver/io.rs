@@ -1,3 +1,7 @@
+use crate::processor::Schedule;
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::pg_stat_database_sum;
+use crate::webserver::settings::draw_settings_changes;
 use crate::DATA;
 use crate::{
     CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
@@ -8,15 +12,14 @@ use chrono::{DateTime, Local};
 use full_palette::{GREY_700, LIGHTBLUE};
 use futures::executor;
 use human_bytes::human_bytes;
-use plotters::backend::RGBPixel;
 use plotters::element::Circle;
 use plotters::prelude::full_palette::PURPLE;
 use plotters::prelude::*;
 use plotters::{chart::SeriesLabelPosition::UpperLeft, style::full_palette::GREEN_800};
 use plotters::{coord::Shift, style::full_palette::RED_300};
 
-pub fn iops(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn iops<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
@@ -82,20 +85,14 @@ pub fn iops(
                 && *timestamp_d <= final_end_time
         })
         .map(|(((_, w), (_, b)), (_, d))| {
-            w.wal_buffers_full_ps
-                + w.wal_write_ps
-                + b.buffers_checkpoint_ps
-                + b.buffers_clean_ps
-                + b.buffers_backend_ps
-                + d.blks_read_ps
+            w.writes_ps().unwrap_or_default() + b.buffers_written_ps() + d.blks_read_ps
         })
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let high_value_wal_sync = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_ps)
+        .filter_map(|(_, w)| w.wal_sync_ps)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let high_value = high_value_io.max(high_value_wal_sync) * 1.1_f64;
@@ -117,6 +114,7 @@ pub fn iops(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // checkpoints timed
     contextarea
@@ -173,7 +171,7 @@ pub fn iops(
             wal_events
                 .iter()
                 .take(1)
-                .map(|(timestamp, w)| (*timestamp, w.wal_records_ps)),
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
             ShapeStyle {
                 color: TRANSPARENT,
                 filled: false,
@@ -186,77 +184,91 @@ pub fn iops(
             "", "min", "max", "last"
         ));
     // wal buffers full
-    let min_wal_buffers_full = wal_events
+    if wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_buffers_full_ps > 0_f64)
-        .map(|(_, w)| w.wal_buffers_full_ps)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_wal_buffers_full = wal_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| w.wal_buffers_full_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| w.wal_buffers_full_ps > 0_f64)
-                .map(|(timestamp, w)| {
-                    Circle::new((*timestamp, w.wal_buffers_full_ps), 6, PURPLE.filled())
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.2} {:10.2} {:10.2}",
-            "wal_buffers_full",
-            min_wal_buffers_full,
-            max_wal_buffers_full,
-            wal_events
-                .back()
-                .map_or(0_f64, |(_, r)| r.wal_buffers_full_ps)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 6, PURPLE.filled()));
+        .any(|(_, w)| w.wal_buffers_full_ps.is_some())
+    {
+        let min_wal_buffers_full = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.wal_buffers_full_ps)
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_wal_buffers_full = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.wal_buffers_full_ps)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| Some((timestamp, w.wal_buffers_full_ps?)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((*timestamp, r), 6, PURPLE.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.2} {:10.2} {:10.2}",
+                "wal_buffers_full",
+                min_wal_buffers_full,
+                max_wal_buffers_full,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.wal_buffers_full_ps)
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 6, PURPLE.filled()));
+    }
     // wal write
-    let min_wal_write = wal_events
+    if wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_write_ps > 0_f64)
-        .map(|(_, w)| w.wal_write_ps)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_wal_write = wal_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| w.wal_write_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| w.wal_write_ps > 0_f64)
-                .map(|(timestamp, w)| {
-                    Circle::new((*timestamp, w.wal_write_ps), 5, GREY_700.filled())
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.2} {:10.2} {:10.2}",
-            "wal_write",
-            min_wal_write,
-            max_wal_write,
-            wal_events.back().map_or(0_f64, |(_, r)| r.wal_write_ps)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 5, GREY_700.filled()));
+        .any(|(_, w)| w.wal_write_ps.is_some())
+    {
+        let min_wal_write = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.wal_write_ps)
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_wal_write = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.wal_write_ps)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| Some((timestamp, w.wal_write_ps?)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((*timestamp, r), 5, GREY_700.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.2} {:10.2} {:10.2}",
+                "wal_write",
+                min_wal_write,
+                max_wal_write,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.wal_write_ps)
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 5, GREY_700.filled()));
+    }
     /*
         // wal sync
         let min_sync = wal_events
@@ -323,42 +335,48 @@ pub fn iops(
         ))
         .legend(move |(x, y)| Circle::new((x, y), 4, GREEN.filled()));
     // bgwriter buffers written backend
-    let min_buf_backend = bgwriter_events
+    if bgwriter_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, b)| b.buffers_backend_ps > 0_f64)
-        .map(|(_, b)| b.buffers_backend_ps)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_buf_backend = bgwriter_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, b)| b.buffers_backend_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            bgwriter_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, b)| b.buffers_backend_ps > 0_f64)
-                .map(|(timestamp, b)| {
-                    Circle::new((*timestamp, b.buffers_backend_ps), 3, RED.filled())
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.2} {:10.2} {:10.2}",
-            "buffers_backend",
-            min_buf_backend,
-            max_buf_backend,
-            bgwriter_events
-                .back()
-                .map_or(0_f64, |(_, b)| b.buffers_backend_ps)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 3, RED.filled()));
+        .any(|(_, b)| b.buffers_backend_ps.is_some())
+    {
+        let min_buf_backend = bgwriter_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, b)| b.buffers_backend_ps)
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_buf_backend = bgwriter_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, b)| b.buffers_backend_ps)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                bgwriter_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, b)| Some((timestamp, b.buffers_backend_ps?)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((*timestamp, r), 3, RED.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.2} {:10.2} {:10.2}",
+                "buffers_backend",
+                min_buf_backend,
+                max_buf_backend,
+                bgwriter_events
+                    .back()
+                    .and_then(|(_, b)| b.buffers_backend_ps)
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 3, RED.filled()));
+    }
     // bgwriter buffers clean
     let min_buf_clean = bgwriter_events
         .iter()
@@ -447,12 +465,7 @@ pub fn iops(
                 && *timestamp_d <= final_end_time
         })
         .map(|(((_, w), (_, b)), (_, d))| {
-            w.wal_buffers_full_ps
-                + w.wal_write_ps
-                + b.buffers_checkpoint_ps
-                + b.buffers_clean_ps
-                + b.buffers_backend_ps
-                + d.blks_read_ps
+            w.writes_ps().unwrap_or_default() + b.buffers_written_ps() + d.blks_read_ps
         })
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
@@ -469,12 +482,7 @@ pub fn iops(
                 && *timestamp_d <= final_end_time
         })
         .map(|(((_, w), (_, b)), (_, d))| {
-            w.wal_buffers_full_ps
-                + w.wal_write_ps
-                + b.buffers_checkpoint_ps
-                + b.buffers_clean_ps
-                + b.buffers_backend_ps
-                + d.blks_read_ps
+            w.writes_ps().unwrap_or_default() + b.buffers_written_ps() + d.blks_read_ps
         })
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
@@ -495,12 +503,7 @@ pub fn iops(
                 .map(|(((timestamp, w), (_, b)), (_, d))| {
                     (
                         *timestamp,
-                        w.wal_buffers_full_ps
-                            + w.wal_write_ps
-                            + b.buffers_checkpoint_ps
-                            + b.buffers_clean_ps
-                            + b.buffers_backend_ps
-                            + d.blks_read_ps,
+                        w.writes_ps().unwrap_or_default() + b.buffers_written_ps() + d.blks_read_ps,
                     )
                 }),
             BLACK,
@@ -513,18 +516,17 @@ pub fn iops(
             max_tot_io,
             bgwriter_events
                 .back()
-                .map_or(0_f64, |(_, b)| b.buffers_checkpoint_ps
-                    + b.buffers_clean_ps
-                    + b.buffers_backend_ps)
+                .map_or(0_f64, |(_, b)| b.buffers_written_ps())
                 + wal_events
                     .back()
-                    .map_or(0_f64, |(_, w)| w.wal_buffers_full_ps + w.wal_write_ps)
+                    .map_or(0_f64, |(_, w)| w.writes_ps().unwrap_or_default())
                 + database_events
                     .back()
                     .map_or(0_f64, |(_, d)| d.blks_read_ps)
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLACK.filled()));
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -534,14 +536,15 @@ pub fn iops(
         .draw()
         .unwrap();
 }
-pub fn io_times(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn io_times<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
     let wal_events = executor::block_on(DATA.pg_stat_wal_sum.read());
-    let database_events = executor::block_on(DATA.pg_stat_database_sum.read());
+    let database_events = pg_stat_database_sum(database);
     let bgwriter_events = executor::block_on(DATA.pg_stat_bgwriter_sum.read());
     let wal_start_time = wal_events
         .iter()
@@ -553,16 +556,17 @@ pub fn io_times(
         .map(|(timestamp, _)| timestamp)
         .max()
         .unwrap();
+    // the selected database can have no statistics yet.
     let database_start_time = database_events
         .iter()
         .map(|(timestamp, _)| timestamp)
         .min()
-        .unwrap();
+        .unwrap_or(wal_start_time);
     let database_end_time = database_events
         .iter()
         .map(|(timestamp, _)| timestamp)
         .max()
-        .unwrap();
+        .unwrap_or(wal_end_time);
     let bgwriter_start_time = bgwriter_events
         .iter()
         .map(|(timestamp, _)| timestamp)
@@ -591,15 +595,13 @@ pub fn io_times(
     let wal_high_value_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_buffers_full_ps + w.wal_write_ps > 0_f64)
-        .map(|(_, w)| w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps))
+        .filter_map(|(_, w)| w.write_latency())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let wal_high_value_sync = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_time_ps / w.wal_sync_ps)
+        .filter_map(|(_, w)| w.sync_latency())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let database_high_value_read = database_events
@@ -618,13 +620,8 @@ pub fn io_times(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .filter(|((_, _), (_, b))| {
-            b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps > 0_f64
-        })
-        .map(|((_, d), (_, b))| {
-            d.blk_write_time_ps
-                / (b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps)
-        })
+        .filter(|((_, _), (_, b))| b.buffers_written_ps() > 0_f64)
+        .map(|((_, d), (_, b))| d.blk_write_time_ps / b.buffers_written_ps())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let high_value = wal_high_value_write
@@ -638,7 +635,10 @@ pub fn io_times(
         .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
         .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
         .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
-        .caption("IO latency", (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .caption(
+            database.map_or("IO latency".to_string(), |d| format!("IO latency: {}", d)),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
         .build_cartesian_2d(final_start_time..final_end_time, low_value_f64..high_value)
         .unwrap();
     contextarea
@@ -650,6 +650,7 @@ pub fn io_times(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // checkpoints timed
     contextarea
@@ -703,7 +704,7 @@ pub fn io_times(
             wal_events
                 .iter()
                 .take(1)
-                .map(|(timestamp, w)| (*timestamp, w.wal_records_ps)),
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
             ShapeStyle {
                 color: TRANSPARENT,
                 filled: false,
@@ -715,103 +716,93 @@ pub fn io_times(
             "{:25} {:>10} {:>10} {:>10}",
             "", "min", "max", "last"
         ));
-    // wal write
-    let min_write = wal_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| {
-            w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-        })
-        .map(|(_, w)| w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps))
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_write = wal_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| {
-            w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-        })
-        .map(|(_, w)| w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps))
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| {
-                    w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-                })
-                .map(|(timestamp, w)| {
-                    Circle::new(
-                        (
-                            *timestamp,
-                            w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps),
-                        ),
-                        4,
-                        GREEN.filled(),
-                    )
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.3} {:10.3} {:10.3} ms",
-            "Wal write",
-            min_write,
-            max_write,
-            wal_events.back().map_or(0_f64, |(_, r)| {
-                if r.wal_buffers_full_ps + r.wal_write_ps == 0_f64 {
-                    0_f64
-                } else {
-                    r.wal_write_time_ps / (r.wal_buffers_full_ps + r.wal_write_ps)
-                }
-            },)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 4, GREEN.filled()));
-    // wal sync
-    let min_sync = wal_events
+    // the write and sync statistics are not in pg_stat_wal before version 14 and since version 18.
+    if wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_time_ps / w.wal_sync_ps)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_sync = wal_events
+        .any(|(_, w)| w.wal_write_ps.is_some())
+    {
+        let min_write = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.write_latency())
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_write = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.write_latency())
+            .filter(|r| *r > 0_f64)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| w.write_latency().map(|r| (*timestamp, r)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((timestamp, r), 4, GREEN.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.3} {:10.3} {:10.3} ms",
+                "Wal write",
+                min_write,
+                max_write,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.write_latency())
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 4, GREEN.filled()));
+    }
+    if wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_time_ps / w.wal_sync_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-                .map(|(timestamp, w)| {
-                    Circle::new((*timestamp, w.wal_sync_time_ps / w.wal_sync_ps), 3, BLUE)
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.3} {:10.3} {:10.3} ms",
-            "Wal sync",
-            min_sync,
-            max_sync,
-            wal_events.back().map_or(0_f64, |(_, r)| {
-                if r.wal_sync_ps == 0_f64 {
-                    0_f64
-                } else {
-                    r.wal_sync_time_ps / r.wal_sync_ps
-                }
-            },)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 3, BLUE.filled()));
+        .any(|(_, w)| w.wal_sync_ps.is_some())
+    {
+        let min_sync = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.sync_latency())
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_sync = wal_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, w)| w.sync_latency())
+            .filter(|r| *r > 0_f64)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| w.sync_latency().map(|r| (*timestamp, r)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((timestamp, r), 3, BLUE)),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.3} {:10.3} {:10.3} ms",
+                "Wal sync",
+                min_sync,
+                max_sync,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.sync_latency())
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 3, BLUE.filled()));
+    }
     // blocks read
     let min_database_read = database_events
         .iter()
@@ -868,14 +859,8 @@ pub fn io_times(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .filter(|((_, d), (_, b))| {
-            b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps > 0_f64
-                && d.blk_write_time_ps > 0_f64
-        })
-        .map(|((_, d), (_, b))| {
-            d.blk_write_time_ps
-                / (b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps)
-        })
+        .filter(|((_, d), (_, b))| b.buffers_written_ps() > 0_f64 && d.blk_write_time_ps > 0_f64)
+        .map(|((_, d), (_, b))| d.blk_write_time_ps / b.buffers_written_ps())
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let max_database_write = database_events
@@ -887,14 +872,8 @@ pub fn io_times(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .filter(|((_, d), (_, b))| {
-            b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps > 0_f64
-                && d.blk_write_time_ps > 0_f64
-        })
-        .map(|((_, d), (_, b))| {
-            d.blk_write_time_ps
-                / (b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps)
-        })
+        .filter(|((_, d), (_, b))| b.buffers_written_ps() > 0_f64 && d.blk_write_time_ps > 0_f64)
+        .map(|((_, d), (_, b))| d.blk_write_time_ps / b.buffers_written_ps())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     contextarea
@@ -909,18 +888,11 @@ pub fn io_times(
                         && *timestamp_b <= final_end_time
                 })
                 .filter(|((_, d), (_, b))| {
-                    b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps > 0_f64
-                        && d.blk_write_time_ps > 0_f64
+                    b.buffers_written_ps() > 0_f64 && d.blk_write_time_ps > 0_f64
                 })
                 .map(|((timestamp, d), (_, b))| {
                     Circle::new(
-                        (
-                            *timestamp,
-                            d.blk_write_time_ps
-                                / (b.buffers_checkpoint_ps
-                                    + b.buffers_clean_ps
-                                    + b.buffers_backend_ps),
-                        ),
+                        (*timestamp, d.blk_write_time_ps / b.buffers_written_ps()),
                         3,
                         RED.filled(),
                     )
@@ -935,19 +907,18 @@ pub fn io_times(
             database_events
                 .iter()
                 .zip(bgwriter_events.iter())
-                .last()
+                .next_back()
                 .map_or(0_f64, |((_, d), (_, b))| {
-                    if b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps == 0_f64
-                    {
+                    if b.buffers_written_ps() == 0_f64 {
                         0_f64
                     } else {
-                        d.blk_write_time_ps
-                            / (b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps)
+                        d.blk_write_time_ps / b.buffers_written_ps()
                     }
                 },)
         ))
         .legend(move |(x, y)| Circle::new((x, y), 3, RED.filled()));
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -957,8 +928,8 @@ pub fn io_times(
         .draw()
         .unwrap();
 }
-pub fn io_bandwidth(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn io_bandwidth<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
@@ -1007,10 +978,7 @@ pub fn io_bandwidth(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .map(|((_, d), (_, b))| {
-            (d.blks_read_ps + b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps)
-                * 8192_f64
-        })
+        .map(|((_, d), (_, b))| (d.blks_read_ps + b.buffers_written_ps()) * 8192_f64)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default()
         * 1.1_f64;
@@ -1036,6 +1004,7 @@ pub fn io_bandwidth(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // This is a dummy plot for the sole intention to write a header in the legend.
     contextarea
@@ -1113,9 +1082,7 @@ pub fn io_bandwidth(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .map(|((_, d), (_, b))| {
-            d.blks_read_ps + b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps
-        })
+        .map(|((_, d), (_, b))| d.blks_read_ps + b.buffers_written_ps())
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let max_total = database_events
@@ -1127,9 +1094,7 @@ pub fn io_bandwidth(
                 && *timestamp_b >= final_start_time
                 && *timestamp_b <= final_end_time
         })
-        .map(|((_, d), (_, b))| {
-            d.blks_read_ps + b.buffers_checkpoint_ps + b.buffers_clean_ps + b.buffers_backend_ps
-        })
+        .map(|((_, d), (_, b))| d.blks_read_ps + b.buffers_written_ps())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap();
     contextarea
@@ -1146,11 +1111,7 @@ pub fn io_bandwidth(
                 .map(|((timestamp, d), (_, b))| {
                     (
                         *timestamp,
-                        (d.blks_read_ps
-                            + b.buffers_checkpoint_ps
-                            + b.buffers_clean_ps
-                            + b.buffers_backend_ps)
-                            * 8192_f64,
+                        (d.blks_read_ps + b.buffers_written_ps()) * 8192_f64,
                     )
                 }),
             BLACK,
@@ -1166,11 +1127,10 @@ pub fn io_bandwidth(
                     .iter()
                     .last()
                     .map_or(0_f64, |(_, d)| d.blks_read_ps * 8192_f64)
-                    + bgwriter_events.iter().last().map_or(0_f64, |(_, b)| (b
-                        .buffers_checkpoint_ps
-                        + b.buffers_clean_ps
-                        + b.buffers_backend_ps)
-                        * 8192_f64)
+                    + bgwriter_events
+                        .iter()
+                        .last()
+                        .map_or(0_f64, |(_, b)| b.buffers_written_ps() * 8192_f64)
             )
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLACK.filled()));
@@ -1304,50 +1264,51 @@ pub fn io_bandwidth(
         ))
         .legend(move |(x, y)| Circle::new((x, y), 3, PURPLE.filled()));
     // blocks written backend
-    let min_read = bgwriter_events
-        .iter()
-        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, b)| b.buffers_backend_ps > 0_f64)
-        .map(|(_, b)| b.buffers_backend_ps)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    let max_read = bgwriter_events
+    if bgwriter_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, b)| b.buffers_backend_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            bgwriter_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, b)| b.buffers_backend_ps > 0_f64)
-                .map(|(timestamp, b)| {
-                    Circle::new(
-                        (*timestamp, b.buffers_backend_ps * 8192_f64),
-                        2,
-                        RED.filled(),
-                    )
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:>10} {:>10} {:>10}",
-            "Backend write",
-            human_bytes(min_read * 8192_f64),
-            human_bytes(max_read * 8192_f64),
-            bgwriter_events
-                .iter()
-                .last()
-                .map_or("".to_string(), |(_, b)| human_bytes(
-                    b.buffers_backend_ps * 8192_f64
-                ))
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 2, RED.filled()));
+        .any(|(_, b)| b.buffers_backend_ps.is_some())
+    {
+        let min_read = bgwriter_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, b)| b.buffers_backend_ps)
+            .filter(|r| *r > 0_f64)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_read = bgwriter_events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .filter_map(|(_, b)| b.buffers_backend_ps)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        contextarea
+            .draw_series(
+                bgwriter_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, b)| Some((timestamp, b.buffers_backend_ps?)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((*timestamp, r * 8192_f64), 2, RED.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10} {:>10} {:>10}",
+                "Backend write",
+                human_bytes(min_read * 8192_f64),
+                human_bytes(max_read * 8192_f64),
+                bgwriter_events
+                    .iter()
+                    .last()
+                    .and_then(|(_, b)| b.buffers_backend_ps)
+                    .map_or("".to_string(), |r| human_bytes(r * 8192_f64))
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 2, RED.filled()));
+    }
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -0,0 +1,291 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use human_bytes::human_bytes;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{GREEN_800, RED_300};
+use std::collections::BTreeMap;
+use std::ops::Bound::Included;
+
+use crate::processor::{PgStatIoSum, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+pub fn io_context_bandwidth<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    io_context_plot(
+        multi_backend,
+        backend_number,
+        "IO bandwidth by backend type, object and context",
+        "Bytes per second",
+        |r| r.read_bytes_ps + r.write_bytes_ps + r.extend_bytes_ps,
+        true,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn io_context_iops<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    io_context_plot(
+        multi_backend,
+        backend_number,
+        "IOPS by backend type, object and context",
+        "IOPS",
+        |r| r.reads_ps + r.writes_ps + r.writebacks_ps + r.extends_ps + r.fsyncs_ps,
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn io_context_time<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    io_context_plot(
+        multi_backend,
+        backend_number,
+        "IO time by backend type, object and context (track_io_timing)",
+        "Milliseconds per second",
+        |r| r.read_time_ps + r.write_time_ps + r.fsync_time_ps,
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+// the pg_stat_io rows are stacked per backend_type, object and context, so that IO of for
+// example autovacuum (context vacuum) or a sequential scan (context bulkread) can be seen apart
+// from the normal IO of client backends.
+#[allow(clippy::too_many_arguments)]
+fn io_context_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    caption: &str,
+    y_description: &str,
+    value: fn(&PgStatIoSum) -> f64,
+    show_bytes: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_io_sum = executor::block_on(DATA.pg_stat_io_sum.read());
+    let bgwriter_events = executor::block_on(DATA.pg_stat_bgwriter_sum.read());
+    let checkpointer_events = executor::block_on(DATA.pg_stat_checkpointer_sum.read());
+
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_stat_io_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_stat_io_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+
+    let mut context_totals: BTreeMap<String, f64> = BTreeMap::new();
+    let mut timestamp_and_contexts: Vec<(DateTime<Local>, BTreeMap<String, f64>)> = Vec::new();
+    for (timestamp, per_sample_vector) in pg_stat_io_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        let mut current_contexts: BTreeMap<String, f64> = BTreeMap::new();
+        for row in per_sample_vector.iter().filter(|r| value(r) > 0_f64) {
+            let context = format!("{}/{}/{}", row.backend_type, row.object, row.context);
+            context_totals
+                .entry(context.clone())
+                .and_modify(|r| *r += value(row))
+                .or_insert(value(row));
+            current_contexts
+                .entry(context)
+                .and_modify(|r| *r += value(row))
+                .or_insert(value(row));
+        }
+        timestamp_and_contexts.push((*timestamp, current_contexts));
+    }
+    // add in the missing contexts that are zero
+    for (_, contexts) in timestamp_and_contexts.iter_mut() {
+        for context in context_totals.keys() {
+            contexts.entry(context.clone()).or_insert(0_f64);
+        }
+    }
+    let low_value = 0_f64;
+    let high_value = timestamp_and_contexts
+        .iter()
+        .map(|(_, contexts)| contexts.values().sum::<f64>())
+        .max_by(|a, b| a.partial_cmp(b).unwrap())
+        .unwrap_or_default()
+        .max(1_f64)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(caption, (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(final_start_time..final_end_time, low_value..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc(y_description)
+        .y_label_formatter(&|value| {
+            if show_bytes {
+                human_bytes(*value)
+            } else {
+                format!("{:6.1}", value)
+            }
+        })
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // checkpoints
+    contextarea
+        .draw_series(
+            bgwriter_events
+                .iter()
+                .filter(|(timestamp, _)| {
+                    *timestamp >= final_start_time && *timestamp <= final_end_time
+                })
+                .filter(|(_, b)| b.checkpoints_timed + b.checkpoints_req > 0_f64)
+                .map(|(timestamp, _)| TriangleMarker::new((*timestamp, high_value), 5, GREEN_800)),
+        )
+        .unwrap()
+        .label(format!(
+            "{:40} {:>10}",
+            "checkpoints",
+            bgwriter_events
+                .iter()
+                .filter(
+                    |(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time
+                )
+                .map(|(_, b)| b.checkpoints_timed + b.checkpoints_req)
+                .sum::<f64>()
+        ))
+        .legend(move |(x, y)| TriangleMarker::new((x, y), 5, GREEN_800.filled()));
+    // restartpoints, these are only available with pg_stat_checkpointer (version 17 and up)
+    contextarea
+        .draw_series(
+            checkpointer_events
+                .iter()
+                .filter(|(timestamp, _)| {
+                    *timestamp >= final_start_time && *timestamp <= final_end_time
+                })
+                .filter(|(_, c)| c.restartpoints_done > 0_f64)
+                .map(|(timestamp, _)| TriangleMarker::new((*timestamp, high_value), 5, RED_300)),
+        )
+        .unwrap()
+        .label(format!(
+            "{:40} {:>10}",
+            "restartpoints",
+            checkpointer_events
+                .iter()
+                .filter(
+                    |(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time
+                )
+                .map(|(_, c)| c.restartpoints_done)
+                .sum::<f64>()
+        ))
+        .legend(move |(x, y)| TriangleMarker::new((x, y), 5, RED_300.filled()));
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            timestamp_and_contexts
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:40} {:>10}  {:>5}", "", "avg", "%"));
+
+    let last_key = context_totals
+        .keys()
+        .max()
+        .unwrap_or(&"".to_string())
+        .clone();
+    let total = context_totals.values().sum::<f64>();
+    let samples = timestamp_and_contexts.len().max(1) as f64;
+    for (color_number, context) in context_totals.keys().enumerate() {
+        contextarea
+            .draw_series(AreaSeries::new(
+                timestamp_and_contexts.iter().map(|(timestamp, contexts)| {
+                    (
+                        *timestamp,
+                        contexts
+                            .range::<str, _>((
+                                Included(context.as_str()),
+                                Included(last_key.as_str()),
+                            ))
+                            .map(|(_, v)| *v)
+                            .sum::<f64>(),
+                    )
+                }),
+                0_f64,
+                Palette99::pick(color_number),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:40} {:>10}  {:>5.2}",
+                context,
+                if show_bytes {
+                    human_bytes(context_totals.get(context).unwrap() / samples)
+                } else {
+                    format!("{:.2}", context_totals.get(context).unwrap() / samples)
+                },
+                context_totals.get(context).unwrap() / total * 100_f64
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(color_number).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
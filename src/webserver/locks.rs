@@ -0,0 +1,383 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound::Included;
+
+use crate::processor::{PgLockEdge, PgStatActivity, Schedule};
+use crate::webserver::collection_gaps::{collection_gap_starts, draw_collection_gaps, gap_between};
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the number of blockers that are shown individually, the other blockers are added up.
+static TOP_BLOCKERS: usize = 10;
+
+// description of a backend for the legend and the blocking tree
+fn backend_description(pg_stat_activity: &PgStatActivity) -> String {
+    format!(
+        "{} {} {}",
+        pg_stat_activity.usename.as_deref().unwrap_or_default(),
+        pg_stat_activity
+            .application_name
+            .as_deref()
+            .unwrap_or_default(),
+        pg_stat_activity.backend_type.as_deref().unwrap_or_default()
+    )
+}
+
+pub fn top_blockers_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+    let pg_lock_edges = executor::block_on(DATA.pg_lock_edges.read());
+
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_stat_activity
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_stat_activity
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+
+    // the number of waiters that a blocker directly blocks, per sample.
+    let mut waiters_per_blocker: HashMap<DateTime<Local>, HashMap<i32, usize>> = HashMap::new();
+    let mut blocker_totals: HashMap<i32, usize> = HashMap::new();
+    for (timestamp, edges) in pg_lock_edges
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        let mut current_waiters: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for edge in edges.iter() {
+            current_waiters
+                .entry(edge.blocker_pid)
+                .or_default()
+                .insert(edge.waiter_pid);
+        }
+        for (blocker_pid, waiters) in current_waiters.iter() {
+            blocker_totals
+                .entry(*blocker_pid)
+                .and_modify(|r| *r += waiters.len())
+                .or_insert(waiters.len());
+        }
+        waiters_per_blocker.insert(
+            *timestamp,
+            current_waiters
+                .into_iter()
+                .map(|(blocker_pid, waiters)| (blocker_pid, waiters.len()))
+                .collect(),
+        );
+    }
+    let mut top_blockers: Vec<(i32, usize)> = blocker_totals.into_iter().collect();
+    top_blockers.sort_by_key(|(pid, total)| (Reverse(*total), *pid));
+    let other_total = top_blockers
+        .iter()
+        .skip(TOP_BLOCKERS)
+        .map(|(_, total)| total)
+        .sum::<usize>();
+    top_blockers.truncate(TOP_BLOCKERS);
+    let rank_of_blocker: HashMap<i32, usize> = top_blockers
+        .iter()
+        .enumerate()
+        .map(|(rank, (pid, _))| (*pid, rank))
+        .collect();
+    // the blockers that are not in the top are added to the last rank.
+    let other_rank = top_blockers.len();
+    let last_rank = if other_total > 0 {
+        other_rank
+    } else {
+        other_rank.saturating_sub(1)
+    };
+
+    // every pg_stat_activity sample is a point in the graph, so the graph drops to zero when
+    // there are no lock waits.
+    let mut timestamp_and_blockers: Vec<(DateTime<Local>, BTreeMap<usize, usize>)> = Vec::new();
+    let mut high_value = 0_usize;
+    let gap_starts = collection_gap_starts(Schedule::Activity);
+    let mut previous_timestamp: Option<DateTime<Local>> = None;
+    for (timestamp, _) in pg_stat_activity
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        let mut current_blockers: BTreeMap<usize, usize> =
+            (0..=last_rank).map(|r| (r, 0)).collect();
+        // the areas drop to zero at both sides of a gap in the samples
+        if let Some((after_previous, before_current)) =
+            previous_timestamp.and_then(|previous| gap_between(&gap_starts, previous, *timestamp))
+        {
+            timestamp_and_blockers.push((after_previous, current_blockers.clone()));
+            timestamp_and_blockers.push((before_current, current_blockers.clone()));
+        }
+        previous_timestamp = Some(*timestamp);
+        if let Some(blockers) = waiters_per_blocker.get(timestamp) {
+            for (blocker_pid, waiters) in blockers.iter() {
+                let rank = rank_of_blocker.get(blocker_pid).unwrap_or(&other_rank);
+                current_blockers
+                    .entry(*rank)
+                    .and_modify(|r| *r += waiters)
+                    .or_insert(*waiters);
+            }
+        }
+        high_value = high_value.max(current_blockers.values().sum::<usize>());
+        timestamp_and_blockers.push((*timestamp, current_blockers));
+    }
+    let high_value = high_value.max(1);
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            "Top blockers",
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_usize..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Sessions waiting per blocker")
+        .y_label_formatter(&|sessions| format!("{:4.0}", sessions))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Activity);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            timestamp_and_blockers
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_usize)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:50} {:>10}", "", "waits"));
+
+    let mut legend: Vec<(usize, String, usize)> = top_blockers
+        .iter()
+        .enumerate()
+        .map(|(rank, (pid, total))| {
+            let description = pg_stat_activity
+                .iter()
+                .rev()
+                .filter(|(timestamp, _)| {
+                    *timestamp >= final_start_time && *timestamp <= final_end_time
+                })
+                .find_map(|(_, v)| v.iter().find(|r| r.pid == *pid))
+                .map(backend_description)
+                .unwrap_or_default();
+            (rank, format!("{} {}", pid, description), *total)
+        })
+        .collect();
+    if other_total > 0 {
+        legend.push((other_rank, "other".to_string(), other_total));
+    }
+    for (rank, description, total) in legend {
+        contextarea
+            .draw_series(AreaSeries::new(
+                timestamp_and_blockers.iter().map(|(timestamp, blockers)| {
+                    (
+                        *timestamp,
+                        blockers
+                            .range((Included(rank), Included(last_rank)))
+                            .map(|(_, v)| *v)
+                            .sum::<usize>(),
+                    )
+                }),
+                0,
+                Palette99::pick(rank),
+            ))
+            .unwrap()
+            .label(format!("{:50} {:>10}", description, total))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(rank).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the blocking tree is shown for the requested sample, or, if no sample is requested, for the
+// sample with the most lock waits in the time range.
+pub fn blocking_tree_html(
+    timestamp: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+    let pg_lock_edges = executor::block_on(DATA.pg_lock_edges.read());
+
+    let final_start_time = start_time.unwrap_or_default();
+    let final_end_time = end_time.unwrap_or_else(Local::now);
+    let samples: Vec<&(DateTime<Local>, Vec<PgLockEdge>)> = pg_lock_edges
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .collect();
+
+    let requested_timestamp = timestamp
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Local));
+    let selected = match requested_timestamp {
+        Some(requested_timestamp) => pg_lock_edges
+            .iter()
+            .find(|(timestamp, _)| *timestamp == requested_timestamp),
+        None => samples.iter().max_by_key(|(_, edges)| edges.len()).copied(),
+    };
+
+    let mut html_output = String::new();
+    match selected {
+        Some((selected_timestamp, edges)) => {
+            let backends: HashMap<i32, &PgStatActivity> = pg_stat_activity
+                .iter()
+                .find(|(timestamp, _)| timestamp == selected_timestamp)
+                .map(|(_, v)| v.iter().map(|r| (r.pid, r)).collect())
+                .unwrap_or_default();
+            let waiters: HashSet<i32> = edges.iter().map(|r| r.waiter_pid).collect();
+            // the roots of the tree are the blockers that are not waiting themselves. if all
+            // blockers are waiting, there is a cycle, which is shown from the first blocker.
+            let mut roots: Vec<i32> = edges
+                .iter()
+                .map(|r| r.blocker_pid)
+                .filter(|pid| !waiters.contains(pid))
+                .collect::<HashSet<i32>>()
+                .into_iter()
+                .collect();
+            roots.sort();
+            if roots.is_empty() {
+                roots.extend(edges.iter().map(|r| r.blocker_pid).take(1));
+            }
+            html_output += format!(
+                "<h3>Blocking tree at {}</h3><ul>",
+                selected_timestamp.format("%Y-%m-%dT%H:%M:%S%.3f")
+            )
+            .as_str();
+            let mut visited: HashSet<i32> = HashSet::new();
+            for root in roots {
+                html_output +=
+                    blocking_tree_node(root, None, edges, &backends, &mut visited).as_str();
+            }
+            html_output += "</ul>";
+        }
+        None => {
+            html_output += "<p>No lock waits found in the selected time range.</p>";
+        }
+    }
+
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Time</th>
+                <th align=right>Blockers</th>
+                <th align=right>Waiters</th>
+            </tr>"#;
+    for (timestamp, edges) in samples.iter() {
+        html_output += format!(
+            r#"<tr>
+                <td><a href="/dual_handler/ash_blockers/blocking_tree/{}/N">{}</a></td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            timestamp.to_rfc3339(),
+            timestamp.format("%Y-%m-%dT%H:%M:%S%.3f"),
+            edges
+                .iter()
+                .map(|r| r.blocker_pid)
+                .collect::<HashSet<i32>>()
+                .len(),
+            edges
+                .iter()
+                .map(|r| r.waiter_pid)
+                .collect::<HashSet<i32>>()
+                .len(),
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
+
+fn blocking_tree_node(
+    pid: i32,
+    lock: Option<&PgLockEdge>,
+    edges: &[PgLockEdge],
+    backends: &HashMap<i32, &PgStatActivity>,
+    visited: &mut HashSet<i32>,
+) -> String {
+    let mut html_output = format!("<li><b>{}</b>", pid);
+    if let Some(lock) = lock {
+        html_output += format!(
+            " waits for {} {} {}",
+            lock.mode.as_deref().unwrap_or_default(),
+            lock.locktype.as_deref().unwrap_or_default(),
+            lock.relation.as_deref().unwrap_or_default()
+        )
+        .as_str();
+    }
+    if let Some(backend) = backends.get(&pid) {
+        html_output += format!(
+            " {} state: {} wait: {} query time: {}s <br><code>{}</code>",
+            backend_description(backend),
+            backend.state.as_deref().unwrap_or_default(),
+            backend.wait_event.as_deref().unwrap_or("on cpu"),
+            backend.query_time.unwrap_or_default(),
+            backend.query.as_deref().unwrap_or_default()
+        )
+        .as_str();
+    }
+    // a backend is only expanded once, which prevents endless recursion for a lock cycle.
+    if visited.insert(pid) {
+        let mut waiters: Vec<&PgLockEdge> = edges.iter().filter(|r| r.blocker_pid == pid).collect();
+        waiters.sort_by_key(|r| r.waiter_pid);
+        if !waiters.is_empty() {
+            html_output += "<ul>";
+            for waiter in waiters {
+                html_output +=
+                    blocking_tree_node(waiter.waiter_pid, Some(waiter), edges, backends, visited)
+                        .as_str();
+            }
+            html_output += "</ul>";
+        }
+    }
+    html_output += "</li>";
+    html_output
+}
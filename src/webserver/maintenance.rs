@@ -0,0 +1,302 @@
+use chrono::{DateTime, Duration, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperRight;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::processor::Schedule;
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    ARGS, CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the number of operations that are shown in the timeline, the longest operations are shown.
+static TIMELINE_OPERATIONS: usize = 30;
+
+#[derive(Debug, Default)]
+struct MaintenanceOperation {
+    pid: i32,
+    command: String,
+    datname: String,
+    relation: String,
+    backend_type: String,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+    // phase and percentage done per sample
+    samples: Vec<(DateTime<Local>, String, Option<f64>)>,
+}
+
+// the progress samples are combined into operations: an operation is a pid running a command
+// for a relation in consecutive samples.
+fn maintenance_operations(
+    commands: &[&str],
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+) -> Vec<MaintenanceOperation> {
+    let pg_stat_progress = executor::block_on(DATA.pg_stat_progress.read());
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+
+    let mut operations: Vec<MaintenanceOperation> = Vec::new();
+    // the index in operations of the operations seen in the previous sample
+    let mut previous: BTreeMap<(i32, String, String), usize> = BTreeMap::new();
+    let mut activity = pg_stat_activity.iter().peekable();
+    for (timestamp, per_sample_vector) in pg_stat_progress
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+    {
+        // the progress samples have the timestamp of a pg_stat_activity sample
+        while activity
+            .peek()
+            .is_some_and(|(activity_timestamp, _)| activity_timestamp < timestamp)
+        {
+            activity.next();
+        }
+        let backends = activity
+            .peek()
+            .filter(|(activity_timestamp, _)| activity_timestamp == timestamp)
+            .map(|(_, v)| v);
+        let mut current: BTreeMap<(i32, String, String), usize> = BTreeMap::new();
+        for row in per_sample_vector
+            .iter()
+            .filter(|r| commands.is_empty() || commands.contains(&r.command.as_str()))
+        {
+            let key = (
+                row.pid,
+                row.command.clone(),
+                row.relation.clone().unwrap_or_default(),
+            );
+            let index = match previous.get(&key) {
+                Some(index) => *index,
+                None => {
+                    operations.push(MaintenanceOperation {
+                        pid: row.pid,
+                        command: row.command.clone(),
+                        datname: row.datname.clone().unwrap_or_default(),
+                        relation: row.relation.clone().unwrap_or_default(),
+                        backend_type: backends
+                            .and_then(|v| v.iter().find(|r| r.pid == row.pid))
+                            .and_then(|r| r.backend_type.clone())
+                            .unwrap_or_default(),
+                        start_time: *timestamp,
+                        ..Default::default()
+                    });
+                    operations.len() - 1
+                }
+            };
+            operations[index].end_time = *timestamp;
+            operations[index].samples.push((
+                *timestamp,
+                row.phase.clone().unwrap_or_default(),
+                row.percent_done(),
+            ));
+            current.insert(key, index);
+        }
+        previous = current;
+    }
+    operations
+}
+
+pub fn maintenance_timeline<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    commands: &[&str],
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_stat_activity
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_stat_activity
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+    drop(pg_stat_activity);
+
+    let mut operations = maintenance_operations(commands, final_start_time, final_end_time);
+    operations.sort_by_key(|r| Reverse(r.end_time - r.start_time));
+    operations.truncate(TIMELINE_OPERATIONS);
+    operations.sort_by_key(|r| r.start_time);
+
+    // every phase gets its own color
+    let mut phases: BTreeMap<String, usize> = BTreeMap::new();
+    for operation in operations.iter() {
+        for (_, phase, _) in operation.samples.iter() {
+            let key = format!("{}: {}", operation.command, phase);
+            let color_number = phases.len();
+            phases.entry(key).or_insert(color_number);
+        }
+    }
+    let sample_duration = Duration::seconds(ARGS.interval as i64);
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            if commands.is_empty() {
+                "Maintenance operations".to_string()
+            } else {
+                format!("Maintenance operations: {}", commands.join(", "))
+            },
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(
+            final_start_time..final_end_time,
+            0_f64..operations.len().max(1) as f64,
+        )
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Operation")
+        .y_labels(0)
+        .disable_y_mesh()
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // the legend shows the phases, the phase rectangles are drawn per operation below.
+    for (phase, color_number) in phases.iter() {
+        let color_number = *color_number;
+        contextarea
+            .draw_series(std::iter::empty::<Rectangle<(DateTime<Local>, f64)>>())
+            .unwrap()
+            .label(phase)
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(color_number).filled(),
+                )
+            });
+    }
+    for (number, operation) in operations.iter().enumerate() {
+        let y = (operations.len() - number - 1) as f64;
+        contextarea
+            .draw_series(operation.samples.iter().map(|(timestamp, phase, _)| {
+                let color_number = *phases
+                    .get(&format!("{}: {}", operation.command, phase))
+                    .unwrap_or(&0);
+                Rectangle::new(
+                    [
+                        (*timestamp, y + 0.15_f64),
+                        (*timestamp + sample_duration, y + 0.85_f64),
+                    ],
+                    Palette99::pick(color_number).filled(),
+                )
+            }))
+            .unwrap();
+        let percent_done = operation
+            .samples
+            .iter()
+            .filter_map(|(_, _, percent_done)| *percent_done)
+            .next_back()
+            .map_or(String::new(), |r| format!(" {:.0}%", r));
+        contextarea
+            .draw_series(std::iter::once(Text::new(
+                format!(
+                    "{} {} {} {}{}",
+                    operation.pid,
+                    operation.backend_type,
+                    operation.command,
+                    operation.relation,
+                    percent_done
+                ),
+                (operation.start_time, y + 0.7_f64),
+                (LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE),
+            )))
+            .unwrap();
+    }
+
+    draw_settings_changes(&mut contextarea);
+    if !phases.is_empty() {
+        contextarea
+            .configure_series_labels()
+            .border_style(BLACK)
+            .background_style(WHITE.mix(0.7))
+            .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+            .position(UpperRight)
+            .draw()
+            .unwrap();
+    }
+}
+
+pub fn maintenance_operations_html(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let operations = maintenance_operations(
+        &[],
+        start_time.unwrap_or_default(),
+        end_time.unwrap_or_else(Local::now),
+    );
+    if operations.is_empty() {
+        return "<p>No maintenance operations found in the selected time range.</p>".to_string();
+    }
+    let mut html_output = String::from(
+        r#"<table border=1>
+            <tr>
+                <th>Start</th>
+                <th>End</th>
+                <th align=right>Seconds</th>
+                <th align=right>Pid</th>
+                <th>Backend type</th>
+                <th>Command</th>
+                <th>Database</th>
+                <th>Relation</th>
+                <th>Last phase</th>
+                <th align=right>Percent done</th>
+            </tr>"#,
+    );
+    for operation in operations.iter() {
+        let (_, last_phase, last_percent_done) = operation.samples.last().unwrap();
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            operation.start_time.format("%Y-%m-%dT%H:%M:%S"),
+            operation.end_time.format("%Y-%m-%dT%H:%M:%S"),
+            (operation.end_time - operation.start_time).num_seconds(),
+            operation.pid,
+            operation.backend_type,
+            operation.command,
+            operation.datname,
+            operation.relation,
+            last_phase,
+            last_percent_done.map_or(String::new(), |r| format!("{:.2}", r)),
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
@@ -0,0 +1,176 @@
+use axum::http::header;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::webserver::{AshDimension, AshFilter};
+use crate::{Data, CLUSTERS, DATA};
+
+// the wait event types that are always exported, so the series of a wait event type does not
+// disappear when no session waits on it.
+static WAIT_EVENT_TYPES: [&str; 10] = [
+    "activity",
+    "bufferpin",
+    "client",
+    "extension",
+    "io",
+    "ipc",
+    "lock",
+    "lwlock",
+    "on_cpu",
+    "timeout",
+];
+
+// the samples per metric name, a sample is the labels and the value.
+#[derive(Default)]
+struct Metrics(BTreeMap<String, (String, Vec<(String, f64)>)>);
+
+impl Metrics {
+    fn add(&mut self, name: &str, help: &str, labels: String, value: f64) {
+        self.0
+            .entry(format!("pas_{}", name))
+            .or_insert_with(|| (help.to_string(), Vec::new()))
+            .1
+            .push((labels, value));
+    }
+    // every numeric field of the last sample of a series is a gauge, named after the series and
+    // the field, such as pas_pg_stat_wal_wal_bytes_ps.
+    fn add_fields<T: Serialize>(
+        &mut self,
+        series: &str,
+        cluster: &str,
+        last: Option<&(DateTime<Local>, T)>,
+    ) {
+        let Some((timestamp, sample)) = last else {
+            return;
+        };
+        let labels = format!(r#"cluster="{}""#, escape(cluster));
+        self.add(
+            &format!("{}_timestamp_seconds", series),
+            &format!("the time of the last {} sample", series),
+            labels.clone(),
+            timestamp.timestamp_millis() as f64 / 1000_f64,
+        );
+        if let Ok(Value::Object(fields)) = serde_json::to_value(sample) {
+            for (field, value) in fields {
+                if let Some(value) = value.as_f64() {
+                    self.add(
+                        &format!("{}_{}", series, field),
+                        &format!("{} of the last {} sample", field, series),
+                        labels.clone(),
+                        value,
+                    );
+                }
+            }
+        }
+    }
+    // the prometheus text format: the help and type of every metric, followed by its samples.
+    fn render(&self) -> String {
+        let mut output = String::new();
+        for (name, (help, samples)) in self.0.iter() {
+            output += &format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name);
+            for (labels, value) in samples {
+                output += &format!("{}{{{}}} {}\n", name, labels, value);
+            }
+        }
+        output
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+async fn add_cluster(metrics: &mut Metrics, cluster: &str, data: &Data) {
+    metrics.add_fields(
+        "pg_stat_database",
+        cluster,
+        data.pg_stat_database_sum.read().await.back(),
+    );
+    metrics.add_fields(
+        "pg_stat_wal",
+        cluster,
+        data.pg_stat_wal_sum.read().await.back(),
+    );
+    metrics.add_fields(
+        "pg_stat_bgwriter",
+        cluster,
+        data.pg_stat_bgwriter_sum.read().await.back(),
+    );
+    metrics.add_fields(
+        "pg_stat_checkpointer",
+        cluster,
+        data.pg_stat_checkpointer_sum.read().await.back(),
+    );
+    metrics.add_fields(
+        "pg_database_xid_limits",
+        cluster,
+        data.pg_database_xid_limits.read().await.back(),
+    );
+
+    // the active sessions of the last pg_stat_activity sample, the same sessions as the ash
+    // plots count.
+    let pg_stat_activity = data.pg_stat_activity.read().await;
+    let Some((timestamp, per_sample_vector)) = pg_stat_activity.back() else {
+        return;
+    };
+    let mut active_sessions: BTreeMap<String, usize> = WAIT_EVENT_TYPES
+        .iter()
+        .map(|r| (r.to_string(), 0))
+        .collect();
+    let exclude_clientread = AshFilter::parse(AshDimension::WaitEvent, "!clientread");
+    let mut active_sessions_without_clientread = 0;
+    for row in per_sample_vector
+        .iter()
+        .filter(|r| r.state.as_deref().unwrap_or_default() == "active")
+    {
+        let wait_event_type = AshDimension::WaitEventType.value(row);
+        *active_sessions
+            .entry(wait_event_type.trim_start_matches('~').to_string())
+            .or_default() += 1;
+        if exclude_clientread.matches(row) {
+            active_sessions_without_clientread += 1;
+        }
+    }
+    metrics.add(
+        "pg_stat_activity_timestamp_seconds",
+        "the time of the last pg_stat_activity sample",
+        format!(r#"cluster="{}""#, escape(cluster)),
+        timestamp.timestamp_millis() as f64 / 1000_f64,
+    );
+    for (wait_event_type, count) in active_sessions {
+        metrics.add(
+            "active_sessions",
+            "the active sessions of the last pg_stat_activity sample by wait event type",
+            format!(
+                r#"cluster="{}",wait_event_type="{}""#,
+                escape(cluster),
+                escape(&wait_event_type)
+            ),
+            count as f64,
+        );
+    }
+    metrics.add(
+        "active_sessions_without_clientread",
+        "the active sessions of the last pg_stat_activity sample that do not wait for the client",
+        format!(r#"cluster="{}""#, escape(cluster)),
+        active_sessions_without_clientread as f64,
+    );
+}
+
+// the last values of all clusters, with the cluster as label.
+pub async fn metrics_handler() -> impl IntoResponse {
+    let mut metrics = Metrics::default();
+    for (cluster, data) in CLUSTERS.iter().zip(DATA.0.iter()) {
+        add_cluster(&mut metrics, &cluster.name, data).await;
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
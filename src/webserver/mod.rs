@@ -1,52 +1,94 @@
 use anyhow::Result;
 use axum::{
-    extract::{Form, Path},
+    extract::{Form, Path, Query, RawQuery, Request},
+    http::{header, StatusCode},
+    middleware::{self, Next},
     response::Html,
     response::IntoResponse,
+    response::{Redirect, Response},
     routing::{get, post},
     Router,
 };
 use axum_session::{Session, SessionConfig, SessionLayer, SessionNullPool, SessionStore};
 use chrono::{DateTime, Local};
+use futures::executor;
 use image::{DynamicImage, ImageFormat};
 use io::iops;
 use log::debug;
+use plotters::coord::Shift;
 use plotters::prelude::*;
 use plotters::style::full_palette::{
-    BLUE_600, BROWN, GREEN_800, GREY, LIGHTBLUE_300, PINK_A100, PURPLE, RED_900,
+    BLUE_600, BROWN, GREEN_800, GREY, GREY_400, LIGHTBLUE_300, PINK_A100, PURPLE, RED_900,
 };
 use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
 use std::io::Cursor;
 use std::time::Duration;
 use tokio::time::sleep;
 
 use crate::{
+    current_cluster,
+    processor::PgStatDatabaseSum,
     webserver::query::{
         show_queries_query_html, show_queries_queryid_html, waits_by_query_id, waits_by_query_text,
     },
-    ARGS, DATA,
+    ARGS, CLUSTER, CLUSTERS, DATA,
 };
 
+mod api;
+mod ash;
+mod collection_gaps;
+mod collectors;
+mod connections;
+mod interactive;
 mod io;
+mod io_context;
+mod locks;
+mod maintenance;
+mod metrics;
+mod os;
 mod query;
+mod replication;
+mod sessions;
+mod settings;
+mod tables;
 mod transactions;
 mod tuples;
 mod wait_events;
 mod wal;
 mod xid_age;
+mod xmin_horizon;
 
+pub use api::{
+    api_ash, api_series, api_series_list, parse_duration, parse_time, time_range_from_parameters,
+    TimeRange,
+};
+pub use ash::{ash_html, ash_plot, AshDimension, AshFilter, AshQuery};
+pub use collectors::{collector_lock_time, collector_time, collectors_html};
+pub use connections::{connection_rate_plot, connections_html, database_sessions_plot};
+pub use interactive::{interactive_data, interactive_html, interactive_live};
 pub use io::{io_bandwidth, io_times};
+pub use io_context::{io_context_bandwidth, io_context_iops, io_context_time};
+pub use locks::{blocking_tree_html, top_blockers_plot};
+pub use maintenance::{maintenance_operations_html, maintenance_timeline};
+pub use metrics::metrics_handler;
+pub use os::{disk_bandwidth, disk_latency, disk_utilization, os_cpu, os_memory, os_paging};
 pub use query::{show_queries, show_queries_html};
+pub use replication::{replication_lag_size, replication_lag_time, replication_slots_retained};
+pub use sessions::{backend_type_plot, session_state_plot, sessions_html};
+pub use settings::settings_html;
+pub use tables::{tables_heap_blks_read, tables_html, tables_seq_tup_read, tables_tuples_modified};
 pub use transactions::transactions;
 pub use tuples::tuples_processed;
 pub use wait_events::{wait_event_plot, wait_event_type_plot};
 pub use wal::{wal_io_times, wal_size};
 pub use xid_age::xid_age;
+pub use xmin_horizon::{xmin_horizon, xmin_horizon_html};
 
 pub fn wait_type_color(wait_event_type: &str) -> RGBColor {
     match wait_event_type {
         "activity" => PURPLE,
-        "buffer_pin" => LIGHTBLUE_300,
+        "bufferpin" => LIGHTBLUE_300,
         "client" => GREY,
         "extension" => GREEN_800,
         "timeout" => BROWN,
@@ -55,10 +97,8 @@ pub fn wait_type_color(wait_event_type: &str) -> RGBColor {
         "lock" => RED,
         "io" => BLUE_600,
         "on_cpu" | "~on_cpu" => GREEN,
-        other => {
-            println!("unknown wait event type: {:?}", other);
-            todo!()
-        }
+        // a wait event type of a newer postgres version, such as injectionpoint
+        _ => GREY_400,
     }
 }
 
@@ -86,8 +126,21 @@ pub async fn webserver_main() -> Result<()> {
             "/plotter/:plot_1/:queryid/:show_clientread",
             get(handler_plotter),
         )
+        .route("/metrics", get(metrics_handler))
+        .route("/api/v1/series", get(api_series_list))
+        .route("/api/v1/series/:name", get(api_series))
+        .route("/api/v1/ash", get(api_ash))
+        .route("/ash", get(ash_handler_html))
+        .route("/ash_plotter", get(ash_plotter))
+        .route("/interactive", get(interactive_html))
+        .route("/interactive_data", get(interactive_data))
+        .route("/interactive_live", get(interactive_live))
         .route("/set_time", post(set_time))
+        .route("/set_database", post(set_database))
+        .route("/set_cluster", post(set_cluster))
         .route("/", get(root_handler))
+        .layer(middleware::from_fn(time_range_scope))
+        .layer(middleware::from_fn(cluster_scope))
         .layer(SessionLayer::new(session_store));
     let listener =
         tokio::net::TcpListener::bind(format!("0.0.0.0:{}", ARGS.webserver_port)).await?;
@@ -100,66 +153,229 @@ pub async fn webserver_main() -> Result<()> {
 pub struct SetTime {
     pub start_time: String,
     pub end_time: String,
+    #[serde(default)]
+    pub last: String,
 }
+// the start and end of the time form are free form timestamps, such as 2024-06-01 12:00:00, and
+// the interactive chart sends rfc 3339 timestamps. a timestamp that cannot be parsed, such as an
+// empty one, removes that end of the time range. last, such as 15m, is a time range up to now
+// instead.
 pub async fn set_time(session: Session<SessionNullPool>, Form(set_time): Form<SetTime>) {
-    let start_time =
-        match DateTime::parse_from_str(&set_time.start_time, "%Y-%m-%d %H:%M:%S.%6f %:z") {
-            Ok(start_time) => Some(start_time),
-            _ => None,
-        };
-    let end_time = match DateTime::parse_from_str(&set_time.end_time, "%Y-%m-%d %H:%M:%S.%6f %:z") {
-        Ok(start_time) => Some(start_time),
-        _ => None,
-    };
+    match parse_duration(&set_time.last) {
+        Some(last) => set_session_time_range(&session, None, None, Some(last)),
+        None => set_session_time_range(
+            &session,
+            parse_time(&set_time.start_time),
+            parse_time(&set_time.end_time),
+            None,
+        ),
+    }
+}
+
+// the time range is kept in the session as a start and end time, or as the duration up to now
+// for a relative time range, which moves along with the time.
+fn set_session_time_range(
+    session: &Session<SessionNullPool>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+    last: Option<chrono::Duration>,
+) {
     session.set("start_time", start_time);
     session.set("end_time", end_time);
+    session.set("last", last.map(|last| last.num_seconds()));
+}
+
+pub fn session_time_range(session: &Session<SessionNullPool>) -> TimeRange {
+    match session
+        .get::<i64>("last")
+        .and_then(chrono::Duration::try_seconds)
+    {
+        Some(last) => (Some(Local::now() - last), None),
+        None => (session.get("start_time"), session.get("end_time")),
+    }
 }
 
+// the times are suggestions per minute of the samples, any timestamp with second precision can be
+// typed.
 pub async fn time_form() -> String {
     let mut form = r#"
     <iframe name="dummyframe" id="dummyframe" style="display: none;"></iframe>
     <form action="/set_time" method="post" target="dummyframe">
       <label for="start_time">start:</label>
-      <select id="start_time" name="start_time">
-        <option value="-">-</option>"
-    "#
-    .to_string();
+      <input list="sample_times" id="start_time" name="start_time" size="19">
+      <label for="end_time">end:</label>
+      <input list="sample_times" id="end_time" name="end_time" size="19">
+      <label for="last">or last:</label>
+      <input type="text" id="last" name="last" size="4" placeholder="15m">
+      <datalist id="sample_times">"#
+        .to_string();
 
     let mut minute = String::from("");
     let pg_stat_activity = DATA.pg_stat_database_sum.read().await;
     for timestamp in pg_stat_activity.iter().map(|(timestamp, _)| timestamp) {
         if minute != format!("{}", timestamp.format("%M")) {
             form += format!(
-                r#"<option value="{}">{}</option>"#,
-                timestamp,
-                timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                r#"<option value="{}">"#,
+                timestamp.format("%Y-%m-%d %H:%M:%S")
             )
             .as_str();
             minute = format!("{}", timestamp.format("%M"));
-        } else {
         };
     }
+    form += r#"
+      </datalist>
+      <input type="submit" value="submit">
+    </form>
+    "#;
+
+    form
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetDatabase {
+    pub database: String,
+}
+pub async fn set_database(
+    session: Session<SessionNullPool>,
+    Form(set_database): Form<SetDatabase>,
+) {
+    let database = Some(set_database.database).filter(|database| database != "-");
+    session.set("database", database);
+}
+
+// the databases are taken from the last per database sample, "-" shows all databases.
+pub async fn database_form() -> String {
+    let mut form = r#"
+    <form action="/set_database" method="post" target="dummyframe">
+      <label for="database">database:</label>
+      <select id="database" name="database">
+        <option value="-">-</option>"
+    "#
+    .to_string();
 
+    if let Some((_, per_database)) = DATA.pg_stat_database_sum_per_database.read().await.back() {
+        for database in per_database.keys() {
+            form += format!(r#"<option value="{}">{}</option>"#, database, database).as_str();
+        }
+    }
     form += r#"
       </select>
-      <label for="end_time">end:</label>
-      <select id="end_time" name="end_time">
-        <option value="-">-</option>"
+      <input type="submit" value="submit">
+    </form>
     "#;
 
-    for timestamp in pg_stat_activity.iter().map(|(timestamp, _)| timestamp) {
-        if minute != format!("{}", timestamp.format("%M")) {
-            form += format!(
-                r#"<option value="{}">{}</option>"#,
-                timestamp,
-                timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
-            )
-            .as_str();
-            minute = format!("{}", timestamp.format("%M"));
-        } else {
+    form
+}
+
+// the pg_stat_database statistics of the selected database, or the sum of all databases if no
+// database is selected.
+pub fn pg_stat_database_sum(
+    database: Option<&str>,
+) -> VecDeque<(DateTime<Local>, PgStatDatabaseSum)> {
+    match database {
+        Some(database) => executor::block_on(DATA.pg_stat_database_sum_per_database.read())
+            .iter()
+            .filter_map(|(timestamp, per_database)| {
+                per_database.get(database).map(|r| (*timestamp, r.clone()))
+            })
+            .collect(),
+        None => executor::block_on(DATA.pg_stat_database_sum.read())
+            .iter()
+            .cloned()
+            .collect(),
+    }
+}
+
+// every request is handled for the cluster that is selected in the session, DATA refers to the
+// data of that cluster. a cluster parameter in the url, such as for the api, overrides the session.
+async fn cluster_scope(
+    session: Session<SessionNullPool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let cluster = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(parameters)| parameters.get("cluster").cloned())
+        .or_else(|| session.get::<String>("cluster"))
+        .and_then(|name| CLUSTERS.iter().position(|r| r.name == name))
+        .unwrap_or_default();
+    CLUSTER.scope(cluster, next.run(request)).await
+}
+
+// a time range in the url, with start and end or with last, such as ?last=15m, sets the time range
+// of the session like the time form, so a link shows the same time range, also in the images of
+// the page and on the next pages. the api uses the time range of its parameters instead.
+async fn time_range_scope(
+    session: Session<SessionNullPool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let parameters = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .map(|Query(parameters)| parameters)
+        .unwrap_or_default();
+    if !request.uri().path().starts_with("/api/")
+        && ["start", "end", "last"]
+            .iter()
+            .any(|name| parameters.contains_key(*name))
+    {
+        let time_range = match parameters.get("last") {
+            Some(text) => parse_duration(text)
+                .map(|last| (None, None, Some(last)))
+                .ok_or_else(|| format!("last is not a valid duration, such as 15m: {}", text)),
+            None => time_range_from_parameters(&parameters)
+                .map(|(start_time, end_time)| (start_time, end_time, None)),
         };
+        match time_range {
+            Ok((start_time, end_time, last)) => {
+                set_session_time_range(&session, start_time, end_time, last)
+            }
+            Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+        }
+    }
+    next.run(request).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetCluster {
+    pub cluster: String,
+}
+pub async fn set_cluster(
+    session: Session<SessionNullPool>,
+    Form(set_cluster): Form<SetCluster>,
+) -> Redirect {
+    session.set("cluster", set_cluster.cluster);
+    // the databases are different per cluster
+    session.set("database", None::<String>);
+    Redirect::to("/")
+}
+
+// the cluster selector is only shown when multiple clusters are monitored. the whole page is
+// reloaded after a cluster is selected, because the time and database selections depend on it.
+pub fn cluster_form() -> String {
+    if CLUSTERS.len() < 2 {
+        return String::new();
+    }
+    let mut form = r#"
+    <form action="/set_cluster" method="post" target="_top">
+      <label for="cluster">cluster:</label>
+      <select id="cluster" name="cluster">
+    "#
+    .to_string();
+    for (number, cluster) in CLUSTERS.iter().enumerate() {
+        form += format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            cluster.name,
+            if number == current_cluster() {
+                " selected"
+            } else {
+                ""
+            },
+            cluster.name
+        )
+        .as_str();
     }
     form += r#"
+      </select>
       <input type="submit" value="submit">
     </form>
     "#;
@@ -170,8 +386,13 @@ pub async fn time_form() -> String {
 //pub async fn root_handler(session: Session<SessionNullPool>) -> Html<String> {
 pub async fn root_handler() -> Html<String> {
     loop {
-        // wait until there is data inside DATA
-        if DATA.pg_stat_database_sum.read().await.iter().count() > 0 {
+        // wait until there is data inside DATA. with multiple clusters, data of any cluster is
+        // enough, so a cluster that cannot be reached does not block selecting another cluster.
+        let mut records_found = false;
+        for data in DATA.0.iter() {
+            records_found |= data.pg_stat_database_sum.read().await.iter().count() > 0;
+        }
+        if records_found {
             debug!("Records found in DATA.pg_stat_database_sum, continue.");
             break;
         } else {
@@ -180,7 +401,9 @@ pub async fn root_handler() -> Html<String> {
         }
     }
 
+    let cluster_form = cluster_form();
     let form = time_form().await;
+    let database_form = database_form().await;
 
     format!(r##"<!doctype html>
  <html>
@@ -205,12 +428,27 @@ pub async fn root_handler() -> Html<String> {
      <li><a href="/handler/ash_wait_type/Y" target="right">ASH by wait type</a></li>
      <li><a href="/handler/ash_wait_event/Y" target="right">ASH by wait event</a></li>
      <li><a href="/dual_handler/ash_wait_query/all_queries/Y" target="right">ASH and Queries</a></li>
+     <li><a href="/ash?group_by=application_name" target="right">ASH by any dimension</a></li>
+     <li><a href="/interactive" target="right">ASH interactive</a></li>
+     <li><a href="/interactive?group_by=wait_event&window=15m&live=Y" target="right">ASH live by wait event</a></li>
+     <li><a href="/dual_handler/ash_blockers/blocking_tree/N" target="right">Lock blocking</a></li>
+     <li><a href="/dual_handler/sessions/sessions/x" target="right">Sessions</a></li>
+     <li><a href="/dual_handler/connections/connections/x" target="right">Connections</a></li>
      <li><a href="/handler/wal_io_times/x" target="right">WAL latency</a></li>
      <li><a href="/handler/wal_size/x" target="right">WAL size</a></li>
+     <li><a href="/handler/replication/x" target="right">Replication</a></li>
      <li><a href="/handler/io_latency/x" target="right">IO latency</a></li>
      <li><a href="/handler/io_bandwidth/x" target="right">IO bandwidth</a></li>
      <li><a href="/handler/iops/x" target="right">IOPS</a></li>
+     <li><a href="/handler/io_context/x" target="right">IO by context</a></li>
      <li><a href="/handler/xid_age/x" target="right">XID Age</a></li>
+     <li><a href="/dual_handler/xmin_horizon/xmin_horizon/x" target="right">Vacuum horizon</a></li>
+     <li><a href="/dual_handler/maintenance/maintenance_operations/Y" target="right">Maintenance</a></li>
+     <li><a href="/dual_handler/tables/tables/x" target="right">Tables</a></li>
+     <li><a href="/handler/os/N" target="right">OS</a></li>
+     <li><a href="/handler/os_disk/x" target="right">OS disk IO</a></li>
+     <li><a href="/dual_handler/ash_wait_type/settings/Y" target="right">Settings</a></li>
+     <li><a href="/dual_handler/collectors/collectors/x" target="right">PAS overhead</a></li>
      <li><a href="/handler/transactions/Y" target="right">Transactions</a></li>
      <li><a href="/handler/tuples/Y" target="right">Tuples</a></li>
      <li><a href="/handler/ash_wait_type/N" target="right">ASH by wait type (no clientread)</a></li>
@@ -219,6 +457,8 @@ pub async fn root_handler() -> Html<String> {
      <li><a href="/handler/transactions/N" target="right">Transactions (no clientread)</a></li>
      <li><a href="/handler/tuples/N" target="right">Tuples (no clientread)</a></li>
      <p>{}</p>
+     <p>{}</p>
+     <p>{}</p>
     </nav>
    </div>
    <div class = "column_right">
@@ -227,7 +467,7 @@ pub async fn root_handler() -> Html<String> {
   </div>
   </body>
  </html>
- "##, form)
+ "##, cluster_form, form, database_form)
     .to_string()
     .into()
 }
@@ -250,11 +490,21 @@ pub async fn dual_handler_html(
     session: Session<SessionNullPool>,
     Path((plot_1, out_1, show_clientread)): Path<(String, String, String)>,
 ) -> Html<String> {
-    let start_time = session.get::<DateTime<Local>>("start_time");
-    let end_time = session.get::<DateTime<Local>>("end_time");
+    let (start_time, end_time) = session_time_range(&session);
+    let database = session.get::<String>("database");
     let output: String = format!(r#"<img src="/plotter/{}/x/{}">"#, plot_1, show_clientread);
     let html = match out_1.as_str() {
-        "all_queries" => show_queries_html(show_clientread, start_time, end_time),
+        "all_queries" => {
+            show_queries_html(show_clientread, database.as_deref(), start_time, end_time)
+        }
+        "blocking_tree" => blocking_tree_html(None, start_time, end_time),
+        "maintenance_operations" => maintenance_operations_html(start_time, end_time),
+        "tables" => tables_html(start_time, end_time),
+        "collectors" => collectors_html(start_time, end_time),
+        "settings" => settings_html(start_time, end_time),
+        "sessions" => sessions_html(database.as_deref(), start_time, end_time),
+        "connections" => connections_html(database.as_deref(), start_time, end_time),
+        "xmin_horizon" => xmin_horizon_html(start_time, end_time),
         &_ => todo!(),
     };
     format!("{}{}", output, html).into()
@@ -263,8 +513,8 @@ pub async fn dual_handler_html_queryid(
     session: Session<SessionNullPool>,
     Path((plot_1, out_1, queryid, show_clientread)): Path<(String, String, String, String)>,
 ) -> Html<String> {
-    let start_time = session.get::<DateTime<Local>>("start_time");
-    let end_time = session.get::<DateTime<Local>>("end_time");
+    let (start_time, end_time) = session_time_range(&session);
+    let database = session.get::<String>("database");
     debug!(
         "dual_handler: plot_1: {}, out_1: {}, queryid: {}, show_clientread: {}",
         plot_1, out_1, queryid, show_clientread
@@ -277,87 +527,163 @@ pub async fn dual_handler_html_queryid(
         "all_queries" => show_queries_queryid_html(
             &queryid.parse::<i64>().unwrap(),
             show_clientread,
+            database.as_deref(),
             start_time,
             end_time,
         ),
-        "selected_queries" => {
-            show_queries_query_html(&queryid, show_clientread, start_time, end_time)
-        }
+        "selected_queries" => show_queries_query_html(
+            &queryid,
+            show_clientread,
+            database.as_deref(),
+            start_time,
+            end_time,
+        ),
+        "blocking_tree" => blocking_tree_html(Some(&queryid), start_time, end_time),
         &_ => todo!(),
     };
     format!("{}{}", output, html).into()
 }
 
+// the response of a plot that body draws on backend: an svg image for the url parameter
+// format=svg, otherwise a png image. the svg image is written as text, so it scales without
+// allocating the pixel buffer of the png image.
+macro_rules! plot_response {
+    ($parameters:expr, $backend:ident => $body:expr) => {
+        if $parameters.get("format").map(String::as_str) == Some("svg") {
+            let mut svg = String::new();
+            {
+                let $backend =
+                    SVGBackend::with_string(&mut svg, (ARGS.graph_width, ARGS.graph_height))
+                        .into_drawing_area();
+                $body;
+            }
+            ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response()
+        } else {
+            let mut buffer = vec![
+                0;
+                (ARGS.graph_width * ARGS.graph_height * 3)
+                    .try_into()
+                    .unwrap()
+            ];
+            {
+                let $backend =
+                    BitMapBackend::with_buffer(&mut buffer, (ARGS.graph_width, ARGS.graph_height))
+                        .into_drawing_area();
+                $body;
+            }
+            png(buffer).into_response()
+        }
+    };
+}
+
 pub async fn handler_plotter(
     session: Session<SessionNullPool>,
     Path((plot_1, queryid, show_clientread)): Path<(String, String, String)>,
-) -> impl IntoResponse {
-    let start_time = session.get::<DateTime<Local>>("start_time");
-    let end_time = session.get::<DateTime<Local>>("end_time");
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Response {
+    let (start_time, end_time) = session_time_range(&session);
+    let database = session.get::<String>("database");
     debug!(
         "handler_plotter: plot_1: {}, queryid: {}, show_clientread: {}",
         plot_1, queryid, show_clientread
     );
-    let mut buffer = vec![
-        0;
-        (ARGS.graph_width * ARGS.graph_height * 3)
-            .try_into()
-            .unwrap()
-    ];
-    let remove_clientread = if show_clientread.as_str() == "Y" {
-        false
-    } else {
-        true
-    };
-    match plot_1.as_str() {
+    plot_response!(&parameters, backend => draw_plot(
+        backend,
+        &plot_1,
+        queryid,
+        &show_clientread,
+        database.as_deref(),
+        start_time,
+        end_time,
+    ))
+}
+
+fn draw_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    plot_1: &str,
+    queryid: String,
+    show_clientread: &str,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let remove_clientread = show_clientread != "Y";
+    match plot_1 {
         "ash_wait_type" => {
-            create_ash_wait_type_plot(&mut buffer, remove_clientread, start_time, end_time)
+            create_ash_wait_type_plot(backend, remove_clientread, database, start_time, end_time)
         }
         "ash_wait_event" => {
-            create_ash_wait_event_plot(&mut buffer, remove_clientread, start_time, end_time)
+            create_ash_wait_event_plot(backend, remove_clientread, database, start_time, end_time)
         }
         "ash_wait_query" => create_ash_wait_event_and_queryid_overview(
-            &mut buffer,
+            backend,
             remove_clientread,
+            database,
             start_time,
             end_time,
         ),
-        "wal_io_times" => create_wait_event_type_and_wal_io_plot(&mut buffer, start_time, end_time),
-        "wal_size" => create_wait_event_type_and_wal_size_plot(&mut buffer, start_time, end_time),
+        "wal_io_times" => create_wait_event_type_and_wal_io_plot(backend, start_time, end_time),
+        "wal_size" => create_wait_event_type_and_wal_size_plot(backend, start_time, end_time),
+        "replication" => create_wal_size_and_replication_plot(backend, start_time, end_time),
         "io_latency" => {
-            create_wait_event_type_and_io_latency_plot(&mut buffer, start_time, end_time)
+            create_wait_event_type_and_io_latency_plot(backend, database, start_time, end_time)
         }
         "io_bandwidth" => {
-            create_wait_event_type_and_io_bandwidth_plot(&mut buffer, start_time, end_time)
+            create_wait_event_type_and_io_bandwidth_plot(backend, start_time, end_time)
+        }
+        "iops" => create_iops_plot(backend, start_time, end_time),
+        "io_context" => create_io_context_plot(backend, start_time, end_time),
+        "tables" => create_tables_plot(backend, start_time, end_time),
+        "os" => create_ash_wait_type_and_os_plot(backend, remove_clientread, start_time, end_time),
+        "os_disk" => create_os_disk_plot(backend, start_time, end_time),
+        "collectors" => create_collectors_plot(backend, start_time, end_time),
+        "ash_blockers" => {
+            create_ash_wait_type_and_blockers_plot(backend, remove_clientread, start_time, end_time)
         }
-        "iops" => create_iops_plot(&mut buffer, start_time, end_time),
-        "xid_age" => create_xid_age_plot(&mut buffer, start_time, end_time),
+        "xid_age" => create_xid_age_plot(backend, start_time, end_time),
+        "xmin_horizon" => create_xmin_horizon_plot(backend, start_time, end_time),
+        "sessions" => create_sessions_plot(backend, database, start_time, end_time),
+        "connections" => create_connections_plot(backend, database, start_time, end_time),
+        "maintenance" => create_ash_wait_type_and_maintenance_plot(
+            backend,
+            remove_clientread,
+            start_time,
+            end_time,
+        ),
         "transactions" => create_wait_event_and_transactions_plot(
-            &mut buffer,
+            backend,
             remove_clientread,
+            database,
+            start_time,
+            end_time,
+        ),
+        "tuples" => create_wait_event_and_tuples_plot(
+            backend,
+            remove_clientread,
+            database,
             start_time,
             end_time,
         ),
-        "tuples" => {
-            create_wait_event_and_tuples_plot(&mut buffer, remove_clientread, start_time, end_time)
-        }
         "we_qid_q" => create_wait_events_and_queryid_and_query(
-            &mut buffer,
+            backend,
             remove_clientread,
+            database,
             start_time,
             end_time,
         ),
         "ash_wait_query_by_queryid" => create_ash_wait_query_by_queryid(
-            &mut buffer,
+            backend,
             queryid,
             remove_clientread,
+            database,
             start_time,
             end_time,
         ),
         "ash_wait_query_by_query" => create_ash_wait_query_by_query(
-            &mut buffer,
+            backend,
             queryid,
             remove_clientread,
+            database,
             start_time,
             end_time,
         ),
@@ -366,6 +692,9 @@ pub async fn handler_plotter(
             todo!()
         }
     }
+}
+
+fn png(buffer: Vec<u8>) -> Vec<u8> {
     let rgb_image = DynamicImage::ImageRgb8(
         image::RgbImage::from_raw(ARGS.graph_width, ARGS.graph_height, buffer).unwrap(),
     );
@@ -374,15 +703,126 @@ pub async fn handler_plotter(
     cursor.into_inner()
 }
 
-pub fn create_ash_wait_query_by_queryid(
-    buffer: &mut [u8],
+// text from the url or from pg_stat_activity, such as an application_name, that is put in html.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the ash query of the url parameters: group_by is the dimension to group by, the default is
+// wait_event_type, and every other dimension can be a filter, such as wait_event=!clientread or
+// application_name=app1,app2. the database of the session is used when datname is not set.
+fn ash_query_from_parameters(
+    parameters: &HashMap<String, String>,
+    database: Option<&str>,
+) -> AshQuery {
+    let mut ash_query = AshQuery::new(
+        parameters
+            .get("group_by")
+            .and_then(|r| AshDimension::from_name(r))
+            .unwrap_or(AshDimension::WaitEventType),
+    );
+    for dimension in AshDimension::ALL {
+        if let Some(text) = parameters
+            .get(dimension.name())
+            .filter(|r| !r.trim().is_empty())
+        {
+            ash_query = ash_query.filter(AshFilter::parse(dimension, text));
+        }
+    }
+    if !parameters.contains_key(AshDimension::Datname.name()) {
+        ash_query = ash_query.exclude_clientread_and_database(false, database);
+    }
+    ash_query
+}
+
+// the form to choose the dimension and the filters of an ash query, which is submitted to action.
+fn ash_form(action: &str, ash_query: &AshQuery, parameters: &HashMap<String, String>) -> String {
+    let mut form = format!(
+        r#"<form action="{}" method="get">
+      <label for="group_by">group by:</label>
+      <select id="group_by" name="group_by">"#,
+        action
+    );
+    for dimension in AshDimension::ALL {
+        form += format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            dimension.name(),
+            if dimension == ash_query.group_by {
+                " selected"
+            } else {
+                ""
+            },
+            dimension.name()
+        )
+        .as_str();
+    }
+    form += "</select><br>";
+    for dimension in AshDimension::ALL {
+        form += format!(
+            r#"<label for="{name}">{name}:</label>
+      <input type="text" id="{name}" name="{name}" value="{value}"> "#,
+            name = dimension.name(),
+            value = parameters
+                .get(dimension.name())
+                .map_or(String::new(), |r| escape_html(r)),
+        )
+        .as_str();
+    }
+    form += r#"<br>filters are comma separated values, a leading ! excludes the values, for example wait_event=!clientread.
+      <input type="submit" value="submit">
+    </form>"#;
+
+    form
+}
+
+// the form to choose the dimension and the filters, the plot and the table of the ash query.
+pub async fn ash_handler_html(
+    session: Session<SessionNullPool>,
+    Query(parameters): Query<HashMap<String, String>>,
+    RawQuery(raw_query): RawQuery,
+) -> Html<String> {
+    let (start_time, end_time) = session_time_range(&session);
+    let database = session.get::<String>("database");
+    let ash_query = ash_query_from_parameters(&parameters, database.as_deref());
+
+    format!(
+        r#"{}<img src="/ash_plotter?{}">{}"#,
+        ash_form("/ash", &ash_query, &parameters),
+        escape_html(&raw_query.unwrap_or_default()),
+        ash_html(&ash_query, start_time, end_time)
+    )
+    .into()
+}
+
+pub async fn ash_plotter(
+    session: Session<SessionNullPool>,
+    Query(parameters): Query<HashMap<String, String>>,
+) -> Response {
+    let (start_time, end_time) = session_time_range(&session);
+    let database = session.get::<String>("database");
+    let ash_query = ash_query_from_parameters(&parameters, database.as_deref());
+    plot_response!(&parameters, backend => ash_plot(
+        &mut backend.split_evenly((1, 1)),
+        0,
+        &ash_query,
+        &format!("Active sessions by {}", ash_query.group_by.name()),
+        "Active sessions",
+        start_time,
+        end_time,
+    ))
+}
+
+pub fn create_ash_wait_query_by_queryid<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     queryid: String,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -392,6 +832,7 @@ pub fn create_ash_wait_query_by_queryid(
         &false,
         "",
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
@@ -401,19 +842,19 @@ pub fn create_ash_wait_query_by_queryid(
         &true,
         &queryid.parse::<i64>().unwrap(),
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
 }
-pub fn create_ash_wait_query_by_query(
-    buffer: &mut [u8],
+pub fn create_ash_wait_query_by_query<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     query: String,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((1, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -423,35 +864,35 @@ pub fn create_ash_wait_query_by_query(
         &true,
         &query,
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
 }
-pub fn create_ash_wait_type_plot(
-    buffer: &mut [u8],
+pub fn create_ash_wait_type_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((1, 1));
     wait_event_type_plot(
         &mut multi_backend,
         0,
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
 }
-pub fn create_ash_wait_event_plot(
-    buffer: &mut [u8],
+pub fn create_ash_wait_event_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((1, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -461,18 +902,18 @@ pub fn create_ash_wait_event_plot(
         &false,
         "",
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
 }
-pub fn create_ash_wait_event_and_queryid_overview(
-    buffer: &mut [u8],
+pub fn create_ash_wait_event_and_queryid_overview<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -482,6 +923,7 @@ pub fn create_ash_wait_event_and_queryid_overview(
         &false,
         "",
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
@@ -491,18 +933,35 @@ pub fn create_ash_wait_event_and_queryid_overview(
         &false,
         &0_i64,
         remove_clientread,
+        database,
+        start_time,
+        end_time,
+    );
+}
+pub fn create_ash_wait_type_and_blockers_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    remove_clientread: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    wait_event_type_plot(
+        &mut multi_backend,
+        0,
+        remove_clientread,
+        None,
         start_time,
         end_time,
     );
+    top_blockers_plot(&mut multi_backend, 1, start_time, end_time);
 }
-pub fn create_wait_event_and_transactions_plot(
-    buffer: &mut [u8],
+pub fn create_wait_event_and_transactions_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -512,19 +971,19 @@ pub fn create_wait_event_and_transactions_plot(
         &false,
         "",
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
-    transactions(&mut multi_backend, 1, start_time, end_time);
+    transactions(&mut multi_backend, 1, database, start_time, end_time);
 }
-pub fn create_wait_event_and_tuples_plot(
-    buffer: &mut [u8],
+pub fn create_wait_event_and_tuples_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -534,34 +993,80 @@ pub fn create_wait_event_and_tuples_plot(
         &false,
         "",
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
-    tuples_processed(&mut multi_backend, 1, start_time, end_time);
+    tuples_processed(&mut multi_backend, 1, database, start_time, end_time);
 }
-pub fn create_xid_age_plot(
-    buffer: &mut [u8],
+pub fn create_xid_age_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
-    let mut multi_backend = backend.split_evenly((1, 1));
+    let mut multi_backend = backend.split_evenly((2, 1));
     xid_age(&mut multi_backend, 0, start_time, end_time);
+    maintenance_timeline(&mut multi_backend, 1, &["vacuum"], start_time, end_time);
+}
+pub fn create_sessions_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    session_state_plot(&mut multi_backend, 0, database, start_time, end_time);
+    backend_type_plot(&mut multi_backend, 1, database, start_time, end_time);
 }
-pub fn create_wait_events_and_queryid_and_query(
-    buffer: &mut [u8],
+pub fn create_connections_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    connection_rate_plot(&mut multi_backend, 0, database, start_time, end_time);
+    database_sessions_plot(&mut multi_backend, 1, database, start_time, end_time);
+}
+pub fn create_xmin_horizon_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    xmin_horizon(&mut multi_backend, 0, start_time, end_time);
+    xid_age(&mut multi_backend, 1, start_time, end_time);
+}
+pub fn create_ash_wait_type_and_maintenance_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    remove_clientread: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    wait_event_type_plot(
+        &mut multi_backend,
+        0,
+        remove_clientread,
+        None,
+        start_time,
+        end_time,
+    );
+    maintenance_timeline(&mut multi_backend, 1, &[], start_time, end_time);
+}
+pub fn create_wait_events_and_queryid_and_query<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     remove_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((3, 1));
     wait_event_type_plot(
         &mut multi_backend,
         0,
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
@@ -571,6 +1076,7 @@ pub fn create_wait_events_and_queryid_and_query(
         &false,
         &0_i64,
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
@@ -578,17 +1084,16 @@ pub fn create_wait_events_and_queryid_and_query(
         &mut multi_backend,
         2,
         remove_clientread,
+        database,
         start_time,
         end_time,
     );
 }
-pub fn create_wait_event_type_and_io_bandwidth_plot(
-    buffer: &mut [u8],
+pub fn create_wait_event_type_and_io_bandwidth_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
     wait_event_plot(
         &mut multi_backend,
@@ -598,52 +1103,115 @@ pub fn create_wait_event_type_and_io_bandwidth_plot(
         &false,
         "",
         true,
+        None,
         start_time,
         end_time,
     );
     io_bandwidth(&mut multi_backend, 1, start_time, end_time);
 }
-pub fn create_iops_plot(
-    buffer: &mut [u8],
+pub fn create_iops_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((1, 1));
     //wait_event_plot(&mut multi_backend, 0, &false, &0_i64, &false, "");
     iops(&mut multi_backend, 0, start_time, end_time);
 }
-pub fn create_wait_event_type_and_io_latency_plot(
-    buffer: &mut [u8],
+pub fn create_io_context_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((3, 1));
+    io_context_bandwidth(&mut multi_backend, 0, start_time, end_time);
+    io_context_iops(&mut multi_backend, 1, start_time, end_time);
+    io_context_time(&mut multi_backend, 2, start_time, end_time);
+}
+pub fn create_tables_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((3, 1));
+    tables_seq_tup_read(&mut multi_backend, 0, start_time, end_time);
+    tables_tuples_modified(&mut multi_backend, 1, start_time, end_time);
+    tables_heap_blks_read(&mut multi_backend, 2, start_time, end_time);
+}
+pub fn create_collectors_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
-    wait_event_type_plot(&mut multi_backend, 0, true, start_time, end_time);
-    io_times(&mut multi_backend, 1, start_time, end_time);
+    collector_time(&mut multi_backend, 0, start_time, end_time);
+    collector_lock_time(&mut multi_backend, 1, start_time, end_time);
+}
+pub fn create_ash_wait_type_and_os_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    remove_clientread: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((4, 1));
+    wait_event_type_plot(
+        &mut multi_backend,
+        0,
+        remove_clientread,
+        None,
+        start_time,
+        end_time,
+    );
+    os_cpu(&mut multi_backend, 1, start_time, end_time);
+    os_memory(&mut multi_backend, 2, start_time, end_time);
+    os_paging(&mut multi_backend, 3, start_time, end_time);
 }
-pub fn create_wait_event_type_and_wal_io_plot(
-    buffer: &mut [u8],
+pub fn create_os_disk_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((3, 1));
+    disk_bandwidth(&mut multi_backend, 0, start_time, end_time);
+    disk_latency(&mut multi_backend, 1, start_time, end_time);
+    disk_utilization(&mut multi_backend, 2, start_time, end_time);
+}
+pub fn create_wait_event_type_and_io_latency_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
-    wait_event_type_plot(&mut multi_backend, 0, true, start_time, end_time);
+    wait_event_type_plot(&mut multi_backend, 0, true, database, start_time, end_time);
+    io_times(&mut multi_backend, 1, database, start_time, end_time);
+}
+pub fn create_wait_event_type_and_wal_io_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((2, 1));
+    wait_event_type_plot(&mut multi_backend, 0, true, None, start_time, end_time);
     wal_io_times(&mut multi_backend, 1, start_time, end_time);
 }
-pub fn create_wait_event_type_and_wal_size_plot(
-    buffer: &mut [u8],
+pub fn create_wait_event_type_and_wal_size_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let backend = BitMapBackend::with_buffer(buffer, (ARGS.graph_width, ARGS.graph_height))
-        .into_drawing_area();
     let mut multi_backend = backend.split_evenly((2, 1));
-    wait_event_type_plot(&mut multi_backend, 0, true, start_time, end_time);
+    wait_event_type_plot(&mut multi_backend, 0, true, None, start_time, end_time);
     wal_size(&mut multi_backend, 1, start_time, end_time);
 }
+pub fn create_wal_size_and_replication_plot<DB: DrawingBackend>(
+    backend: DrawingArea<DB, Shift>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let mut multi_backend = backend.split_evenly((4, 1));
+    wal_size(&mut multi_backend, 0, start_time, end_time);
+    replication_lag_time(&mut multi_backend, 1, start_time, end_time);
+    replication_lag_size(&mut multi_backend, 2, start_time, end_time);
+    replication_slots_retained(&mut multi_backend, 3, start_time, end_time);
+}
@@ -0,0 +1,572 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use human_bytes::human_bytes;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{BLUE_300, GREEN_800, GREY, ORANGE, PURPLE, RED_900};
+use std::collections::BTreeMap;
+
+use crate::processor::{ProcDiskstatsSum, ProcMeminfo, ProcStatSum, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+type TimeSeries = BTreeMap<String, Vec<(DateTime<Local>, f64)>>;
+type ValueFn<T> = fn(&T) -> f64;
+
+// the time range of the os plots is taken from /proc/stat, which is read every interval.
+fn os_time_range(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> (DateTime<Local>, DateTime<Local>) {
+    let proc_stat_sum = executor::block_on(DATA.proc_stat_sum.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        proc_stat_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        proc_stat_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+    (final_start_time, final_end_time)
+}
+
+fn min_max_last(values: &[(DateTime<Local>, f64)]) -> (f64, f64, f64) {
+    if values.is_empty() {
+        return (0_f64, 0_f64, 0_f64);
+    }
+    let min_value = values
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::INFINITY, f64::min);
+    let max_value = values
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let last_value = values.last().map_or(0_f64, |(_, value)| *value);
+    (min_value, max_value, last_value)
+}
+
+// the cpu usage is shown in cpus, so it can be compared with the number of active sessions. the
+// cpu states are stacked, with the run queue and the load average drawn as lines on top.
+pub fn os_cpu<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    // the time range is determined before taking the lock on proc_stat_sum, it takes the lock
+    // itself.
+    let (final_start_time, final_end_time) = os_time_range(start_time, end_time);
+    let proc_stat_sum = executor::block_on(DATA.proc_stat_sum.read());
+    let proc_loadavg = executor::block_on(DATA.proc_loadavg.read());
+
+    let proc_stat_sum: Vec<&(DateTime<Local>, ProcStatSum)> = proc_stat_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .collect();
+    let run_queue: Vec<(DateTime<Local>, f64)> = proc_stat_sum
+        .iter()
+        .map(|(timestamp, r)| (*timestamp, r.procs_running))
+        .collect();
+    let load: Vec<(DateTime<Local>, f64)> = proc_loadavg
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .map(|(timestamp, r)| (*timestamp, r.load_1))
+        .collect();
+    let cpus = proc_stat_sum.last().map_or(1, |(_, r)| r.cpus) as f64;
+    let high_value = run_queue
+        .iter()
+        .chain(load.iter())
+        .map(|(_, value)| *value)
+        .fold(cpus, f64::max)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            format!("CPU usage ({} cpus)", cpus),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("CPUs")
+        .y_label_formatter(&|cpus| format!("{:4.1}", cpus))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Os);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            proc_stat_sum
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:25} {:>10} {:>10} {:>10}",
+            "", "min", "max", "last"
+        ));
+
+    // every area is the sum of its own cpu state and the states after it.
+    let cpu_states: [(&str, ValueFn<ProcStatSum>, RGBColor); 6] = [
+        ("user", |r| r.user, GREEN),
+        ("nice", |r| r.nice, GREEN_800),
+        ("system", |r| r.system, RED_900),
+        ("irq", |r| r.irq + r.softirq, PURPLE),
+        ("iowait", |r| r.iowait, BLUE_300),
+        ("steal", |r| r.steal, ORANGE),
+    ];
+    for (number, (name, value, color)) in cpu_states.iter().enumerate() {
+        let values: Vec<(DateTime<Local>, f64)> = proc_stat_sum
+            .iter()
+            .map(|(timestamp, r)| (*timestamp, value(r)))
+            .collect();
+        let (min_value, max_value, last_value) = min_max_last(&values);
+        let color = *color;
+        contextarea
+            .draw_series(AreaSeries::new(
+                proc_stat_sum.iter().map(|(timestamp, r)| {
+                    (
+                        *timestamp,
+                        cpu_states
+                            .iter()
+                            .skip(number)
+                            .map(|(_, value, _)| value(r))
+                            .sum::<f64>(),
+                    )
+                }),
+                0_f64,
+                color,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10.2} {:>10.2} {:>10.2}",
+                name, min_value, max_value, last_value
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    for (name, values, color) in [
+        ("run queue", run_queue, BLACK),
+        ("load average 1 minute", load, RED),
+    ] {
+        let (min_value, max_value, last_value) = min_max_last(&values);
+        contextarea
+            .draw_series(LineSeries::new(values, color.stroke_width(2)))
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10.2} {:>10.2} {:>10.2}",
+                name, min_value, max_value, last_value
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+    contextarea
+        .draw_series(LineSeries::new(
+            [(final_start_time, cpus), (final_end_time, cpus)],
+            GREY.stroke_width(2),
+        ))
+        .unwrap();
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the memory is stacked as used, cached (including buffers) and free, the swap in use is drawn
+// as a line.
+pub fn os_memory<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let proc_meminfo = executor::block_on(DATA.proc_meminfo.read());
+    let (final_start_time, final_end_time) = os_time_range(start_time, end_time);
+
+    let proc_meminfo: Vec<&(DateTime<Local>, ProcMeminfo)> = proc_meminfo
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .collect();
+    let high_value = proc_meminfo
+        .iter()
+        .map(|(_, r)| r.memtotal.max(r.swaptotal - r.swapfree))
+        .fold(1_f64, f64::max)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption("Memory", (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Memory")
+        .y_label_formatter(&|size| human_bytes(*size))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Os);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            proc_meminfo
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:25} {:>10} {:>10} {:>10}",
+            "", "min", "max", "last"
+        ));
+
+    // every area is the sum of its own memory type and the types after it.
+    let memory_types: [(&str, ValueFn<ProcMeminfo>, RGBColor); 3] = [
+        (
+            "used",
+            |r| r.memtotal - r.memfree - r.buffers - r.cached,
+            RED_900,
+        ),
+        ("cached", |r| r.buffers + r.cached, BLUE_300),
+        ("free", |r| r.memfree, GREEN),
+    ];
+    for (number, (name, value, color)) in memory_types.iter().enumerate() {
+        let values: Vec<(DateTime<Local>, f64)> = proc_meminfo
+            .iter()
+            .map(|(timestamp, r)| (*timestamp, value(r)))
+            .collect();
+        let (min_value, max_value, last_value) = min_max_last(&values);
+        let color = *color;
+        contextarea
+            .draw_series(AreaSeries::new(
+                proc_meminfo.iter().map(|(timestamp, r)| {
+                    (
+                        *timestamp,
+                        memory_types
+                            .iter()
+                            .skip(number)
+                            .map(|(_, value, _)| value(r))
+                            .sum::<f64>(),
+                    )
+                }),
+                0_f64,
+                color,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10} {:>10} {:>10}",
+                name,
+                human_bytes(min_value),
+                human_bytes(max_value),
+                human_bytes(last_value)
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+    for (name, value, color) in [
+        (
+            "dirty",
+            (|r| r.dirty + r.writeback) as ValueFn<ProcMeminfo>,
+            ORANGE,
+        ),
+        ("swap used", |r| r.swaptotal - r.swapfree, BLACK),
+    ] {
+        let values: Vec<(DateTime<Local>, f64)> = proc_meminfo
+            .iter()
+            .map(|(timestamp, r)| (*timestamp, value(r)))
+            .collect();
+        let (min_value, max_value, last_value) = min_max_last(&values);
+        contextarea
+            .draw_series(LineSeries::new(values, color.stroke_width(2)))
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10} {:>10} {:>10}",
+                name,
+                human_bytes(min_value),
+                human_bytes(max_value),
+                human_bytes(last_value)
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+pub fn os_paging<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let proc_vmstat_sum = executor::block_on(DATA.proc_vmstat_sum.read());
+    let mut series: TimeSeries = BTreeMap::new();
+    for (timestamp, row) in proc_vmstat_sum.iter() {
+        for (name, value) in [
+            ("swap in", row.pswpin_ps),
+            ("swap out", row.pswpout_ps),
+            ("major faults", row.pgmajfault_ps),
+        ] {
+            series
+                .entry(name.to_string())
+                .or_default()
+                .push((*timestamp, value));
+        }
+    }
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Swapping and major page faults",
+        "Pages per second",
+        series,
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn disk_bandwidth<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Disk bandwidth",
+        "Bytes per second",
+        disk_series(&[
+            ("read", |r| r.read_bytes_ps),
+            ("write", |r| r.write_bytes_ps),
+        ]),
+        true,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn disk_latency<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Disk latency",
+        "Milliseconds",
+        disk_series(&[("read", |r| r.read_latency), ("write", |r| r.write_latency)]),
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn disk_utilization<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Disk utilization and queue size",
+        "Percent / requests",
+        disk_series(&[
+            ("utilization %", |r| r.utilization),
+            ("queue size", |r| r.queue_size),
+        ]),
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+fn disk_series(values: &[(&str, ValueFn<ProcDiskstatsSum>)]) -> TimeSeries {
+    let proc_diskstats_sum = executor::block_on(DATA.proc_diskstats_sum.read());
+    let mut series: TimeSeries = BTreeMap::new();
+    for (timestamp, per_sample_vector) in proc_diskstats_sum.iter() {
+        for row in per_sample_vector.iter() {
+            for (name, value) in values.iter() {
+                series
+                    .entry(format!("{} {}", row.device, name))
+                    .or_default()
+                    .push((*timestamp, value(row)));
+            }
+        }
+    }
+    series
+}
+
+#[allow(clippy::too_many_arguments)]
+fn line_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    caption: &str,
+    y_description: &str,
+    series: TimeSeries,
+    show_bytes: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time) = os_time_range(start_time, end_time);
+    let format_value = |value: f64| {
+        if show_bytes {
+            human_bytes(value)
+        } else {
+            format!("{:.2}", value)
+        }
+    };
+    let series: TimeSeries = series
+        .into_iter()
+        .map(|(name, values)| {
+            (
+                name,
+                values
+                    .into_iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+    let high_value = series
+        .values()
+        .flat_map(|v| v.iter())
+        .map(|(_, value)| *value)
+        .fold(1_f64, f64::max)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(caption, (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc(y_description)
+        .y_label_formatter(&|value| format_value(*value))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Os);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            [(final_start_time, 0_f64)],
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:40} {:>10} {:>10} {:>10}",
+            "", "min", "max", "last"
+        ));
+
+    for (color_number, (name, values)) in series.into_iter().enumerate() {
+        if values.is_empty() {
+            continue;
+        }
+        let (min_value, max_value, last_value) = min_max_last(&values);
+        contextarea
+            .draw_series(LineSeries::new(
+                values,
+                Palette99::pick(color_number).stroke_width(2),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:40} {:>10} {:>10} {:>10}",
+                name,
+                format_value(min_value),
+                format_value(max_value),
+                format_value(last_value)
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(color_number).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
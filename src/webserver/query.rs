@@ -1,3 +1,4 @@
+use crate::processor::{PgStatActivity, PgStatStatementsQueryid};
 use crate::{ARGS, DATA};
 use crate::{
     CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
@@ -6,17 +7,43 @@ use crate::{
 use base64::{engine::general_purpose::URL_SAFE, Engine as _};
 use chrono::{DateTime, Local};
 use futures::executor;
-use plotters::backend::RGBPixel;
+use human_bytes::human_bytes;
 use plotters::chart::SeriesLabelPosition::LowerRight;
 use plotters::coord::Shift;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, HashMap};
 use std::ops::Bound::Included;
 
-pub fn show_queries(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+// the cpu time, run queue time and io of the backends from /proc/<pid>, summed for the samples
+// of a queryid. samples is the number of samples that had these statistics.
+#[derive(Debug, Default, Clone, Copy)]
+struct BackendUsage {
+    samples: usize,
+    cpu_time: f64,
+    runqueue_time: f64,
+    read_bytes: f64,
+    write_bytes: f64,
+}
+
+impl BackendUsage {
+    fn add(&mut self, row: &PgStatActivity) {
+        if let Some(cpu_time) = row.cpu_time {
+            self.samples += 1;
+            self.cpu_time += cpu_time;
+            self.runqueue_time += row.runqueue_time.unwrap_or_default();
+            self.read_bytes += row.read_bytes.unwrap_or_default();
+            self.write_bytes += row.write_bytes.unwrap_or_default();
+        }
+    }
+}
+
+pub fn show_queries<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     exclude_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
@@ -53,7 +80,8 @@ pub fn show_queries(
         .map(|(_, v)| v)
     {
         for r in per_sample_vector.iter().filter(|r| {
-            !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+            (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                && database.is_none_or(|d| r.datname.as_deref() == Some(d))
         }) {
             if r.state.as_deref().unwrap_or_default() == "active" {
                 samples_per_queryid
@@ -81,7 +109,7 @@ pub fn show_queries(
             total: vector.total,
         });
     }
-    qc.sort_by(|a, b| b.total.cmp(&a.total));
+    qc.sort_by_key(|b| Reverse(b.total));
     let grand_total_samples: f64 = qc.iter().map(|r| r.total as f64).sum();
 
     multi_backend[backend_number].fill(&WHITE).unwrap();
@@ -153,6 +181,7 @@ pub fn show_queries(
 pub fn show_queries_query_html(
     query: &str,
     show_clientread: String,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) -> String {
@@ -184,11 +213,7 @@ pub fn show_queries_query_html(
     };
     let mut samples_per_query: HashMap<String, QueryidAndTotal> =
         HashMap::with_capacity(pg_stat_activity.len());
-    let exclude_clientread = if show_clientread.as_str() == "Y" {
-        false
-    } else {
-        true
-    };
+    let exclude_clientread = show_clientread.as_str() != "Y";
     for per_sample_vector in pg_stat_activity
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
@@ -200,7 +225,8 @@ pub fn show_queries_query_html(
                 r.query.as_deref().unwrap_or_default() == String::from_utf8(query.clone()).unwrap()
             })
             .filter(|r| {
-                !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+                (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                    && database.is_none_or(|d| r.datname.as_deref() == Some(d))
             })
         {
             if r.state.as_deref().unwrap_or_default() == "active" {
@@ -229,7 +255,7 @@ pub fn show_queries_query_html(
             total: vector.total,
         });
     }
-    qc.sort_by(|a, b| b.total.cmp(&a.total));
+    qc.sort_by_key(|b| Reverse(b.total));
     let grand_total_samples: f64 = qc.iter().map(|r| r.total as f64).sum();
 
     let mut html_output = format!(
@@ -282,6 +308,7 @@ pub fn show_queries_query_html(
 pub fn show_queries_queryid_html(
     queryid: &i64,
     show_clientread: String,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) -> String {
@@ -311,11 +338,7 @@ pub fn show_queries_queryid_html(
     };
     let mut samples_per_query: HashMap<String, QueryidAndTotal> =
         HashMap::with_capacity(pg_stat_activity.len());
-    let exclude_clientread = if show_clientread.as_str() == "Y" {
-        false
-    } else {
-        true
-    };
+    let exclude_clientread = show_clientread.as_str() != "Y";
     for per_sample_vector in pg_stat_activity
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
@@ -325,7 +348,8 @@ pub fn show_queries_queryid_html(
             .iter()
             .filter(|r| r.query_id.as_ref().unwrap_or(&0) == queryid)
             .filter(|r| {
-                !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+                (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                    && database.is_none_or(|d| r.datname.as_deref() == Some(d))
             })
         {
             if r.state.as_deref().unwrap_or_default() == "active" {
@@ -354,7 +378,7 @@ pub fn show_queries_queryid_html(
             total: vector.total,
         });
     }
-    qc.sort_by(|a, b| b.total.cmp(&a.total));
+    qc.sort_by_key(|b| Reverse(b.total));
     let grand_total_samples: f64 = qc.iter().map(|r| r.total as f64).sum();
 
     let mut html_output = format!(
@@ -410,10 +434,122 @@ pub fn show_queries_queryid_html(
     .as_str();
 
     html_output += "</table>";
-    html_output
+    // the pg_stat_statements figures for the queryid are shown before the ASH figures.
+    format!(
+        "{}{}",
+        pg_stat_statements_queryid_html(queryid, start_time, end_time),
+        html_output
+    )
+}
+pub fn pg_stat_statements_queryid_html(
+    queryid: &i64,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let pg_stat_statements = executor::block_on(DATA.pg_stat_statements.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_stat_statements
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_stat_statements
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+    // the number of seconds is taken from the samples, not from the requested start and end time,
+    // because the samples are the period for which the calls have been measured. the calls of a
+    // sample are the calls since the previous sample, so the period starts at the sample before the
+    // first sample in the time range.
+    let in_range = |timestamp: &DateTime<Local>| {
+        *timestamp >= final_start_time && *timestamp <= final_end_time
+    };
+    let timestamps: Vec<DateTime<Local>> = pg_stat_statements
+        .iter()
+        .map(|(timestamp, _)| *timestamp)
+        .collect();
+    let seconds = match (
+        timestamps.iter().position(in_range),
+        timestamps.iter().rposition(in_range),
+    ) {
+        (Some(first), Some(last)) => {
+            timestamps[last]
+                .signed_duration_since(timestamps[first.saturating_sub(1)])
+                .num_milliseconds() as f64
+                / 1000_f64
+        }
+        _ => 0_f64,
+    };
+
+    let mut total = PgStatStatementsQueryid::default();
+    for row in pg_stat_statements
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .flat_map(|(_, v)| v.iter())
+        .filter(|r| r.queryid == *queryid)
+    {
+        total.calls_delta += row.calls_delta;
+        total.total_exec_time_delta += row.total_exec_time_delta;
+        total.rows_delta += row.rows_delta;
+        total.shared_blks_read_delta += row.shared_blks_read_delta;
+        total.shared_blks_hit_delta += row.shared_blks_hit_delta;
+        total.wal_bytes_delta += row.wal_bytes_delta;
+    }
+    if total.calls_delta == 0_f64 {
+        return "<p>No pg_stat_statements calls found for this queryid.</p>".to_string();
+    }
+
+    format!(
+        r#"<table border=1>
+            <tr>
+                <th align=right>Calls</th>
+                <th align=right>Calls/s</th>
+                <th align=right>Total time ms</th>
+                <th align=right>Mean time ms</th>
+                <th align=right>Rows</th>
+                <th align=right>Rows/call</th>
+                <th align=right>Shared blks read</th>
+                <th align=right>Shared blks hit</th>
+                <th align=right>WAL</th>
+            </tr>
+            <tr>
+                <td align=right>{:.0}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.3}</td>
+                <td align=right>{:.3}</td>
+                <td align=right>{:.0}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.0}</td>
+                <td align=right>{:.0}</td>
+                <td align=right>{}</td>
+            </tr>
+        </table>"#,
+        total.calls_delta,
+        if seconds == 0_f64 {
+            0_f64
+        } else {
+            total.calls_delta / seconds
+        },
+        total.total_exec_time_delta,
+        total.total_exec_time_delta / total.calls_delta,
+        total.rows_delta,
+        total.rows_delta / total.calls_delta,
+        total.shared_blks_read_delta,
+        total.shared_blks_hit_delta,
+        human_bytes(total.wal_bytes_delta),
+    )
 }
 pub fn show_queries_html(
     show_clientread: String,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) -> String {
@@ -421,6 +557,7 @@ pub fn show_queries_html(
     struct QueryAndTotal {
         query: String,
         total: usize,
+        usage: BackendUsage,
     }
     let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
     let final_start_time = if let Some(final_start_time) = start_time {
@@ -443,27 +580,26 @@ pub fn show_queries_html(
     };
     let mut samples_per_queryid: HashMap<i64, QueryAndTotal> =
         HashMap::with_capacity(pg_stat_activity.len());
-    let exclude_clientread = if show_clientread.as_str() == "Y" {
-        false
-    } else {
-        true
-    };
+    let exclude_clientread = show_clientread.as_str() != "Y";
     for per_sample_vector in pg_stat_activity
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
         .map(|(_, v)| v)
     {
         for r in per_sample_vector.iter().filter(|r| {
-            !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+            (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                && database.is_none_or(|d| r.datname.as_deref() == Some(d))
         }) {
             if r.state.as_deref().unwrap_or_default() == "active" {
-                samples_per_queryid
+                let query_and_total = samples_per_queryid
                     .entry(r.query_id.unwrap_or_default())
-                    .and_modify(|r| r.total += 1)
                     .or_insert(QueryAndTotal {
                         query: r.query.as_deref().unwrap_or_default().to_string(),
-                        total: 1,
+                        total: 0,
+                        usage: BackendUsage::default(),
                     });
+                query_and_total.total += 1;
+                query_and_total.usage.add(r);
             }
         }
     }
@@ -473,6 +609,7 @@ pub fn show_queries_html(
         query_id: i64,
         query: String,
         total: usize,
+        usage: BackendUsage,
     }
     let mut qc: Vec<QueryIdQueryTotal> = Vec::new();
     for (query_id, vector) in samples_per_queryid {
@@ -480,9 +617,10 @@ pub fn show_queries_html(
             query_id,
             query: vector.query,
             total: vector.total,
+            usage: vector.usage,
         });
     }
-    qc.sort_by(|a, b| b.total.cmp(&a.total));
+    qc.sort_by_key(|b| Reverse(b.total));
     let grand_total_samples: f64 = qc.iter().map(|r| r.total as f64).sum();
 
     let mut html_output = format!(
@@ -491,15 +629,23 @@ pub fn show_queries_html(
                 <col style="width:160px;">
                 <col style="width:80;">
                 <col style="width:100px;">
+                <col style="width:100px;">
+                <col style="width:100px;">
+                <col style="width:100px;">
+                <col style="width:100px;">
                 <col style="width:{}px;">
             </colgroup>
             <tr>
                 <th align=right>Query ID</th>
                 <th align=right>Percent</th>
                 <th align=right>Total</th>
+                <th align=right>CPU time</th>
+                <th align=right>Run queue</th>
+                <th align=right>Read</th>
+                <th align=right>Written</th>
                 <th>Query</th>
             </tr>"#,
-        ARGS.graph_width - (160_u32 + 80_u32 + 100_u32)
+        ARGS.graph_width - (160_u32 + 80_u32 + 500_u32)
     );
 
     for query in qc.iter() {
@@ -510,6 +656,10 @@ pub fn show_queries_html(
                 </td>
                 <td align=right>{:6.2}%</td>
                 <td align=right>{:8}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
                 <td>
                   <a href="/dual_handler/ash_wait_query_by_query/selected_queries/{}/{}">{}</a>
                 </td>
@@ -519,10 +669,14 @@ pub fn show_queries_html(
             query.query_id,
             query.total as f64 / grand_total_samples * 100_f64,
             query.total,
+            usage_seconds(query.usage.samples, query.usage.cpu_time),
+            usage_seconds(query.usage.samples, query.usage.runqueue_time),
+            usage_bytes(query.usage.samples, query.usage.read_bytes),
+            usage_bytes(query.usage.samples, query.usage.write_bytes),
             if query.query_id == 0 {
                 "*".to_string()
             } else {
-                URL_SAFE.encode(query.query.to_string())
+                URL_SAFE.encode(&query.query)
             },
             show_clientread,
             if query.query_id == 0 {
@@ -538,21 +692,61 @@ pub fn show_queries_html(
                 <td align=right>{:>20}</td>
                 <td align=right>{:6.2}%</td>
                 <td align=right>{:8}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
                 <td>{}</td>
             </tr>",
-        "total", 100_f64, grand_total_samples, ""
+        "total",
+        100_f64,
+        grand_total_samples,
+        usage_seconds(
+            qc.iter().map(|r| r.usage.samples).sum(),
+            qc.iter().map(|r| r.usage.cpu_time).sum()
+        ),
+        usage_seconds(
+            qc.iter().map(|r| r.usage.samples).sum(),
+            qc.iter().map(|r| r.usage.runqueue_time).sum()
+        ),
+        usage_bytes(
+            qc.iter().map(|r| r.usage.samples).sum(),
+            qc.iter().map(|r| r.usage.read_bytes).sum()
+        ),
+        usage_bytes(
+            qc.iter().map(|r| r.usage.samples).sum(),
+            qc.iter().map(|r| r.usage.write_bytes).sum()
+        ),
+        ""
     )
     .as_str();
 
     html_output += "</table>";
     html_output
 }
-pub fn waits_by_query_id(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+// the usage columns are empty when there are no statistics from /proc/<pid>.
+fn usage_seconds(samples: usize, seconds: f64) -> String {
+    if samples == 0 {
+        String::new()
+    } else {
+        format!("{:.2} s", seconds)
+    }
+}
+fn usage_bytes(samples: usize, bytes: f64) -> String {
+    if samples == 0 {
+        String::new()
+    } else {
+        human_bytes(bytes)
+    }
+}
+#[allow(clippy::too_many_arguments)]
+pub fn waits_by_query_id<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     queryid_filter: &bool,
     queryid: &i64,
     exclude_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
@@ -578,6 +772,7 @@ pub fn waits_by_query_id(
     let mut wait_event_counter: BTreeMap<String, usize> = BTreeMap::new();
     let mut queryid_waits: HashMap<i64, BTreeMap<String, usize>> =
         HashMap::with_capacity(pg_stat_activity.len());
+    let mut queryid_usage: HashMap<i64, BackendUsage> = HashMap::new();
     for (_, per_sample_vector) in pg_stat_activity
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
@@ -586,7 +781,8 @@ pub fn waits_by_query_id(
             .iter()
             .filter(|r| !*queryid_filter || r.query_id.as_ref().unwrap_or(&0) == queryid)
             .filter(|r| {
-                !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+                (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                    && database.is_none_or(|d| r.datname.as_deref() == Some(d))
             })
         {
             if row.state.as_deref().unwrap_or_default() == "active" {
@@ -616,6 +812,10 @@ pub fn waits_by_query_id(
                             .or_insert(1_usize);
                     })
                     .or_insert(BTreeMap::from([(wait_event.clone(), 1_usize)]));
+                queryid_usage
+                    .entry(row.query_id.unwrap_or_default())
+                    .or_default()
+                    .add(row);
             }
         }
     }
@@ -637,7 +837,7 @@ pub fn waits_by_query_id(
     let mut samples_max = 0;
     for (_, waits) in queryid_waits.iter() {
         samples_max =
-            samples_max.max(waits.iter().map(|(_, nr)| *nr as isize).sum::<isize>() as usize);
+            samples_max.max(waits.values().map(|nr| *nr as isize).sum::<isize>() as usize);
     }
     #[derive(Debug, Default)]
     struct DynamicQueryIdTotalWaits {
@@ -645,6 +845,7 @@ pub fn waits_by_query_id(
         total: usize,
         others: bool,
         waits: BTreeMap<String, usize>,
+        usage: BackendUsage,
     }
 
     let mut queryid_total_waits: Vec<DynamicQueryIdTotalWaits> =
@@ -655,10 +856,11 @@ pub fn waits_by_query_id(
             queryid: *queryid,
             waits: waits.clone(),
             others: false,
+            usage: queryid_usage.get(queryid).copied().unwrap_or_default(),
             total: {
                 waits
-                    .iter()
-                    .map(|(_, nr)| *nr as isize)
+                    .values()
+                    .map(|nr| *nr as isize)
                     .sum::<isize>()
                     .try_into()
                     .unwrap()
@@ -666,7 +868,7 @@ pub fn waits_by_query_id(
         })
     }
     //queryid_total_waits.sort_by_key(|k| k.total);
-    queryid_total_waits.sort_by(|a, b| b.total.cmp(&a.total));
+    queryid_total_waits.sort_by_key(|b| Reverse(b.total));
 
     let mut queryid_total_waits_count = if queryid_total_waits.len() > 1 {
         queryid_total_waits.len() - 1
@@ -690,6 +892,15 @@ pub fn waits_by_query_id(
 
     queryid_total_waits.reverse();
 
+    // the cpu time and io of the queryid from /proc/<pid> are written after the bars, which
+    // needs some room on the right side of the graph.
+    let show_usage = queryid_total_waits.iter().any(|r| r.usage.samples > 0);
+    let x_max = if show_usage {
+        samples_max + samples_max / 2
+    } else {
+        samples_max
+    };
+
     // build the graph
     multi_backend[backend_number].fill(&WHITE).unwrap();
     let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
@@ -699,10 +910,7 @@ pub fn waits_by_query_id(
             "Query id by number of samples",
             (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
         )
-        .build_cartesian_2d(
-            0..samples_max,
-            (0..queryid_total_waits_count).into_segmented(),
-        )
+        .build_cartesian_2d(0..x_max, (0..queryid_total_waits_count).into_segmented())
         .unwrap();
     contextarea
         .configure_mesh()
@@ -804,6 +1012,30 @@ pub fn waits_by_query_id(
             ));
     }
 
+    if show_usage {
+        contextarea
+            .draw_series(
+                (0..)
+                    .zip(queryid_total_waits.iter())
+                    .filter(|(_, x)| !x.others && x.usage.samples > 0)
+                    .map(|(y, x)| {
+                        Text::new(
+                            format!(
+                                " cpu {:.1}s, run queue {:.1}s, read {}, written {}",
+                                x.usage.cpu_time,
+                                x.usage.runqueue_time,
+                                human_bytes(x.usage.read_bytes),
+                                human_bytes(x.usage.write_bytes)
+                            ),
+                            (x.total, SegmentValue::CenterOf(y)),
+                            TextStyle::from((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+                                .pos(Pos::new(HPos::Left, VPos::Center)),
+                        )
+                    }),
+            )
+            .unwrap();
+    }
+
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -813,12 +1045,14 @@ pub fn waits_by_query_id(
         .draw()
         .unwrap();
 }
-pub fn waits_by_query_text(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+#[allow(clippy::too_many_arguments)]
+pub fn waits_by_query_text<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     queryid_filter: &bool,
     queryid: &i64,
     exclude_clientread: bool,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
@@ -852,7 +1086,8 @@ pub fn waits_by_query_text(
             .iter()
             .filter(|r| !*queryid_filter || r.query_id.as_ref().unwrap_or(&0) == queryid)
             .filter(|r| {
-                !exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread"
+                (!exclude_clientread || r.wait_event.as_deref().unwrap_or_default() != "clientread")
+                    && database.is_none_or(|d| r.datname.as_deref() == Some(d))
             })
         {
             if row.state.as_deref().unwrap_or_default() == "active" {
@@ -903,7 +1138,7 @@ pub fn waits_by_query_text(
     let mut samples_max = 0;
     for (_, waits) in query_waits.iter() {
         samples_max =
-            samples_max.max(waits.iter().map(|(_, nr)| *nr as isize).sum::<isize>() as usize);
+            samples_max.max(waits.values().map(|nr| *nr as isize).sum::<isize>() as usize);
     }
     #[derive(Debug, Default)]
     struct DynamicQueryTextTotalWaits {
@@ -925,15 +1160,15 @@ pub fn waits_by_query_text(
             nr: 0,
             total: {
                 waits
-                    .iter()
-                    .map(|(_, nr)| *nr as isize)
+                    .values()
+                    .map(|nr| *nr as isize)
                     .sum::<isize>()
                     .try_into()
                     .unwrap()
             },
         })
     }
-    query_total_waits.sort_by(|a, b| b.total.cmp(&a.total));
+    query_total_waits.sort_by_key(|b| Reverse(b.total));
     for (nr, record) in query_total_waits.iter_mut().enumerate() {
         record.nr = nr;
     }
@@ -0,0 +1,283 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use human_bytes::human_bytes;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::BTreeMap;
+
+use crate::processor::{PgStatReplication, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+type TimeSeries = BTreeMap<String, Vec<(DateTime<Local>, f64)>>;
+
+// a standby is identified by its application_name and client address, because the pid of the
+// walsender changes when a standby reconnects.
+fn standby_name(pg_stat_replication: &PgStatReplication) -> String {
+    format!(
+        "{} {}",
+        pg_stat_replication
+            .application_name
+            .as_deref()
+            .unwrap_or_default(),
+        pg_stat_replication
+            .client_addr
+            .as_deref()
+            .unwrap_or("local")
+    )
+}
+
+pub fn replication_lag_time<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_replication = executor::block_on(DATA.pg_stat_replication.read());
+    let pg_stat_wal_receiver = executor::block_on(DATA.pg_stat_wal_receiver.read());
+    let mut series: TimeSeries = BTreeMap::new();
+    for (timestamp, per_sample_vector) in pg_stat_replication.iter() {
+        for row in per_sample_vector.iter() {
+            for (lag_type, lag) in [
+                ("write", row.write_lag),
+                ("flush", row.flush_lag),
+                ("replay", row.replay_lag),
+            ] {
+                series
+                    .entry(format!("{} {}", standby_name(row), lag_type))
+                    .or_default()
+                    .push((*timestamp, lag.unwrap_or_default()));
+            }
+        }
+    }
+    for (timestamp, row) in pg_stat_wal_receiver.iter() {
+        series
+            .entry("wal receiver replay delay".to_string())
+            .or_default()
+            .push((*timestamp, row.replay_delay.unwrap_or_default()));
+    }
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Replication lag time",
+        "Seconds",
+        series,
+        false,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn replication_lag_size<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_replication = executor::block_on(DATA.pg_stat_replication.read());
+    let pg_stat_wal_receiver = executor::block_on(DATA.pg_stat_wal_receiver.read());
+    let mut series: TimeSeries = BTreeMap::new();
+    for (timestamp, per_sample_vector) in pg_stat_replication.iter() {
+        for row in per_sample_vector.iter() {
+            series
+                .entry(format!("{} replay", standby_name(row)))
+                .or_default()
+                .push((*timestamp, row.replay_lag_bytes.unwrap_or_default()));
+        }
+    }
+    for (timestamp, row) in pg_stat_wal_receiver.iter() {
+        series
+            .entry("wal receiver replay".to_string())
+            .or_default()
+            .push((*timestamp, row.replay_lag_bytes.unwrap_or_default()));
+    }
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Replication lag size",
+        "Wal size",
+        series,
+        true,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn replication_slots_retained<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_replication_slots = executor::block_on(DATA.pg_replication_slots.read());
+    let mut series: TimeSeries = BTreeMap::new();
+    for (timestamp, per_sample_vector) in pg_replication_slots.iter() {
+        for row in per_sample_vector.iter() {
+            series
+                .entry(format!(
+                    "{} {}",
+                    row.slot_name,
+                    row.slot_type.as_deref().unwrap_or_default()
+                ))
+                .or_default()
+                .push((*timestamp, row.retained_bytes.unwrap_or_default()));
+        }
+    }
+    line_plot(
+        multi_backend,
+        backend_number,
+        "Wal retained by replication slots",
+        "Wal size",
+        series,
+        true,
+        start_time,
+        end_time,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn line_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    caption: &str,
+    y_description: &str,
+    series: TimeSeries,
+    show_bytes: bool,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    // the time range is taken from pg_stat_wal, which is fetched every interval, so that the
+    // graph is shown next to the wal generation rate, also if there is no replication.
+    let wal_events = executor::block_on(DATA.pg_stat_wal_sum.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        wal_events
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        wal_events
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+    let format_value = |value: f64| {
+        if show_bytes {
+            human_bytes(value)
+        } else {
+            format!("{:.3}", value)
+        }
+    };
+    let high_value = series
+        .values()
+        .flat_map(|v| v.iter())
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .map(|(_, value)| *value)
+        .max_by(|a, b| a.partial_cmp(b).unwrap())
+        .unwrap_or_default()
+        .max(1_f64)
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(caption, (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc(y_description)
+        .y_label_formatter(&|value| format_value(*value))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            wal_events
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:40} {:>10} {:>10} {:>10}",
+            "", "min", "max", "last"
+        ));
+
+    for (color_number, (name, values)) in series.iter().enumerate() {
+        let values: Vec<(DateTime<Local>, f64)> = values
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+            .cloned()
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        let min_value = values
+            .iter()
+            .map(|(_, value)| *value)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let max_value = values
+            .iter()
+            .map(|(_, value)| *value)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or_default();
+        let last_value = values.last().map_or(0_f64, |(_, value)| *value);
+        contextarea
+            .draw_series(LineSeries::new(
+                values,
+                Palette99::pick(color_number).stroke_width(2),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:40} {:>10} {:>10} {:>10}",
+                name,
+                format_value(min_value),
+                format_value(max_value),
+                format_value(last_value)
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(color_number).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
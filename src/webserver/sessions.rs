@@ -0,0 +1,515 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{BROWN, GREEN, GREY_400, ORANGE, PURPLE, RED};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound::Included;
+
+use crate::processor::{PgStatActivity, Schedule};
+use crate::webserver::collection_gaps::{collection_gap_starts, draw_collection_gaps, gap_between};
+use crate::webserver::settings::{draw_settings_changes, setting_value_at, setting_values};
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the states of pg_stat_activity, in the order in which they are stacked.
+static STATES: [(&str, RGBColor); 6] = [
+    ("active", GREEN),
+    ("idle in transaction", ORANGE),
+    ("idle in transaction (aborted)", RED),
+    ("fastpath function call", PURPLE),
+    ("disabled", BROWN),
+    ("idle", GREY_400),
+];
+
+// the number of idle in transaction sessions in the table.
+static TOP_IDLE_IN_TRANSACTION: usize = 10;
+
+type SessionCounts = Vec<(DateTime<Local>, BTreeMap<String, usize>)>;
+
+// the client backends are the sessions that count against max_connections.
+fn client_backend_state(pg_stat_activity: &PgStatActivity) -> Option<String> {
+    if pg_stat_activity.backend_type.as_deref() == Some("client backend") {
+        pg_stat_activity.state.clone()
+    } else {
+        None
+    }
+}
+
+fn backend_type(pg_stat_activity: &PgStatActivity) -> Option<String> {
+    Some(
+        pg_stat_activity
+            .backend_type
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string()),
+    )
+}
+
+pub fn activity_time_range(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> (DateTime<Local>, DateTime<Local>) {
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+    (
+        start_time.unwrap_or_else(|| {
+            pg_stat_activity
+                .front()
+                .map(|(timestamp, _)| *timestamp)
+                .unwrap_or_default()
+        }),
+        end_time.unwrap_or_else(|| {
+            pg_stat_activity
+                .back()
+                .map(|(timestamp, _)| *timestamp)
+                .unwrap_or_default()
+        }),
+    )
+}
+
+// the number of sessions per key at every sample of pg_stat_activity in the time range, with
+// empty samples at both sides of a gap, so the areas drop to zero. the sessions for which key
+// returns None are not counted.
+fn session_counts(
+    key: impl Fn(&PgStatActivity) -> Option<String>,
+    database: Option<&str>,
+    start_time: DateTime<Local>,
+    end_time: DateTime<Local>,
+) -> SessionCounts {
+    let pg_stat_activity = executor::block_on(DATA.pg_stat_activity.read());
+    let gap_starts = collection_gap_starts(Schedule::Activity);
+    let mut session_counts: SessionCounts = Vec::new();
+    let mut previous_timestamp: Option<DateTime<Local>> = None;
+    for (timestamp, per_sample_vector) in pg_stat_activity
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+    {
+        if let Some((after_previous, before_current)) =
+            previous_timestamp.and_then(|previous| gap_between(&gap_starts, previous, *timestamp))
+        {
+            session_counts.push((after_previous, BTreeMap::new()));
+            session_counts.push((before_current, BTreeMap::new()));
+        }
+        previous_timestamp = Some(*timestamp);
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for key in per_sample_vector
+            .iter()
+            .filter(|r| database.is_none_or(|d| r.datname.as_deref() == Some(d)))
+            .filter_map(&key)
+        {
+            *counts.entry(key).or_default() += 1;
+        }
+        session_counts.push((*timestamp, counts));
+    }
+    session_counts
+}
+
+// the average and maximum number of sessions of a key.
+fn average_and_maximum(session_counts: &SessionCounts, key: &str) -> (f64, usize) {
+    let counts: Vec<usize> = session_counts
+        .iter()
+        .map(|(_, r)| r.get(key).copied().unwrap_or_default())
+        .collect();
+    (
+        counts.iter().sum::<usize>() as f64 / counts.len().max(1) as f64,
+        counts.iter().max().copied().unwrap_or_default(),
+    )
+}
+
+// the highest number of client backends, with the percentage of max_connections and the time.
+fn saturation_peak(session_counts: &SessionCounts) -> Option<(usize, f64, f64, DateTime<Local>)> {
+    let max_connections = setting_values("max_connections");
+    session_counts
+        .iter()
+        .filter_map(|(timestamp, r)| {
+            setting_value_at(&max_connections, *timestamp).map(|max_connections| {
+                let sessions = r.values().sum::<usize>();
+                (
+                    sessions,
+                    max_connections,
+                    sessions as f64 / max_connections * 100_f64,
+                    *timestamp,
+                )
+            })
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+}
+
+// the client backends by state, stacked, and max_connections.
+pub fn session_state_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let session_counts = session_counts(
+        client_backend_state,
+        database,
+        final_start_time,
+        final_end_time,
+    );
+    let high_value = session_counts
+        .iter()
+        .map(|(_, r)| r.values().sum::<usize>())
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            database.map_or("Client sessions by state".to_string(), |d| {
+                format!("Client sessions by state: {}", d)
+            }),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Client sessions")
+        .y_label_formatter(&|sessions| format!("{:4.0}", sessions))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Activity);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            session_counts
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:30} {:>10} {:>10}", "", "avg", "max"));
+
+    for (state_number, (state, color)) in STATES.iter().enumerate() {
+        let (average, maximum) = average_and_maximum(&session_counts, state);
+        if maximum == 0 {
+            continue;
+        }
+        contextarea
+            .draw_series(AreaSeries::new(
+                session_counts.iter().map(|(timestamp, r)| {
+                    (
+                        *timestamp,
+                        STATES[state_number..]
+                            .iter()
+                            .map(|(state, _)| r.get(*state).copied().unwrap_or_default())
+                            .sum::<usize>() as f64,
+                    )
+                }),
+                0_f64,
+                color,
+            ))
+            .unwrap()
+            .label(format!("{:30} {:>10.2} {:>10}", state, average, maximum))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    // max_connections is only drawn when the number of sessions comes near it, the saturation is
+    // always shown in the legend.
+    let max_connections = setting_values("max_connections");
+    if let Some((_, peak_max_connections, percentage, _)) = saturation_peak(&session_counts) {
+        contextarea
+            .draw_series(LineSeries::new(
+                session_counts
+                    .iter()
+                    .filter_map(|(timestamp, _)| {
+                        setting_value_at(&max_connections, *timestamp)
+                            .map(|value| (*timestamp, value))
+                    })
+                    .filter(|(_, value)| *value <= high_value),
+                RED.stroke_width(2),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:30} {:>10.0} {:>9.1}%",
+                "max_connections", peak_max_connections, percentage
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], RED.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// all backends by backend_type, stacked.
+pub fn backend_type_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let session_counts = session_counts(backend_type, database, final_start_time, final_end_time);
+    let backend_types: Vec<String> = session_counts
+        .iter()
+        .flat_map(|(_, r)| r.keys().cloned())
+        .collect::<std::collections::BTreeSet<String>>()
+        .into_iter()
+        .collect();
+    let last_key = backend_types.last().cloned().unwrap_or_default();
+    let high_value = session_counts
+        .iter()
+        .map(|(_, r)| r.values().sum::<usize>())
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            "Backends by backend type",
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("Backends")
+        .y_label_formatter(&|backends| format!("{:4.0}", backends))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Activity);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            session_counts
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:30} {:>10} {:>10}", "", "avg", "max"));
+
+    for (color_number, backend_type) in backend_types.iter().enumerate() {
+        let (average, maximum) = average_and_maximum(&session_counts, backend_type);
+        contextarea
+            .draw_series(AreaSeries::new(
+                session_counts.iter().map(|(timestamp, r)| {
+                    (
+                        *timestamp,
+                        r.range::<str, _>((
+                            Included(backend_type.as_str()),
+                            Included(last_key.as_str()),
+                        ))
+                        .map(|(_, count)| *count)
+                        .sum::<usize>() as f64,
+                    )
+                }),
+                0_f64,
+                Palette99::pick(color_number),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:30} {:>10.2} {:>10}",
+                backend_type, average, maximum
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(color_number).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the saturation of max_connections, the sessions per state and backend type, and the sessions
+// that were idle in transaction the longest.
+pub fn sessions_html(
+    database: Option<&str>,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let (final_start_time, final_end_time) = activity_time_range(start_time, end_time);
+    let state_counts = session_counts(
+        client_backend_state,
+        database,
+        final_start_time,
+        final_end_time,
+    );
+    let backend_type_counts =
+        session_counts(backend_type, database, final_start_time, final_end_time);
+
+    let mut html_output = match saturation_peak(&state_counts) {
+        Some((sessions, max_connections, percentage, timestamp)) => format!(
+            "<p>The highest number of client sessions was {} of max_connections {} ({:.1}%) at {}.</p>",
+            sessions,
+            max_connections,
+            percentage,
+            timestamp.format("%Y-%m-%dT%H:%M:%S"),
+        ),
+        None => String::new(),
+    };
+
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Client session state</th>
+                <th align=right>Avg</th>
+                <th align=right>Max</th>
+            </tr>"#;
+    for (state, _) in STATES.iter() {
+        let (average, maximum) = average_and_maximum(&state_counts, state);
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            state, average, maximum,
+        )
+        .as_str();
+    }
+    html_output += "</table><br>";
+
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Backend type</th>
+                <th align=right>Avg</th>
+                <th align=right>Max</th>
+            </tr>"#;
+    let backend_types: std::collections::BTreeSet<&String> = backend_type_counts
+        .iter()
+        .flat_map(|(_, r)| r.keys())
+        .collect();
+    for backend_type in backend_types {
+        let (average, maximum) = average_and_maximum(&backend_type_counts, backend_type);
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            backend_type, average, maximum,
+        )
+        .as_str();
+    }
+    html_output += "</table><br>";
+
+    // the sample at which a session was idle in transaction the longest.
+    let mut idle_in_transaction: HashMap<i32, (DateTime<Local>, PgStatActivity)> = HashMap::new();
+    for (timestamp, per_sample_vector) in executor::block_on(DATA.pg_stat_activity.read())
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        for row in per_sample_vector
+            .iter()
+            .filter(|r| {
+                r.state
+                    .as_deref()
+                    .unwrap_or_default()
+                    .starts_with("idle in transaction")
+            })
+            .filter(|r| database.is_none_or(|d| r.datname.as_deref() == Some(d)))
+        {
+            let entry = idle_in_transaction
+                .entry(row.pid)
+                .or_insert_with(|| (*timestamp, row.clone()));
+            if row.state_time >= entry.1.state_time {
+                *entry = (*timestamp, row.clone());
+            }
+        }
+    }
+    if idle_in_transaction.is_empty() {
+        html_output += "<p>No sessions idle in transaction found in the selected time range.</p>";
+        return html_output;
+    }
+    let mut idle_in_transaction: Vec<(DateTime<Local>, PgStatActivity)> =
+        idle_in_transaction.into_values().collect();
+    idle_in_transaction.sort_by_key(|(_, r)| std::cmp::Reverse(r.state_time));
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Time</th>
+                <th align=right>Pid</th>
+                <th>Database</th>
+                <th>User</th>
+                <th>Application</th>
+                <th>Client</th>
+                <th>State</th>
+                <th align=right>State seconds</th>
+                <th align=right>Transaction seconds</th>
+                <th>Last query</th>
+            </tr>"#;
+    for (timestamp, row) in idle_in_transaction.iter().take(TOP_IDLE_IN_TRANSACTION) {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+            </tr>"#,
+            timestamp.format("%Y-%m-%dT%H:%M:%S"),
+            row.pid,
+            row.datname.as_deref().unwrap_or_default(),
+            row.usename.as_deref().unwrap_or_default(),
+            row.application_name.as_deref().unwrap_or_default(),
+            row.client_addr.as_deref().unwrap_or_default(),
+            row.state.as_deref().unwrap_or_default(),
+            row.state_time.unwrap_or_default(),
+            row.xact_time.unwrap_or_default(),
+            row.query.as_deref().unwrap_or_default(),
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
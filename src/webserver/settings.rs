@@ -0,0 +1,214 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::coord::ranged1d::Ranged;
+use plotters::coord::types::RangedDateTime;
+use plotters::element::DashedPathElement;
+use plotters::prelude::*;
+use plotters::style::full_palette::ORANGE_900;
+
+use crate::processor::PgSettingsChange;
+use crate::{DATA, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE};
+
+// the number of setting names that are shown at a change, the others are counted.
+static LABEL_SETTINGS: usize = 3;
+
+// draw a vertical line at every change of the settings in the plot, with the names of the
+// changed settings at the top. the changes are drawn after the series, so they are not hidden by
+// a stacked area.
+pub fn draw_settings_changes<DB, Y>(
+    contextarea: &mut ChartContext<DB, Cartesian2d<RangedDateTime<DateTime<Local>>, Y>>,
+) where
+    DB: DrawingBackend,
+    Y: Ranged,
+    Y::ValueType: Clone + 'static,
+{
+    let x_range = contextarea.x_range();
+    let y_range = contextarea.y_range();
+    let changes: Vec<(DateTime<Local>, String)> =
+        executor::block_on(DATA.pg_settings_changes.read())
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= x_range.start && *timestamp <= x_range.end)
+            .map(|(timestamp, changes)| (*timestamp, changes_label(changes)))
+            .collect();
+    if changes.is_empty() {
+        return;
+    }
+    contextarea
+        .draw_series(changes.iter().map(|(timestamp, _)| {
+            DashedPathElement::new(
+                vec![
+                    (*timestamp, y_range.start.clone()),
+                    (*timestamp, y_range.end.clone()),
+                ],
+                6,
+                4,
+                ORANGE_900.stroke_width(2),
+            )
+        }))
+        .unwrap();
+    contextarea
+        .draw_series(changes.iter().map(|(timestamp, label)| {
+            EmptyElement::at((*timestamp, y_range.end.clone()))
+                + Text::new(
+                    label.clone(),
+                    (4, 4),
+                    (LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE)
+                        .into_font()
+                        .color(&ORANGE_900),
+                )
+        }))
+        .unwrap();
+}
+
+// the numeric value of a setting in every snapshot of the settings. the value at a time is the
+// value of the last snapshot before it, or of the first snapshot when the time is before it.
+pub fn setting_values(name: &str) -> Vec<(DateTime<Local>, f64)> {
+    executor::block_on(DATA.pg_settings.read())
+        .iter()
+        .filter_map(|(timestamp, snapshot)| {
+            snapshot
+                .iter()
+                .find(|r| r.name == name)
+                .and_then(|r| r.setting.parse::<f64>().ok())
+                .map(|value| (*timestamp, value))
+        })
+        .collect()
+}
+
+pub fn setting_value_at(values: &[(DateTime<Local>, f64)], time: DateTime<Local>) -> Option<f64> {
+    values
+        .iter()
+        .rev()
+        .find(|(timestamp, _)| *timestamp <= time)
+        .or(values.first())
+        .map(|(_, value)| *value)
+}
+
+fn changes_label(changes: &[PgSettingsChange]) -> String {
+    let mut label = changes
+        .iter()
+        .take(LABEL_SETTINGS)
+        .map(|r| r.name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    if changes.len() > LABEL_SETTINGS {
+        label += format!(" +{}", changes.len() - LABEL_SETTINGS).as_str();
+    }
+    label
+}
+
+fn setting_with_unit(setting: Option<&str>, unit: Option<&str>) -> String {
+    match (setting, unit) {
+        (Some(setting), Some(unit)) => format!("{} {}", setting, unit),
+        (Some(setting), None) => setting.to_string(),
+        (None, _) => "(none)".to_string(),
+    }
+}
+
+fn source_location(sourcefile: Option<&str>, sourceline: Option<i32>) -> String {
+    sourcefile.map_or(String::new(), |r| {
+        format!("{}:{}", r, sourceline.unwrap_or_default())
+    })
+}
+
+// the changes of the settings in the time range, and the settings that are not default at the
+// end of the time range.
+pub fn settings_html(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let start_time = start_time.unwrap_or_default();
+    let end_time = end_time.unwrap_or_else(Local::now);
+
+    let mut html_output = String::new();
+    let pg_settings_changes = executor::block_on(DATA.pg_settings_changes.read());
+    let changes: Vec<&(DateTime<Local>, Vec<PgSettingsChange>)> = pg_settings_changes
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+        .collect();
+    if changes.is_empty() {
+        html_output += "<p>No changes of the settings found in the selected time range.</p>";
+    } else {
+        html_output += r#"<table border=1>
+            <tr>
+                <th>Time</th>
+                <th>Name</th>
+                <th>Old setting</th>
+                <th>New setting</th>
+                <th>Pending restart</th>
+                <th>Source file</th>
+            </tr>"#;
+        for (timestamp, change) in changes
+            .iter()
+            .flat_map(|(timestamp, changes)| changes.iter().map(move |r| (timestamp, r)))
+        {
+            html_output += format!(
+                r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+            </tr>"#,
+                timestamp.format("%Y-%m-%dT%H:%M:%S"),
+                change.name,
+                setting_with_unit(change.old_setting.as_deref(), change.unit.as_deref()),
+                setting_with_unit(change.new_setting.as_deref(), change.unit.as_deref()),
+                if change.pending_restart { "yes" } else { "" },
+                source_location(change.sourcefile.as_deref(), change.sourceline),
+            )
+            .as_str();
+        }
+        html_output += "</table>";
+    }
+    drop(pg_settings_changes);
+
+    let pg_settings = executor::block_on(DATA.pg_settings.read());
+    let Some((timestamp, snapshot)) = pg_settings
+        .iter()
+        .rev()
+        .find(|(timestamp, _)| *timestamp <= end_time)
+    else {
+        return html_output;
+    };
+    html_output += format!(
+        r#"<p>The settings that are not default at {}:</p>
+        <table border=1>
+            <tr>
+                <th>Name</th>
+                <th>Setting</th>
+                <th>Default</th>
+                <th>Source</th>
+                <th>Source file</th>
+                <th>Pending restart</th>
+            </tr>"#,
+        timestamp.format("%Y-%m-%dT%H:%M:%S"),
+    )
+    .as_str();
+    // the client and session settings are the settings of the session of pas.
+    for setting in snapshot
+        .iter()
+        .filter(|r| !["default", "client", "session"].contains(&r.source.as_str()))
+    {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+            </tr>"#,
+            setting.name,
+            setting_with_unit(Some(&setting.setting), setting.unit.as_deref()),
+            setting_with_unit(setting.boot_val.as_deref(), setting.unit.as_deref()),
+            setting.source,
+            source_location(setting.sourcefile.as_deref(), setting.sourceline),
+            if setting.pending_restart { "yes" } else { "" },
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
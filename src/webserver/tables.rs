@@ -0,0 +1,406 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound::Included;
+
+use crate::processor::{PgStatUserIndexesSum, PgStatUserTablesSum, Schedule};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the number of tables that are shown individually, the other tables are added up.
+static TOP_TABLES: usize = 10;
+
+fn table_name(pg_stat_user_tables_sum: &PgStatUserTablesSum) -> String {
+    format!(
+        "{}.{}",
+        pg_stat_user_tables_sum.schemaname, pg_stat_user_tables_sum.relname
+    )
+}
+
+pub fn tables_seq_tup_read<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    tables_plot(
+        multi_backend,
+        backend_number,
+        "Tuples read by sequential scans per table",
+        "Tuples per second",
+        |r| r.seq_tup_read_ps,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn tables_tuples_modified<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    tables_plot(
+        multi_backend,
+        backend_number,
+        "Tuples inserted, updated and deleted per table",
+        "Tuples per second",
+        |r| r.n_tup_ins_ps + r.n_tup_upd_ps + r.n_tup_del_ps,
+        start_time,
+        end_time,
+    );
+}
+
+pub fn tables_heap_blks_read<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    tables_plot(
+        multi_backend,
+        backend_number,
+        "Heap blocks read per table",
+        "Blocks per second",
+        |r| r.heap_blks_read_ps,
+        start_time,
+        end_time,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tables_plot<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    caption: &str,
+    y_description: &str,
+    value: fn(&PgStatUserTablesSum) -> f64,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_stat_user_tables_sum = executor::block_on(DATA.pg_stat_user_tables_sum.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_stat_user_tables_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_stat_user_tables_sum
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+
+    // the tables are ranked by the sum of the value in the time range.
+    let mut table_totals: HashMap<String, f64> = HashMap::new();
+    for (_, per_sample_vector) in pg_stat_user_tables_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        for row in per_sample_vector.iter() {
+            *table_totals.entry(table_name(row)).or_default() += value(row);
+        }
+    }
+    let mut top_tables: Vec<(String, f64)> = table_totals
+        .into_iter()
+        .filter(|(_, total)| *total > 0_f64)
+        .collect();
+    top_tables.sort_by(|(a_name, a), (b_name, b)| b.total_cmp(a).then(a_name.cmp(b_name)));
+    let has_other = top_tables.len() > TOP_TABLES;
+    top_tables.truncate(TOP_TABLES);
+    let rank_of_table: HashMap<&str, usize> = top_tables
+        .iter()
+        .enumerate()
+        .map(|(rank, (name, _))| (name.as_str(), rank))
+        .collect();
+    // the tables that are not in the top are added to the last rank.
+    let other_rank = top_tables.len();
+    let last_rank = if has_other {
+        other_rank
+    } else {
+        other_rank.saturating_sub(1)
+    };
+
+    let mut timestamp_and_tables: Vec<(DateTime<Local>, BTreeMap<usize, f64>)> = Vec::new();
+    let mut high_value = 0_f64;
+    for (timestamp, per_sample_vector) in pg_stat_user_tables_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        let mut current_tables: BTreeMap<usize, f64> =
+            (0..=last_rank).map(|r| (r, 0_f64)).collect();
+        for row in per_sample_vector.iter() {
+            let rank = rank_of_table
+                .get(table_name(row).as_str())
+                .unwrap_or(&other_rank);
+            if *rank <= last_rank {
+                *current_tables.entry(*rank).or_default() += value(row);
+            }
+        }
+        high_value = high_value.max(current_tables.values().sum::<f64>());
+        timestamp_and_tables.push((*timestamp, current_tables));
+    }
+    let high_value = high_value.max(1_f64) * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(caption, (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc(y_description)
+        .y_label_formatter(&|value| format!("{:.0}", value))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            timestamp_and_tables
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!("{:50} {:>10} {:>10}", "", "max", "last"));
+
+    let mut legend: Vec<(usize, String)> = top_tables
+        .iter()
+        .enumerate()
+        .map(|(rank, (name, _))| (rank, name.clone()))
+        .collect();
+    if has_other {
+        legend.push((other_rank, "other".to_string()));
+    }
+    for (rank, name) in legend {
+        let max_value = timestamp_and_tables
+            .iter()
+            .map(|(_, tables)| *tables.get(&rank).unwrap_or(&0_f64))
+            .fold(0_f64, f64::max);
+        let last_value = timestamp_and_tables
+            .last()
+            .map_or(0_f64, |(_, tables)| *tables.get(&rank).unwrap_or(&0_f64));
+        contextarea
+            .draw_series(AreaSeries::new(
+                timestamp_and_tables.iter().map(|(timestamp, tables)| {
+                    (
+                        *timestamp,
+                        tables
+                            .range((Included(rank), Included(last_rank)))
+                            .map(|(_, v)| *v)
+                            .sum::<f64>(),
+                    )
+                }),
+                0_f64,
+                Palette99::pick(rank),
+            ))
+            .unwrap()
+            .label(format!(
+                "{:50} {:>10.2} {:>10.2}",
+                name, max_value, last_value
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new(
+                    [(x - 3, y - 3), (x + 3, y + 3)],
+                    Palette99::pick(rank).filled(),
+                )
+            });
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the rates are averaged over all samples in the time range, a table that is not in the top of a
+// sample was less busy, and counts as zero for that sample.
+pub fn tables_html(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let pg_stat_user_tables_sum = executor::block_on(DATA.pg_stat_user_tables_sum.read());
+    let pg_stat_user_indexes_sum = executor::block_on(DATA.pg_stat_user_indexes_sum.read());
+    let final_start_time = start_time.unwrap_or_default();
+    let final_end_time = end_time.unwrap_or_else(Local::now);
+
+    let mut tables: BTreeMap<String, PgStatUserTablesSum> = BTreeMap::new();
+    let mut table_samples = 0_usize;
+    for (_, per_sample_vector) in pg_stat_user_tables_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        table_samples += 1;
+        for row in per_sample_vector.iter() {
+            let table = tables.entry(table_name(row)).or_default();
+            table.seq_scan_ps += row.seq_scan_ps;
+            table.seq_tup_read_ps += row.seq_tup_read_ps;
+            table.idx_scan_ps += row.idx_scan_ps;
+            table.n_tup_ins_ps += row.n_tup_ins_ps;
+            table.n_tup_upd_ps += row.n_tup_upd_ps;
+            table.n_tup_hot_upd_ps += row.n_tup_hot_upd_ps;
+            table.n_tup_del_ps += row.n_tup_del_ps;
+            table.heap_blks_read_ps += row.heap_blks_read_ps;
+            table.heap_blks_hit_ps += row.heap_blks_hit_ps;
+            table.n_live_tup = row.n_live_tup;
+            table.n_dead_tup = row.n_dead_tup;
+        }
+    }
+    let mut tables: Vec<(String, PgStatUserTablesSum)> = tables.into_iter().collect();
+    tables.sort_by(|(_, a), (_, b)| {
+        (b.seq_tup_read_ps + b.n_tup_ins_ps + b.n_tup_upd_ps + b.n_tup_del_ps)
+            .total_cmp(&(a.seq_tup_read_ps + a.n_tup_ins_ps + a.n_tup_upd_ps + a.n_tup_del_ps))
+    });
+
+    let mut html_output = String::from(
+        r#"<table border=1>
+            <tr>
+                <th>Table</th>
+                <th align=right>Seq scan/s</th>
+                <th align=right>Seq tup read/s</th>
+                <th align=right>Idx scan/s</th>
+                <th align=right>Inserted/s</th>
+                <th align=right>Updated/s</th>
+                <th align=right>HOT updated %</th>
+                <th align=right>Deleted/s</th>
+                <th align=right>Heap blks read/s</th>
+                <th align=right>Heap hit %</th>
+                <th align=right>Live tuples</th>
+                <th align=right>Dead tuples</th>
+            </tr>"#,
+    );
+    let samples = table_samples.max(1) as f64;
+    for (name, table) in tables.iter() {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+            </tr>"#,
+            name,
+            table.seq_scan_ps / samples,
+            table.seq_tup_read_ps / samples,
+            table.idx_scan_ps / samples,
+            table.n_tup_ins_ps / samples,
+            table.n_tup_upd_ps / samples,
+            if table.n_tup_upd_ps > 0_f64 {
+                format!(
+                    "{:.2}",
+                    table.n_tup_hot_upd_ps / table.n_tup_upd_ps * 100_f64
+                )
+            } else {
+                String::new()
+            },
+            table.n_tup_del_ps / samples,
+            table.heap_blks_read_ps / samples,
+            if table.heap_blks_read_ps + table.heap_blks_hit_ps > 0_f64 {
+                format!(
+                    "{:.2}",
+                    table.heap_blks_hit_ps / (table.heap_blks_read_ps + table.heap_blks_hit_ps)
+                        * 100_f64
+                )
+            } else {
+                String::new()
+            },
+            table.n_live_tup,
+            table.n_dead_tup,
+        )
+        .as_str();
+    }
+    html_output += "</table><br>";
+
+    let mut indexes: BTreeMap<(String, String), PgStatUserIndexesSum> = BTreeMap::new();
+    let mut index_samples = 0_usize;
+    for (_, per_sample_vector) in pg_stat_user_indexes_sum
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        index_samples += 1;
+        for row in per_sample_vector.iter() {
+            let index = indexes
+                .entry((
+                    format!("{}.{}", row.schemaname, row.relname),
+                    row.indexrelname.clone(),
+                ))
+                .or_default();
+            index.idx_scan_ps += row.idx_scan_ps;
+            index.idx_tup_read_ps += row.idx_tup_read_ps;
+            index.idx_tup_fetch_ps += row.idx_tup_fetch_ps;
+        }
+    }
+    let mut indexes: Vec<((String, String), PgStatUserIndexesSum)> = indexes.into_iter().collect();
+    indexes.sort_by(|(_, a), (_, b)| b.idx_scan_ps.total_cmp(&a.idx_scan_ps));
+
+    html_output += r#"<table border=1>
+            <tr>
+                <th>Table</th>
+                <th>Index</th>
+                <th align=right>Idx scan/s</th>
+                <th align=right>Idx tup read/s</th>
+                <th align=right>Idx tup fetch/s</th>
+            </tr>"#;
+    let samples = index_samples.max(1) as f64;
+    for ((table, index_name), index) in indexes.iter() {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+                <td align=right>{:.2}</td>
+            </tr>"#,
+            table,
+            index_name,
+            index.idx_scan_ps / samples,
+            index.idx_tup_read_ps / samples,
+            index.idx_tup_fetch_ps / samples,
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}
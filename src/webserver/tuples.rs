@@ -1,4 +1,7 @@
-use crate::DATA;
+use crate::processor::Schedule;
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::pg_stat_database_sum;
+use crate::webserver::settings::draw_settings_changes;
 use crate::{
     CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
     LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
@@ -6,19 +9,18 @@ use crate::{
 };
 use chrono::{DateTime, Local};
 use full_palette::ORANGE;
-use futures::executor;
-use plotters::backend::RGBPixel;
 use plotters::chart::SeriesLabelPosition::UpperLeft;
 use plotters::coord::Shift;
 use plotters::prelude::*;
 
-pub fn tuples_processed(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn tuples_processed<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
+    database: Option<&str>,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
 ) {
-    let pg_stat_database = executor::block_on(DATA.pg_stat_database_sum.read());
+    let pg_stat_database = pg_stat_database_sum(database);
     let final_start_time = if let Some(final_start_time) = start_time {
         final_start_time
     } else {
@@ -63,7 +65,10 @@ pub fn tuples_processed(
         .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
         .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
         .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
-        .caption("Tuples", (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE))
+        .caption(
+            database.map_or("Tuples".to_string(), |d| format!("Tuples: {}", d)),
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
         .build_cartesian_2d(final_start_time..final_end_time, low_value..high_value)
         .unwrap();
     contextarea
@@ -76,6 +81,7 @@ pub fn tuples_processed(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // This is a dummy plot for the sole intention to write a header in the legend.
     contextarea
@@ -283,6 +289,7 @@ pub fn tuples_processed(
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLACK.filled()));
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
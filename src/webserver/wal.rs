@@ -1,3 +1,6 @@
+use crate::processor::Schedule;
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
 use crate::DATA;
 use crate::{
     CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
@@ -8,13 +11,12 @@ use chrono::{DateTime, Local};
 use full_palette::{GREEN_800, LIGHTBLUE, RED_300};
 use futures::executor;
 use human_bytes::human_bytes;
-use plotters::backend::RGBPixel;
 use plotters::chart::SeriesLabelPosition::UpperLeft;
 use plotters::coord::Shift;
 use plotters::prelude::*;
 
-pub fn wal_io_times(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn wal_io_times<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
@@ -58,25 +60,13 @@ pub fn wal_io_times(
     let high_value_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| {
-            if w.wal_buffers_full_ps + w.wal_write_ps == 0_f64 {
-                0_f64
-            } else {
-                w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps)
-            }
-        })
+        .filter_map(|(_, w)| w.write_latency())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let high_value_sync = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| {
-            if w.wal_sync_ps == 0_f64 {
-                0_f64
-            } else {
-                w.wal_sync_time_ps / w.wal_sync_ps
-            }
-        })
+        .filter_map(|(_, w)| w.sync_latency())
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let high_value = high_value_write.max(high_value_sync) * 1.1_f64;
@@ -101,6 +91,7 @@ pub fn wal_io_times(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // checkpoints timed
     contextarea
@@ -153,7 +144,7 @@ pub fn wal_io_times(
             wal_events
                 .iter()
                 .take(1)
-                .map(|(timestamp, w)| (*timestamp, w.wal_records_ps)),
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
             ShapeStyle {
                 color: TRANSPARENT,
                 filled: false,
@@ -166,107 +157,96 @@ pub fn wal_io_times(
             "", "min", "max", "last"
         ));
 
+    // the write and sync statistics are not in pg_stat_wal before version 14 and since version 18.
     let min_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| {
-            w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-        })
-        .map(|(_, w)| w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps))
+        .filter_map(|(_, w)| w.write_latency())
+        .filter(|r| *r > 0_f64)
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let max_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| {
-            w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-        })
-        .map(|(_, w)| w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps))
+        .filter_map(|(_, w)| w.write_latency())
+        .filter(|r| *r > 0_f64)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| {
-                    w.wal_buffers_full_ps + w.wal_write_ps > 0_f64 && w.wal_write_time_ps > 0_f64
-                })
-                .map(|(timestamp, w)| {
-                    Circle::new(
-                        (
-                            *timestamp,
-                            w.wal_write_time_ps / (w.wal_buffers_full_ps + w.wal_write_ps),
-                        ),
-                        4,
-                        BLACK,
-                    )
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.3} {:10.3} {:10.3} ms",
-            "Wal write",
-            min_write,
-            max_write,
-            wal_events.back().map_or(0_f64, |(_, r)| {
-                if r.wal_buffers_full_ps + r.wal_write_ps == 0_f64 {
-                    0_f64
-                } else {
-                    r.wal_write_time_ps / (r.wal_buffers_full_ps + r.wal_write_ps)
-                }
-            },)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 4, BLACK.filled()));
+    if wal_events
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .any(|(_, w)| w.wal_write_ps.is_some())
+    {
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| w.write_latency().map(|r| (*timestamp, r)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((timestamp, r), 4, BLACK)),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.3} {:10.3} {:10.3} ms",
+                "Wal write",
+                min_write,
+                max_write,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.write_latency())
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 4, BLACK.filled()));
+    }
 
     let min_sync = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_time_ps / w.wal_sync_ps)
+        .filter_map(|(_, w)| w.sync_latency())
+        .filter(|r| *r > 0_f64)
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let max_sync = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-        .map(|(_, w)| w.wal_sync_time_ps / w.wal_sync_ps)
+        .filter_map(|(_, w)| w.sync_latency())
+        .filter(|r| *r > 0_f64)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| w.wal_sync_ps > 0_f64 && w.wal_sync_time_ps > 0_f64)
-                .map(|(timestamp, w)| {
-                    Circle::new(
-                        (*timestamp, w.wal_sync_time_ps / w.wal_sync_ps),
-                        3,
-                        LIGHTBLUE,
-                    )
-                }),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:10.3} {:10.3} {:10.3} ms",
-            "Wal sync",
-            min_sync,
-            max_sync,
-            wal_events.back().map_or(0_f64, |(_, r)| {
-                if r.wal_sync_ps == 0_f64 {
-                    0_f64
-                } else {
-                    r.wal_sync_time_ps / r.wal_sync_ps
-                }
-            },)
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 3, LIGHTBLUE.filled()));
+    if wal_events
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .any(|(_, w)| w.wal_sync_ps.is_some())
+    {
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| w.sync_latency().map(|r| (*timestamp, r)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((timestamp, r), 3, LIGHTBLUE)),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:10.3} {:10.3} {:10.3} ms",
+                "Wal sync",
+                min_sync,
+                max_sync,
+                wal_events
+                    .back()
+                    .and_then(|(_, r)| r.sync_latency())
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 3, LIGHTBLUE.filled()));
+    }
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -276,8 +256,8 @@ pub fn wal_io_times(
         .draw()
         .unwrap();
 }
-pub fn wal_size(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn wal_size<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
@@ -320,7 +300,7 @@ pub fn wal_size(
     let high_value_bytes = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| w.wal_bytes_ps)
+        .filter_map(|(_, w)| w.wal_bytes_ps)
         .max_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default()
         * 1.1_f64;
@@ -346,6 +326,7 @@ pub fn wal_size(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
 
     // checkpoints timed
     contextarea
@@ -399,7 +380,7 @@ pub fn wal_size(
             wal_events
                 .iter()
                 .take(1)
-                .map(|(timestamp, w)| (*timestamp, w.wal_records_ps)),
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
             ShapeStyle {
                 color: TRANSPARENT,
                 filled: false,
@@ -412,39 +393,48 @@ pub fn wal_size(
             "", "min", "max", "last"
         ));
     //
+    // pg_stat_wal exists since version 14.
     let min_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .filter(|(_, w)| w.wal_bytes_ps > 0_f64)
-        .map(|(_, w)| w.wal_bytes_ps)
+        .filter_map(|(_, w)| w.wal_bytes_ps)
+        .filter(|r| *r > 0_f64)
         .min_by(|a, b| a.partial_cmp(b).unwrap())
         .unwrap_or_default();
     let max_write = wal_events
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, w)| w.wal_bytes_ps)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap_or_default();
-    contextarea
-        .draw_series(
-            wal_events
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .filter(|(_, w)| w.wal_bytes_ps > 0_f64)
-                .map(|(timestamp, w)| Circle::new((*timestamp, w.wal_bytes_ps), 3, BLACK.filled())),
-        )
-        .unwrap()
-        .label(format!(
-            "{:25} {:>10} {:>10} {:>10}",
-            "Wal size",
-            human_bytes(min_write),
-            human_bytes(max_write),
-            human_bytes(wal_events.back().map_or(0_f64, |(_, r)| r.wal_bytes_ps))
-        ))
-        .legend(move |(x, y)| Circle::new((x, y), 3, BLACK.filled()));
+        .filter_map(|(_, w)| w.wal_bytes_ps)
+        .max_by(|a, b| a.partial_cmp(b).unwrap());
+    if let Some(max_write) = max_write {
+        contextarea
+            .draw_series(
+                wal_events
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, w)| w.wal_bytes_ps.map(|r| (*timestamp, r)))
+                    .filter(|(_, r)| *r > 0_f64)
+                    .map(|(timestamp, r)| Circle::new((timestamp, r), 3, BLACK.filled())),
+            )
+            .unwrap()
+            .label(format!(
+                "{:25} {:>10} {:>10} {:>10}",
+                "Wal size",
+                human_bytes(min_write),
+                human_bytes(max_write),
+                human_bytes(
+                    wal_events
+                        .back()
+                        .and_then(|(_, r)| r.wal_bytes_ps)
+                        .unwrap_or_default()
+                )
+            ))
+            .legend(move |(x, y)| Circle::new((x, y), 3, BLACK.filled()));
+    }
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -1,3 +1,6 @@
+use crate::processor::Schedule;
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
 use crate::DATA;
 use crate::{
     CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
@@ -6,7 +9,6 @@ use crate::{
 };
 use chrono::{DateTime, Local};
 use futures::executor;
-use plotters::backend::RGBPixel;
 use plotters::chart::SeriesLabelPosition::UpperLeft;
 use plotters::coord::Shift;
 use plotters::prelude::*;
@@ -14,8 +16,8 @@ use plotters::style::full_palette::{
     BLUE_200, GREEN_200, GREY, ORANGE, ORANGE_200, PURPLE, PURPLE_200, RED_200,
 };
 
-pub fn xid_age(
-    multi_backend: &mut [DrawingArea<BitMapBackend<RGBPixel>, Shift>],
+pub fn xid_age<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
     backend_number: usize,
     start_time: Option<DateTime<Local>>,
     end_time: Option<DateTime<Local>>,
@@ -75,6 +77,14 @@ pub fn xid_age(
         .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
         .draw()
         .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Settings);
+    // the xid limits are fetched on the settings interval, so there can be no samples yet.
+    if !xid_age
+        .iter()
+        .any(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+    {
+        return;
+    }
 
     // This is a dummy plot for the sole intention to write a header in the legend.
     contextarea
@@ -199,42 +209,46 @@ pub fn xid_age(
                 .map_or(0_f64, |(_, b)| b.autovacuum_multixact_freeze_max_age)
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLUE_200.filled()));
+    // vacuum_multixact_failsafe_age does not exist before version 14.
     let min_vmfa = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, d)| d.vacuum_multixact_failsafe_age)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
+        .filter_map(|(_, d)| d.vacuum_multixact_failsafe_age)
+        .min_by(|a, b| a.partial_cmp(b).unwrap());
     let max_vmfa = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, d)| d.vacuum_multixact_failsafe_age)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    contextarea
-        .draw_series(LineSeries::new(
-            xid_age
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .map(|(timestamp, d)| (*timestamp, d.vacuum_multixact_failsafe_age)),
-            PURPLE_200,
-        ))
-        .unwrap()
-        .label(format!(
-            "{:50} {:>10} {:>10} {:>10}",
-            "vacuum_multixact_failsafe_age",
-            min_vmfa,
-            max_vmfa,
-            xid_age
-                .iter()
-                .last()
-                .map_or(0_f64, |(_, b)| b.vacuum_multixact_failsafe_age)
-        ))
-        .legend(move |(x, y)| {
-            Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], PURPLE_200.filled())
-        });
+        .filter_map(|(_, d)| d.vacuum_multixact_failsafe_age)
+        .max_by(|a, b| a.partial_cmp(b).unwrap());
+    if let (Some(min_vmfa), Some(max_vmfa)) = (min_vmfa, max_vmfa) {
+        contextarea
+            .draw_series(LineSeries::new(
+                xid_age
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, d)| {
+                        d.vacuum_multixact_failsafe_age.map(|r| (*timestamp, r))
+                    }),
+                PURPLE_200,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:50} {:>10} {:>10} {:>10}",
+                "vacuum_multixact_failsafe_age",
+                min_vmfa,
+                max_vmfa,
+                xid_age
+                    .iter()
+                    .last()
+                    .and_then(|(_, b)| b.vacuum_multixact_failsafe_age)
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], PURPLE_200.filled())
+            });
+    }
     let min_mxid_xid = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
@@ -375,40 +389,44 @@ pub fn xid_age(
                 .map_or(0_f64, |(_, b)| b.autovacuum_freeze_max_age)
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], BLUE.filled()));
+    // vacuum_failsafe_age does not exist before version 14.
     let min_vfa = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, d)| d.vacuum_failsafe_age)
-        .min_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
+        .filter_map(|(_, d)| d.vacuum_failsafe_age)
+        .min_by(|a, b| a.partial_cmp(b).unwrap());
     let max_vfa = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
-        .map(|(_, d)| d.vacuum_failsafe_age)
-        .max_by(|a, b| a.partial_cmp(b).unwrap())
-        .unwrap();
-    contextarea
-        .draw_series(LineSeries::new(
-            xid_age
-                .iter()
-                .filter(|(timestamp, _)| {
-                    *timestamp >= final_start_time && *timestamp <= final_end_time
-                })
-                .map(|(timestamp, d)| (*timestamp, d.vacuum_failsafe_age)),
-            PURPLE,
-        ))
-        .unwrap()
-        .label(format!(
-            "{:50} {:>10} {:>10} {:>10}",
-            "vacuum_failsafe_age",
-            min_vfa,
-            max_vfa,
-            xid_age
-                .iter()
-                .last()
-                .map_or(0_f64, |(_, b)| b.vacuum_failsafe_age)
-        ))
-        .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], PURPLE.filled()));
+        .filter_map(|(_, d)| d.vacuum_failsafe_age)
+        .max_by(|a, b| a.partial_cmp(b).unwrap());
+    if let (Some(min_vfa), Some(max_vfa)) = (min_vfa, max_vfa) {
+        contextarea
+            .draw_series(LineSeries::new(
+                xid_age
+                    .iter()
+                    .filter(|(timestamp, _)| {
+                        *timestamp >= final_start_time && *timestamp <= final_end_time
+                    })
+                    .filter_map(|(timestamp, d)| d.vacuum_failsafe_age.map(|r| (*timestamp, r))),
+                PURPLE,
+            ))
+            .unwrap()
+            .label(format!(
+                "{:50} {:>10} {:>10} {:>10}",
+                "vacuum_failsafe_age",
+                min_vfa,
+                max_vfa,
+                xid_age
+                    .iter()
+                    .last()
+                    .and_then(|(_, b)| b.vacuum_failsafe_age)
+                    .unwrap_or_default()
+            ))
+            .legend(move |(x, y)| {
+                Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], PURPLE.filled())
+            });
+    }
     let min_frozen_xid = xid_age
         .iter()
         .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
@@ -489,6 +507,7 @@ pub fn xid_age(
         ))
         .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], RED.filled()));
 
+    draw_settings_changes(&mut contextarea);
     contextarea
         .configure_series_labels()
         .border_style(BLACK)
@@ -0,0 +1,255 @@
+use chrono::{DateTime, Local};
+use futures::executor;
+use plotters::chart::SeriesLabelPosition::UpperLeft;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{BLUE_600, GREEN_800, RED_900};
+use std::collections::HashMap;
+
+use crate::processor::{Schedule, XminHolder};
+use crate::webserver::collection_gaps::draw_collection_gaps;
+use crate::webserver::settings::draw_settings_changes;
+use crate::DATA;
+use crate::{
+    CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE, LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE,
+    LABEL_AREA_SIZE_BOTTOM, LABEL_AREA_SIZE_LEFT, LABEL_AREA_SIZE_RIGHT, MESH_STYLE_FONT,
+    MESH_STYLE_FONT_SIZE,
+};
+
+// the number of holders in the table.
+static TOP_HOLDERS: usize = 20;
+
+static HOLDER_TYPES: [(&str, RGBColor); 3] = [
+    ("session", GREEN_800),
+    ("replication slot", BLUE_600),
+    ("prepared transaction", RED_900),
+];
+
+// a holder that held the vacuum horizon: the first and last sample at which it was the oldest
+// holder, the number of these samples, and the holder at the sample with the highest age.
+struct HorizonHolder {
+    first: DateTime<Local>,
+    last: DateTime<Local>,
+    samples: usize,
+    holder: XminHolder,
+}
+
+// the age of the vacuum horizon, per type of holder: the oldest session, replication slot and
+// prepared transaction at every sample.
+pub fn xmin_horizon<DB: DrawingBackend>(
+    multi_backend: &mut [DrawingArea<DB, Shift>],
+    backend_number: usize,
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) {
+    let pg_xmin_horizon = executor::block_on(DATA.pg_xmin_horizon.read());
+    let final_start_time = if let Some(final_start_time) = start_time {
+        final_start_time
+    } else {
+        pg_xmin_horizon
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .min()
+            .unwrap_or_default()
+    };
+    let final_end_time = if let Some(final_end_time) = end_time {
+        final_end_time
+    } else {
+        pg_xmin_horizon
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .max()
+            .unwrap_or_default()
+    };
+    let samples: Vec<_> = pg_xmin_horizon
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= final_start_time && *timestamp <= final_end_time)
+        .collect();
+    let high_value = samples
+        .iter()
+        .map(|(_, r)| r.horizon_age)
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64
+        * 1.1_f64;
+
+    multi_backend[backend_number].fill(&WHITE).unwrap();
+    let mut contextarea = ChartBuilder::on(&multi_backend[backend_number])
+        .set_label_area_size(LabelAreaPosition::Left, LABEL_AREA_SIZE_LEFT)
+        .set_label_area_size(LabelAreaPosition::Bottom, LABEL_AREA_SIZE_BOTTOM)
+        .set_label_area_size(LabelAreaPosition::Right, LABEL_AREA_SIZE_RIGHT)
+        .caption(
+            "Vacuum horizon age",
+            (CAPTION_STYLE_FONT, CAPTION_STYLE_FONT_SIZE),
+        )
+        .build_cartesian_2d(final_start_time..final_end_time, 0_f64..high_value)
+        .unwrap();
+    contextarea
+        .configure_mesh()
+        .x_labels(6)
+        .x_label_formatter(&|timestamp| timestamp.format("%Y-%m-%dT%H:%M:%S").to_string())
+        .x_desc("Time")
+        .y_desc("XID age of the oldest xmin")
+        .y_label_formatter(&|age| format!("{:.0}", *age))
+        .label_style((MESH_STYLE_FONT, MESH_STYLE_FONT_SIZE))
+        .draw()
+        .unwrap();
+    draw_collection_gaps(&mut contextarea, Schedule::Statistics);
+
+    // This is a dummy plot for the sole intention to write a header in the legend.
+    contextarea
+        .draw_series(LineSeries::new(
+            samples
+                .iter()
+                .take(1)
+                .map(|(timestamp, _)| (*timestamp, 0_f64)),
+            ShapeStyle {
+                color: TRANSPARENT,
+                filled: false,
+                stroke_width: 1,
+            },
+        ))
+        .unwrap()
+        .label(format!(
+            "{:25} {:>12} {:>12} {:>12}",
+            "", "min", "max", "last"
+        ));
+
+    for (holder_type, color) in HOLDER_TYPES {
+        // a holder type without holders at a sample has age 0, so the line drops when the
+        // holder is gone.
+        let values: Vec<(DateTime<Local>, f64)> = samples
+            .iter()
+            .map(|(timestamp, r)| {
+                (
+                    *timestamp,
+                    r.holders
+                        .iter()
+                        .filter(|h| h.holder_type == holder_type)
+                        .map(|h| h.xmin_age)
+                        .max()
+                        .unwrap_or_default() as f64,
+                )
+            })
+            .collect();
+        if values.iter().all(|(_, age)| *age == 0_f64) {
+            continue;
+        }
+        let min_value = values.iter().map(|(_, age)| *age).fold(f64::MAX, f64::min);
+        let max_value = values.iter().map(|(_, age)| *age).fold(0_f64, f64::max);
+        let last_value = values.last().map_or(0_f64, |(_, age)| *age);
+        contextarea
+            .draw_series(LineSeries::new(values, color.stroke_width(2)))
+            .unwrap()
+            .label(format!(
+                "{:25} {:12.0} {:12.0} {:12.0}",
+                holder_type, min_value, max_value, last_value
+            ))
+            .legend(move |(x, y)| Rectangle::new([(x - 3, y - 3), (x + 3, y + 3)], color.filled()));
+    }
+
+    draw_settings_changes(&mut contextarea);
+    contextarea
+        .configure_series_labels()
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.7))
+        .label_font((LABELS_STYLE_FONT, LABELS_STYLE_FONT_SIZE))
+        .position(UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+// the holders that held the vacuum horizon in the time range, which are the oldest holders at the
+// samples, with the highest age first.
+pub fn xmin_horizon_html(
+    start_time: Option<DateTime<Local>>,
+    end_time: Option<DateTime<Local>>,
+) -> String {
+    let start_time = start_time.unwrap_or_default();
+    let end_time = end_time.unwrap_or_else(Local::now);
+
+    let mut holders: HashMap<(String, String, Option<i32>), HorizonHolder> = HashMap::new();
+    for (timestamp, pg_xmin_horizon) in executor::block_on(DATA.pg_xmin_horizon.read())
+        .iter()
+        .filter(|(timestamp, _)| *timestamp >= start_time && *timestamp <= end_time)
+    {
+        let Some(holder) = pg_xmin_horizon.holders.first() else {
+            continue;
+        };
+        let entry = holders
+            .entry((holder.holder_type.clone(), holder.name.clone(), holder.pid))
+            .or_insert_with(|| HorizonHolder {
+                first: *timestamp,
+                last: *timestamp,
+                samples: 0,
+                holder: holder.clone(),
+            });
+        entry.last = *timestamp;
+        entry.samples += 1;
+        if holder.xmin_age >= entry.holder.xmin_age {
+            entry.holder = holder.clone();
+        }
+    }
+    if holders.is_empty() {
+        return "<p>No vacuum horizon found in the selected time range.</p>".to_string();
+    }
+    let mut holders: Vec<HorizonHolder> = holders.into_values().collect();
+    holders.sort_by_key(|r| std::cmp::Reverse(r.holder.xmin_age));
+
+    let mut html_output = String::from(
+        r#"<table border=1>
+            <tr>
+                <th>First</th>
+                <th>Last</th>
+                <th align=right>Samples</th>
+                <th>Holder type</th>
+                <th>Name</th>
+                <th align=right>Pid</th>
+                <th>Database</th>
+                <th>User</th>
+                <th>State</th>
+                <th align=right>Transaction seconds</th>
+                <th align=right>Max age</th>
+                <th>Query</th>
+            </tr>"#,
+    );
+    for HorizonHolder {
+        first,
+        last,
+        samples,
+        holder,
+    } in holders.iter().take(TOP_HOLDERS)
+    {
+        html_output += format!(
+            r#"<tr>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td>{}</td>
+                <td align=right>{}</td>
+                <td align=right>{}</td>
+                <td>{}</td>
+            </tr>"#,
+            first.format("%Y-%m-%dT%H:%M:%S"),
+            last.format("%Y-%m-%dT%H:%M:%S"),
+            samples,
+            holder.holder_type,
+            holder.name,
+            holder.pid.map_or(String::new(), |r| r.to_string()),
+            holder.datname.as_deref().unwrap_or_default(),
+            holder.usename.as_deref().unwrap_or_default(),
+            holder.state.as_deref().unwrap_or_default(),
+            holder.xact_time.map_or(String::new(), |r| r.to_string()),
+            holder.xmin_age,
+            holder.query.as_deref().unwrap_or_default(),
+        )
+        .as_str();
+    }
+    html_output += "</table>";
+    html_output
+}